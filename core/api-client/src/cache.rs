@@ -0,0 +1,84 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A local cache of the effective configuration.
+
+use domain_api::configuration::ConfigurationEffectiveSetResponse;
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// A cached copy of the effective configuration that expires after a maximum
+/// age
+///
+/// Clones share the same cache.
+#[derive(Debug, Clone)]
+pub struct ConfigurationClientCache {
+    max_age: Duration,
+    state: Arc<Mutex<Option<CachedConfiguration>>>,
+}
+
+/// The effective configuration along with when it was loaded
+#[derive(Debug)]
+struct CachedConfiguration {
+    loaded: Instant,
+    effective: Arc<ConfigurationEffectiveSetResponse>,
+}
+
+impl ConfigurationClientCache {
+    /// Create an empty cache
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            state: Default::default(),
+        }
+    }
+
+    /// The age after which the cached configuration is no longer used
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Get the cached configuration if it has not expired.
+    pub fn get(&self) -> Option<Arc<ConfigurationEffectiveSetResponse>> {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .filter(|cached| cached.loaded.elapsed() < self.max_age)
+            .map(|cached| cached.effective.clone())
+    }
+
+    /// Replace the cached configuration.
+    pub fn set(&self, effective: Arc<ConfigurationEffectiveSetResponse>) {
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner) = Some(CachedConfiguration {
+            loaded: Instant::now(),
+            effective,
+        });
+    }
+
+    /// Clear the cached configuration.
+    pub fn invalidate(&self) {
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
@@ -0,0 +1,458 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Requests to the `/configuration` routes.
+
+pub mod groups;
+pub mod keys;
+pub mod types;
+pub mod user;
+
+use crate::{Error, PreludeClient};
+use chrono::{DateTime, SecondsFormat, Utc};
+use domain_api::{
+    configuration::{
+        ConfigurationDiffResponse, ConfigurationEffectiveEntryResponse,
+        ConfigurationEffectiveSetResponse, ConfigurationEntryRequest, ConfigurationEntryResponse,
+        ConfigurationEntrySetResponse, ConfigurationEntryTreeResponse, ConfigurationEventResponse,
+        ConfigurationRollbackRequest, ConfigurationRollbackResponse, ConfigurationValueRequest,
+        ConfigurationValueResponse, CONFIGURATION_KEY_NAME_REGEX,
+    },
+    ErrorWithMessageResponse,
+};
+use reqwest::{Method, RequestBuilder, Response};
+use std::{sync::Arc, time::Duration};
+use url::Url;
+
+/// Query parameters for reading configuration entries
+#[derive(Debug, Default, Clone)]
+pub struct ConfigurationEntriesQuery {
+    /// Whether to return secret values in plaintext instead of redacting them.
+    /// Only clients with an admin token may do this.
+    pub reveal_secrets: bool,
+    /// Only return entries for keys whose names are this dotted prefix or
+    /// start with it followed by a dot.
+    pub prefix: Option<String>,
+    /// Return the entries as they were at this time instead of the current
+    /// ones.
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+impl ConfigurationEntriesQuery {
+    /// Add the query parameters to a request.
+    fn apply(&self, request: RequestBuilder, tree: bool) -> RequestBuilder {
+        let mut query = vec![
+            ("revealSecrets", self.reveal_secrets.to_string()),
+            ("tree", tree.to_string()),
+        ];
+
+        if let Some(prefix) = &self.prefix {
+            query.push(("prefix", prefix.clone()));
+        }
+
+        if let Some(as_of) = &self.as_of {
+            query.push(("asOf", format_timestamp(as_of)));
+        }
+
+        request.query(&query)
+    }
+}
+
+/// A stream of changes to the configuration, as sent by
+/// `GET /configuration/events`
+#[derive(Debug)]
+pub struct ConfigurationEventStream {
+    response: Response,
+    buffer: String,
+}
+
+impl ConfigurationEventStream {
+    /// Wait for the next change.
+    ///
+    /// # Returns
+    ///
+    /// The change, or null if the server closed the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns any HTTP errors. If an event cannot be parsed, an error is
+    /// returned. If the server sends an error event before closing the stream,
+    /// an error is returned.
+    pub async fn next(&mut self) -> Result<Option<ConfigurationEventResponse>, Error> {
+        loop {
+            // Events are separated by blank lines
+            while let Some(end) = self.buffer.find("\n\n") {
+                let event = self.buffer[..end].to_owned();
+
+                self.buffer.drain(..end + 2);
+
+                if let Some(event) = parse_event(&event)? {
+                    return Ok(Some(event));
+                }
+            }
+
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.push_str(
+                    std::str::from_utf8(&chunk)
+                        .map_err(|error| Error::EventStreamInvalid(error.to_string()))?,
+                ),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+impl PreludeClient {
+    /// Get the current configuration entries as `GET /configuration` does.
+    pub async fn get_configuration_entries(
+        &self,
+        query: &ConfigurationEntriesQuery,
+    ) -> Result<ConfigurationEntrySetResponse, Error> {
+        Self::send(query.apply(self.request(Method::GET, "configuration")?, false)).await
+    }
+
+    /// Get the configuration entries nested by key name as
+    /// `GET /configuration?tree=true` does.
+    pub async fn get_configuration_entry_tree(
+        &self,
+        query: &ConfigurationEntriesQuery,
+    ) -> Result<ConfigurationEntryTreeResponse, Error> {
+        Self::send(query.apply(self.request(Method::GET, "configuration")?, true)).await
+    }
+
+    /// Get the effective configuration for the client's user as
+    /// `GET /configuration/effective` does.
+    ///
+    /// This always makes a request, even if the client has a cache.
+    pub async fn get_effective_configuration(
+        &self,
+    ) -> Result<ConfigurationEffectiveSetResponse, Error> {
+        Self::send(self.request(Method::GET, "configuration/effective")?).await
+    }
+
+    /// Get the changes between two points in time as
+    /// `GET /configuration/diff` does.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The start of the range
+    /// * `to` - The end of the range. If this value is null, the range ends at
+    ///          the current time.
+    pub async fn get_configuration_diff(
+        &self,
+        from: DateTime<Utc>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<ConfigurationDiffResponse, Error> {
+        let mut query = vec![("from", format_timestamp(&from))];
+
+        if let Some(to) = &to {
+            query.push(("to", format_timestamp(to)));
+        }
+
+        Self::send(
+            self.request(Method::GET, "configuration/diff")?
+                .query(&query),
+        )
+        .await
+    }
+
+    /// Roll configuration entries back to a point in history as
+    /// `POST /configuration/rollback` does.
+    pub async fn rollback_configuration(
+        &self,
+        request: &ConfigurationRollbackRequest,
+    ) -> Result<ConfigurationRollbackResponse, Error> {
+        let response = Self::send(
+            self.request(Method::POST, "configuration/rollback")?
+                .json(request),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Start streaming changes to the configuration as
+    /// `GET /configuration/events` does.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The dotted key name prefix to receive changes for. If this
+    ///              value is null, changes to all keys are received.
+    pub async fn stream_configuration_events(
+        &self,
+        prefix: Option<&str>,
+    ) -> Result<ConfigurationEventStream, Error> {
+        let mut request = self.request(Method::GET, "configuration/events")?;
+
+        if let Some(prefix) = prefix {
+            request = request.query(&[("prefix", prefix)]);
+        }
+
+        Ok(ConfigurationEventStream {
+            response: Self::send_raw(request).await?,
+            buffer: String::new(),
+        })
+    }
+
+    /// Get the entry of a single configuration key as
+    /// `GET /configuration/<name>` does.
+    pub async fn get_configuration_entry(
+        &self,
+        name: &str,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        Self::send(self.request(Method::GET, &entry_path(name)?)?).await
+    }
+
+    /// Append a global item to the entry of a configuration key as
+    /// `POST /configuration/<name>` does.
+    pub async fn insert_configuration_entry_item(
+        &self,
+        name: &str,
+        value: &ConfigurationValueRequest,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        let response =
+            Self::send(self.request(Method::POST, &entry_path(name)?)?.json(value)).await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Replace the global items of a configuration key as
+    /// `PUT /configuration/<name>` does.
+    pub async fn replace_configuration_entry_items(
+        &self,
+        name: &str,
+        entry: &ConfigurationEntryRequest,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        let response = Self::send(self.request(Method::PUT, &entry_path(name)?)?.json(entry)).await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Deactivate the global items of a configuration key as
+    /// `DELETE /configuration/<name>` does.
+    pub async fn deactivate_configuration_entry_items(
+        &self,
+        name: &str,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        let response = Self::send(self.request(Method::DELETE, &entry_path(name)?)?).await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Get the effective entry of a configuration key for the client's user.
+    ///
+    /// The effective configuration is read through the cache if the client
+    /// has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns any HTTP errors. If there is no active configuration key with
+    /// the given name, an error is returned.
+    pub async fn get_effective_entry(
+        &self,
+        name: &str,
+    ) -> Result<ConfigurationEffectiveEntryResponse, Error> {
+        let effective = match self.cache.as_ref().and_then(|cache| cache.get()) {
+            Some(effective) => effective,
+            None => {
+                let effective = Arc::new(self.get_effective_configuration().await?);
+
+                if let Some(cache) = &self.cache {
+                    cache.set(effective.clone());
+                }
+
+                effective
+            }
+        };
+
+        effective
+            .iter()
+            .find(|entry| entry.key.name == name)
+            .cloned()
+            .ok_or_else(|| Error::ConfigurationKeyNotFoundByName(name.to_owned()))
+    }
+
+    /// Get the effective value of a boolean configuration key.
+    ///
+    /// # Returns
+    ///
+    /// The value, or null if the key has no value.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`PreludeClient::get_effective_entry`]. If the
+    /// value is not a boolean, an error is returned.
+    pub async fn get_bool(&self, name: &str) -> Result<Option<bool>, Error> {
+        self.get_typed(name, "boolean", |value| value.as_boolean)
+            .await
+    }
+
+    /// Get the effective value of an integer configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`].
+    pub async fn get_integer(&self, name: &str) -> Result<Option<i64>, Error> {
+        self.get_typed(name, "integer", |value| value.as_integer)
+            .await
+    }
+
+    /// Get the effective value of a float configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`].
+    pub async fn get_float(&self, name: &str) -> Result<Option<f64>, Error> {
+        self.get_typed(name, "float", |value| value.as_float).await
+    }
+
+    /// Get the effective value of a string configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`].
+    pub async fn get_string(&self, name: &str) -> Result<Option<String>, Error> {
+        self.get_typed(name, "string", |value| value.as_string)
+            .await
+    }
+
+    /// Get the effective value of an enum configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`].
+    pub async fn get_enum(&self, name: &str) -> Result<Option<String>, Error> {
+        self.get_typed(name, "enum", |value| value.as_enum).await
+    }
+
+    /// Get the effective value of a JSON configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`].
+    pub async fn get_json(&self, name: &str) -> Result<Option<serde_json::Value>, Error> {
+        self.get_typed(name, "json", |value| value.as_json).await
+    }
+
+    /// Get the effective value of a duration configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`]. If the value cannot be parsed as a
+    /// duration, an error is returned.
+    pub async fn get_duration(&self, name: &str) -> Result<Option<Duration>, Error> {
+        self.get_typed(name, "duration", |value| value.as_duration)
+            .await?
+            .map(|text| humantime::parse_duration(&text))
+            .transpose()
+            .map_err(Error::from)
+    }
+
+    /// Get the effective value of a URL configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`]. If the value cannot be parsed as a
+    /// URL, an error is returned.
+    pub async fn get_url(&self, name: &str) -> Result<Option<Url>, Error> {
+        self.get_typed(name, "url", |value| value.as_url)
+            .await?
+            .map(|text| Url::parse(&text))
+            .transpose()
+            .map_err(Error::from)
+    }
+
+    /// Get the effective value of a datetime configuration key.
+    ///
+    /// See [`PreludeClient::get_bool`]. If the value cannot be parsed as an
+    /// RFC 3339 timestamp, an error is returned.
+    pub async fn get_datetime(&self, name: &str) -> Result<Option<DateTime<Utc>>, Error> {
+        self.get_typed(name, "datetime", |value| value.as_datetime)
+            .await?
+            .map(|text| DateTime::parse_from_rfc3339(&text))
+            .transpose()
+            .map(|timestamp| timestamp.map(|timestamp| timestamp.with_timezone(&Utc)))
+            .map_err(Error::from)
+    }
+
+    /// Get the effective value of a configuration key as one kind of value.
+    async fn get_typed<T>(
+        &self,
+        name: &str,
+        type_name: &str,
+        extract: impl FnOnce(ConfigurationValueResponse) -> Option<T>,
+    ) -> Result<Option<T>, Error> {
+        let entry = self.get_effective_entry(name).await?;
+
+        if entry.key.configuration_type.name != type_name {
+            return Err(Error::ConfigurationValueTypeMismatch(
+                name.to_owned(),
+                type_name.to_owned(),
+            ));
+        }
+
+        entry
+            .value
+            .map(|value| {
+                extract(value).ok_or_else(|| {
+                    Error::ConfigurationValueTypeMismatch(name.to_owned(), type_name.to_owned())
+                })
+            })
+            .transpose()
+    }
+}
+
+/// Get the path of a configuration entry, relative to the base URL.
+///
+/// # Errors
+///
+/// Returns an error if the name does not match `CONFIGURATION_KEY_NAME_REGEX`.
+pub(crate) fn entry_path(name: &str) -> Result<String, Error> {
+    if !CONFIGURATION_KEY_NAME_REGEX.is_match(name) {
+        return Err(Error::ConfigurationKeyNameInvalid(name.to_owned()));
+    }
+
+    Ok(format!("configuration/{name}"))
+}
+
+/// Format a timestamp the way the server parses it.
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Parse a single server-sent event, skipping events other than changes and
+/// errors.
+fn parse_event(event: &str) -> Result<Option<ConfigurationEventResponse>, Error> {
+    let mut name = None;
+    let mut data = Vec::new();
+
+    // Other fields and comments, which are sent as heartbeats, are skipped
+    for line in event.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = Some(value.trim_start());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+
+    match name {
+        Some("change") if !data.is_empty() => Ok(Some(serde_json::from_str(&data.join("\n"))?)),
+        Some("error") if !data.is_empty() => Err(Error::EventStreamFailed(
+            serde_json::from_str::<ErrorWithMessageResponse>(&data.join("\n"))?.message,
+        )),
+        _ => Ok(None),
+    }
+}
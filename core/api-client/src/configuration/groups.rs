@@ -0,0 +1,173 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Requests to the `/configuration/<name>/group/<group_id>` and
+//! `/configuration/groups` routes.
+
+use super::entry_path;
+use crate::{Error, PreludeClient};
+use domain_api::configuration::{
+    ConfigurationEntryRequest, ConfigurationEntryResponse, ConfigurationGroupResponse,
+    ConfigurationGroupSetResponse, ConfigurationGroupUpdateRequest,
+};
+use reqwest::Method;
+use url::form_urlencoded;
+
+impl PreludeClient {
+    /// Get all groups with active members or a priority as
+    /// `GET /configuration/groups` does.
+    pub async fn get_configuration_groups(&self) -> Result<ConfigurationGroupSetResponse, Error> {
+        Self::send(self.request(Method::GET, "configuration/groups")?).await
+    }
+
+    /// Get a group with its active members as
+    /// `GET /configuration/groups/<group_id>` does.
+    pub async fn get_configuration_group(
+        &self,
+        group_id: &str,
+    ) -> Result<ConfigurationGroupResponse, Error> {
+        Self::send(self.request(Method::GET, &group_path(group_id))?).await
+    }
+
+    /// Set the priority of a group as `PUT /configuration/groups/<group_id>`
+    /// does.
+    pub async fn update_configuration_group(
+        &self,
+        group_id: &str,
+        request: &ConfigurationGroupUpdateRequest,
+    ) -> Result<ConfigurationGroupResponse, Error> {
+        let response = Self::send(
+            self.request(Method::PUT, &group_path(group_id))?
+                .json(request),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Add a user to a group as
+    /// `PUT /configuration/groups/<group_id>/members/<user_id>` does.
+    pub async fn add_configuration_group_member(
+        &self,
+        group_id: &str,
+        user_id: &str,
+    ) -> Result<ConfigurationGroupResponse, Error> {
+        let response =
+            Self::send(self.request(Method::PUT, &group_member_path(group_id, user_id))?).await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Remove a user from a group as
+    /// `DELETE /configuration/groups/<group_id>/members/<user_id>` does.
+    pub async fn remove_configuration_group_member(
+        &self,
+        group_id: &str,
+        user_id: &str,
+    ) -> Result<ConfigurationGroupResponse, Error> {
+        let response =
+            Self::send(self.request(Method::DELETE, &group_member_path(group_id, user_id))?).await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Get the entry of a configuration key with a group's override items as
+    /// `GET /configuration/<name>/group/<group_id>` does.
+    pub async fn get_group_configuration_entry(
+        &self,
+        name: &str,
+        group_id: &str,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        Self::send(self.request(Method::GET, &group_entry_path(name, group_id)?)?).await
+    }
+
+    /// Replace the override items of a group for a configuration key as
+    /// `PUT /configuration/<name>/group/<group_id>` does.
+    pub async fn replace_group_configuration_entry_items(
+        &self,
+        name: &str,
+        group_id: &str,
+        entry: &ConfigurationEntryRequest,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        let response = Self::send(
+            self.request(Method::PUT, &group_entry_path(name, group_id)?)?
+                .json(entry),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Deactivate the override items of a group for a configuration key as
+    /// `DELETE /configuration/<name>/group/<group_id>` does.
+    pub async fn deactivate_group_configuration_entry_items(
+        &self,
+        name: &str,
+        group_id: &str,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        let response =
+            Self::send(self.request(Method::DELETE, &group_entry_path(name, group_id)?)?).await;
+
+        self.invalidate_cache();
+
+        response
+    }
+}
+
+/// Percent-encode an id for use as a single path segment.
+fn encode_segment(segment: &str) -> String {
+    form_urlencoded::byte_serialize(segment.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+/// Get the path of a group, relative to the base URL.
+fn group_path(group_id: &str) -> String {
+    format!("configuration/groups/{}", encode_segment(group_id))
+}
+
+/// Get the path of a group member, relative to the base URL.
+fn group_member_path(group_id: &str, user_id: &str) -> String {
+    format!(
+        "{}/members/{}",
+        group_path(group_id),
+        encode_segment(user_id)
+    )
+}
+
+/// Get the path of the group overrides of a configuration entry, relative to
+/// the base URL.
+fn group_entry_path(name: &str, group_id: &str) -> Result<String, Error> {
+    Ok(format!(
+        "{}/group/{}",
+        entry_path(name)?,
+        encode_segment(group_id)
+    ))
+}
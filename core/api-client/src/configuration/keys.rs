@@ -0,0 +1,82 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Requests to the `/configuration/keys` routes.
+
+use crate::{Error, PreludeClient};
+use domain_api::configuration::{
+    ConfigurationKeyCreateRequest, ConfigurationKeyResponse, ConfigurationKeySetResponse,
+    ConfigurationKeyUpdateRequest,
+};
+use reqwest::Method;
+
+impl PreludeClient {
+    /// Get all active configuration keys as `GET /configuration/keys` does.
+    pub async fn get_configuration_keys(&self) -> Result<ConfigurationKeySetResponse, Error> {
+        Self::send(self.request(Method::GET, "configuration/keys")?).await
+    }
+
+    /// Create a configuration key as `POST /configuration/keys` does.
+    pub async fn create_configuration_key(
+        &self,
+        request: &ConfigurationKeyCreateRequest,
+    ) -> Result<ConfigurationKeyResponse, Error> {
+        let response = Self::send(
+            self.request(Method::POST, "configuration/keys")?
+                .json(request),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Update a configuration key as `PUT /configuration/keys/<id>` does.
+    pub async fn update_configuration_key(
+        &self,
+        id: i32,
+        request: &ConfigurationKeyUpdateRequest,
+    ) -> Result<ConfigurationKeyResponse, Error> {
+        let response = Self::send(
+            self.request(Method::PUT, &format!("configuration/keys/{id}"))?
+                .json(request),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Deactivate a configuration key as `DELETE /configuration/keys/<id>`
+    /// does.
+    pub async fn deactivate_configuration_key(&self, id: i32) -> Result<(), Error> {
+        let response =
+            Self::send_empty(self.request(Method::DELETE, &format!("configuration/keys/{id}"))?)
+                .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+}
@@ -0,0 +1,82 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Requests to the `/configuration/types` routes.
+
+use crate::{Error, PreludeClient};
+use domain_api::configuration::{
+    ConfigurationTypeCreateRequest, ConfigurationTypeResponse, ConfigurationTypeSetResponse,
+    ConfigurationTypeUpdateRequest,
+};
+use reqwest::Method;
+
+impl PreludeClient {
+    /// Get all active configuration types as `GET /configuration/types` does.
+    pub async fn get_configuration_types(&self) -> Result<ConfigurationTypeSetResponse, Error> {
+        Self::send(self.request(Method::GET, "configuration/types")?).await
+    }
+
+    /// Create a configuration type as `POST /configuration/types` does.
+    pub async fn create_configuration_type(
+        &self,
+        request: &ConfigurationTypeCreateRequest,
+    ) -> Result<ConfigurationTypeResponse, Error> {
+        let response = Self::send(
+            self.request(Method::POST, "configuration/types")?
+                .json(request),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Update a configuration type as `PUT /configuration/types/<id>` does.
+    pub async fn update_configuration_type(
+        &self,
+        id: i32,
+        request: &ConfigurationTypeUpdateRequest,
+    ) -> Result<ConfigurationTypeResponse, Error> {
+        let response = Self::send(
+            self.request(Method::PUT, &format!("configuration/types/{id}"))?
+                .json(request),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Deactivate a configuration type as `DELETE /configuration/types/<id>`
+    /// does.
+    pub async fn deactivate_configuration_type(&self, id: i32) -> Result<(), Error> {
+        let response =
+            Self::send_empty(self.request(Method::DELETE, &format!("configuration/types/{id}"))?)
+                .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+}
@@ -0,0 +1,79 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Requests to the `/configuration/<name>/user` routes.
+//!
+//! These routes act on the overrides of the client's user, so the client must
+//! have one.
+
+use super::entry_path;
+use crate::{Error, PreludeClient};
+use domain_api::configuration::{ConfigurationEntryRequest, ConfigurationEntryResponse};
+use reqwest::Method;
+
+impl PreludeClient {
+    /// Get the entry of a configuration key with the client user's override
+    /// items as `GET /configuration/<name>/user` does.
+    pub async fn get_user_configuration_entry(
+        &self,
+        name: &str,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        Self::send(self.request(Method::GET, &user_entry_path(name)?)?).await
+    }
+
+    /// Replace the override items of the client's user for a configuration
+    /// key as `PUT /configuration/<name>/user` does.
+    pub async fn replace_user_configuration_entry_items(
+        &self,
+        name: &str,
+        entry: &ConfigurationEntryRequest,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        let response = Self::send(
+            self.request(Method::PUT, &user_entry_path(name)?)?
+                .json(entry),
+        )
+        .await;
+
+        self.invalidate_cache();
+
+        response
+    }
+
+    /// Deactivate the override items of the client's user for a configuration
+    /// key as `DELETE /configuration/<name>/user` does.
+    pub async fn deactivate_user_configuration_entry_items(
+        &self,
+        name: &str,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        let response = Self::send(self.request(Method::DELETE, &user_entry_path(name)?)?).await;
+
+        self.invalidate_cache();
+
+        response
+    }
+}
+
+/// Get the path of the user overrides of a configuration entry, relative to
+/// the base URL.
+fn user_entry_path(name: &str) -> Result<String, Error> {
+    Ok(format!("{}/user", entry_path(name)?))
+}
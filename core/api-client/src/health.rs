@@ -0,0 +1,61 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Requests to the `/health` routes.
+
+use crate::{Error, PreludeClient};
+use domain_api::health::{HealthMigrationsResponse, HealthReadinessResponse, HealthResponse};
+use reqwest::{Method, StatusCode};
+
+impl PreludeClient {
+    /// Check that the server is running as `GET /health/live` does.
+    pub async fn get_health_liveness(&self) -> Result<HealthResponse, Error> {
+        Self::send(self.request(Method::GET, "health/live")?).await
+    }
+
+    /// Check whether the server can handle requests as `GET /health/ready`
+    /// does.
+    ///
+    /// # Returns
+    ///
+    /// The readiness of the server, including when it is not ready.
+    ///
+    /// # Errors
+    ///
+    /// Returns any HTTP errors other than the server not being ready.
+    pub async fn get_health_readiness(&self) -> Result<HealthReadinessResponse, Error> {
+        let response = self.request(Method::GET, "health/ready")?.send().await?;
+
+        // The server responds with the same body when it is not ready
+        if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+            return Ok(response.json().await?);
+        }
+
+        Ok(Self::check_status(response).await?.json().await?)
+    }
+
+    /// Get the applied and pending database migrations as
+    /// `GET /health/migrations` does.
+    pub async fn get_health_migrations(&self) -> Result<HealthMigrationsResponse, Error> {
+        Self::send(self.request(Method::GET, "health/migrations")?).await
+    }
+}
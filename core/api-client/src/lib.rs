@@ -0,0 +1,267 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A typed client for the Prelude REST API.
+//!
+//! The client covers every route in `server-routes` and uses the objects from
+//! `domain-api` for requests and responses.
+
+pub mod cache;
+pub mod configuration;
+pub mod health;
+pub mod metrics;
+
+use cache::ConfigurationClientCache;
+use domain_api::ErrorWithMessageResponse;
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::{
+    error,
+    fmt::{self, Display},
+    time::Duration,
+};
+use url::Url;
+
+/// The name of the header that the user id is sent in.
+pub const USER_ID_HEADER: &str = "X-User-Id";
+
+/// Error type for this crate
+#[derive(Debug)]
+pub enum Error {
+    /// The server responded with an error status, along with the status code
+    /// and the error message
+    ResponseStatus(u16, String),
+    /// A configuration key is not in the effective configuration
+    ConfigurationKeyNotFoundByName(String),
+    /// A configuration key name does not match the allowed format
+    ConfigurationKeyNameInvalid(String),
+    /// A configuration value does not have the requested type, along with the
+    /// key name and the requested type name
+    ConfigurationValueTypeMismatch(String, String),
+    /// An event sent by the server could not be read
+    EventStreamInvalid(String),
+    /// The server closed the event stream because of an error, along with the
+    /// error message
+    EventStreamFailed(String),
+    /// Wrapper for duration parsing errors
+    HumantimeDurationError(humantime::DurationError),
+    /// Wrapper for datetime parsing errors
+    ChronoParseError(chrono::ParseError),
+    /// Wrapper for URL parsing errors
+    UrlParseError(url::ParseError),
+    /// Wrapper for JSON errors
+    SerdeJsonError(serde_json::Error),
+    /// Wrapper for HTTP client errors
+    ReqwestError(reqwest::Error),
+}
+
+impl From<humantime::DurationError> for Error {
+    fn from(value: humantime::DurationError) -> Self {
+        Self::HumantimeDurationError(value)
+    }
+}
+
+impl From<chrono::ParseError> for Error {
+    fn from(value: chrono::ParseError) -> Self {
+        Self::ChronoParseError(value)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(value: url::ParseError) -> Self {
+        Self::UrlParseError(value)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::SerdeJsonError(value)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        Self::ReqwestError(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ResponseStatus(status, message) => {
+                write!(f, "server responded with status {status}: {message}")
+            }
+            Error::ConfigurationKeyNotFoundByName(name) => {
+                write!(f, "configuration key {name:#?} not found")
+            }
+            Error::ConfigurationKeyNameInvalid(name) => {
+                write!(f, "configuration key name {name:#?} is invalid")
+            }
+            Error::ConfigurationValueTypeMismatch(name, type_name) => {
+                write!(
+                    f,
+                    "configuration value of key {name:#?} is not of type {type_name:#?}"
+                )
+            }
+            Error::EventStreamInvalid(message) => {
+                write!(f, "invalid configuration event stream: {message}")
+            }
+            Error::EventStreamFailed(message) => {
+                write!(f, "configuration event stream failed: {message}")
+            }
+            Error::HumantimeDurationError(error) => write!(f, "{error}"),
+            Error::ChronoParseError(error) => write!(f, "{error}"),
+            Error::UrlParseError(error) => write!(f, "{error}"),
+            Error::SerdeJsonError(error) => write!(f, "{error}"),
+            Error::ReqwestError(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// A client for the Prelude REST API
+#[derive(Debug, Clone)]
+pub struct PreludeClient {
+    http: reqwest::Client,
+    base_url: Url,
+    user_id: Option<String>,
+    admin_token: Option<String>,
+    cache: Option<ConfigurationClientCache>,
+}
+
+impl PreludeClient {
+    /// Create a client without a user or a cache
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The URL that the API is served at, such as
+    ///                `http://localhost:8000`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the base URL cannot be parsed.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let mut base_url = Url::parse(base_url)?;
+
+        // Relative paths are joined after the last slash
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url,
+            user_id: None,
+            admin_token: None,
+            cache: None,
+        })
+    }
+
+    /// Make requests as the given user, so that their configuration overrides
+    /// are read and written.
+    pub fn with_user_id(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_owned());
+        self.cache = self
+            .cache
+            .map(|cache| ConfigurationClientCache::new(cache.max_age()));
+        self
+    }
+
+    /// Send the given admin token with every request, so that configuration
+    /// keys and types can be managed, secret configuration values can be seen
+    /// and configuration can be rolled back.
+    pub fn with_admin_token(mut self, admin_token: &str) -> Self {
+        self.admin_token = Some(admin_token.to_owned());
+        self
+    }
+
+    /// Cache the effective configuration that the typed getters read for up to
+    /// the given age.
+    ///
+    /// Writes made through this client clear the cache. Writes made by anyone
+    /// else are seen once the cache expires.
+    pub fn with_cache(mut self, max_age: Duration) -> Self {
+        self.cache = Some(ConfigurationClientCache::new(max_age));
+        self
+    }
+
+    /// The user that requests are made as, if any
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    /// Start building a request to a path relative to the base URL.
+    pub(crate) fn request(&self, method: Method, path: &str) -> Result<RequestBuilder, Error> {
+        let mut request = self.http.request(method, self.base_url.join(path)?);
+
+        if let Some(user_id) = &self.user_id {
+            request = request.header(USER_ID_HEADER, user_id);
+        }
+
+        if let Some(admin_token) = &self.admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        Ok(request)
+    }
+
+    /// Send a request and parse the JSON body of a successful response.
+    pub(crate) async fn send<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, Error> {
+        Ok(Self::send_raw(request).await?.json::<T>().await?)
+    }
+
+    /// Send a request, expecting a successful response without a body.
+    pub(crate) async fn send_empty(request: RequestBuilder) -> Result<(), Error> {
+        Self::send_raw(request).await.map(|_| ())
+    }
+
+    /// Send a request, turning error statuses into errors.
+    pub(crate) async fn send_raw(request: RequestBuilder) -> Result<Response, Error> {
+        Self::check_status(request.send().await?).await
+    }
+
+    /// Turn a response with an error status into an error.
+    pub(crate) async fn check_status(response: Response) -> Result<Response, Error> {
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let text = response.text().await?;
+
+        // Fall back on the raw body for errors that are not from the routes
+        let message = serde_json::from_str::<ErrorWithMessageResponse>(&text)
+            .map(|error| error.message)
+            .unwrap_or(text);
+
+        Err(Error::ResponseStatus(status.as_u16(), message))
+    }
+
+    /// Clear the local cache after a write.
+    pub(crate) fn invalidate_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate();
+        }
+    }
+}
@@ -0,0 +1,582 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use api_client::{configuration::ConfigurationEntriesQuery, Error, PreludeClient};
+use chrono::Utc;
+use config_env::Configuration;
+use db::{
+    seeding::{
+        insert_configuration_entry, insert_configuration_key_reference,
+        insert_configuration_type_reference,
+    },
+    testing::initialize_unit_database,
+};
+use domain_api::configuration::{
+    ConfigurationEffectiveSource, ConfigurationEntryRequest, ConfigurationEventKind,
+    ConfigurationGroupUpdateRequest, ConfigurationKeyConstraintsResponse,
+    ConfigurationKeyCreateRequest, ConfigurationKeyUpdateRequest, ConfigurationRollbackRequest,
+    ConfigurationTypeCreateRequest, ConfigurationTypeUpdateRequest, ConfigurationValueResponse,
+};
+use domain_api::health::HealthStatus;
+use rocket::{config::LogLevel, Config, Shutdown};
+use sea_orm::DatabaseConnection;
+use serial_test::serial;
+use std::{
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+/// The admin token that the test server accepts.
+const ADMIN_API_TOKEN: &str = "admin";
+
+/// Launch the server on a free local port.
+///
+/// # Returns
+///
+/// The base URL of the server and a handle to shut it down with.
+async fn launch_server(connection: DatabaseConnection) -> (String, Shutdown) {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("unable to find a free port")
+        .port();
+
+    let configuration = Configuration {
+        admin_api_token: Some(ADMIN_API_TOKEN.to_owned()),
+        ..Configuration::new().expect("unable to load configuration")
+    };
+
+    let rocket = server_routes::rocket(connection, configuration)
+        .configure(Config {
+            port,
+            log_level: LogLevel::Off,
+            ..Config::debug_default()
+        })
+        .ignite()
+        .await
+        .expect("error igniting Rocket instance");
+
+    let shutdown = rocket.shutdown();
+
+    rocket::tokio::spawn(rocket.launch());
+
+    // Wait for the server to accept connections
+    for _ in 0..50 {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            break;
+        }
+
+        rocket::tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    (format!("http://127.0.0.1:{port}"), shutdown)
+}
+
+fn boolean_value(value: bool) -> ConfigurationValueResponse {
+    ConfigurationValueResponse {
+        as_boolean: Some(value),
+        ..Default::default()
+    }
+}
+
+#[rocket::async_test]
+#[serial]
+async fn test_typed_getters() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+    let duration_id =
+        insert_configuration_type_reference(&connection, "duration", "A length of time").await?;
+
+    let system_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "system.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let session_timeout_id = insert_configuration_key_reference(
+        &connection,
+        "session.timeout",
+        "How long sessions last",
+        duration_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "session.warning",
+        "How long before the end of a session to warn the user",
+        duration_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, system_enabled_code_id, 1, None, "true").await?;
+    insert_configuration_entry(&connection, session_timeout_id, 1, None, "1h 30m").await?;
+
+    let (base_url, shutdown) = launch_server(connection).await;
+
+    let client = PreludeClient::new(&base_url).unwrap().with_user_id("user");
+
+    assert_eq!(
+        client.get_bool("system.enabled.code").await.unwrap(),
+        Some(true)
+    );
+    assert_eq!(
+        client.get_duration("session.timeout").await.unwrap(),
+        Some(Duration::from_secs(90 * 60))
+    );
+    assert_eq!(client.get_duration("session.warning").await.unwrap(), None);
+
+    assert!(matches!(
+        client.get_integer("system.enabled.code").await,
+        Err(Error::ConfigurationValueTypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        client.get_bool("system.enabled.missing").await,
+        Err(Error::ConfigurationKeyNotFoundByName(_))
+    ));
+
+    // User overrides are applied for the client's user
+    client
+        .replace_user_configuration_entry_items(
+            "system.enabled.code",
+            &ConfigurationEntryRequest {
+                items: vec![boolean_value(false)],
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.get_bool("system.enabled.code").await.unwrap(),
+        Some(false)
+    );
+
+    let entry = client
+        .get_effective_entry("system.enabled.code")
+        .await
+        .unwrap();
+    assert_eq!(entry.source, ConfigurationEffectiveSource::User);
+
+    // Cached values are kept until the client writes
+    let cached = PreludeClient::new(&base_url)
+        .unwrap()
+        .with_admin_token(ADMIN_API_TOKEN)
+        .with_cache(Duration::from_secs(3600));
+    let other = PreludeClient::new(&base_url)
+        .unwrap()
+        .with_admin_token(ADMIN_API_TOKEN);
+
+    assert_eq!(
+        cached.get_bool("system.enabled.code").await.unwrap(),
+        Some(true)
+    );
+
+    other
+        .replace_configuration_entry_items(
+            "system.enabled.code",
+            &ConfigurationEntryRequest {
+                items: vec![boolean_value(false)],
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        cached.get_bool("system.enabled.code").await.unwrap(),
+        Some(true)
+    );
+
+    cached
+        .deactivate_configuration_entry_items("session.timeout")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        cached.get_bool("system.enabled.code").await.unwrap(),
+        Some(false)
+    );
+    assert_eq!(cached.get_duration("session.timeout").await.unwrap(), None);
+
+    shutdown.notify();
+
+    Ok(())
+}
+
+#[rocket::async_test]
+#[serial]
+async fn test_routes() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let (base_url, shutdown) = launch_server(connection).await;
+
+    let client = PreludeClient::new(&base_url)
+        .unwrap()
+        .with_user_id("user")
+        .with_admin_token(ADMIN_API_TOKEN);
+
+    // Types
+    let boolean = client
+        .create_configuration_type(&ConfigurationTypeCreateRequest {
+            name: "boolean".to_owned(),
+            description: "A true/false value".to_owned(),
+        })
+        .await
+        .unwrap();
+
+    let boolean = client
+        .update_configuration_type(
+            boolean.id,
+            &ConfigurationTypeUpdateRequest {
+                description: "A true or false value".to_owned(),
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.get_configuration_types().await.unwrap(),
+        vec![boolean.clone()]
+    );
+
+    // Keys
+    let key = client
+        .create_configuration_key(&ConfigurationKeyCreateRequest {
+            name: "theme.darkMode".to_owned(),
+            description: "Whether or not to use dark mode".to_owned(),
+            type_id: boolean.id,
+            optional: true,
+            allows_multiple: false,
+            allows_user_override: false,
+            constraints: ConfigurationKeyConstraintsResponse::default(),
+            rollout: None,
+        })
+        .await
+        .unwrap();
+
+    let key = client
+        .update_configuration_key(
+            key.id,
+            &ConfigurationKeyUpdateRequest {
+                description: "Whether or not to use dark mode".to_owned(),
+                type_id: boolean.id,
+                optional: true,
+                allows_multiple: false,
+                allows_user_override: true,
+                constraints: ConfigurationKeyConstraintsResponse::default(),
+                rollout: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.get_configuration_keys().await.unwrap(),
+        vec![key.clone()]
+    );
+
+    // Entries
+    let before = Utc::now();
+
+    rocket::tokio::time::sleep(Duration::from_millis(10)).await;
+
+    client
+        .insert_configuration_entry_item("theme.darkMode", &boolean_value(false))
+        .await
+        .unwrap();
+
+    rocket::tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let after_insert = Utc::now();
+
+    let entry = client
+        .replace_configuration_entry_items(
+            "theme.darkMode",
+            &ConfigurationEntryRequest {
+                items: vec![boolean_value(true)],
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(entry.items_global[0].value.as_boolean, Some(true));
+    assert_eq!(
+        client
+            .get_configuration_entry("theme.darkMode")
+            .await
+            .unwrap(),
+        entry
+    );
+
+    let entry = client
+        .replace_user_configuration_entry_items(
+            "theme.darkMode",
+            &ConfigurationEntryRequest {
+                items: vec![boolean_value(false)],
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client
+            .get_user_configuration_entry("theme.darkMode")
+            .await
+            .unwrap(),
+        entry
+    );
+    assert_eq!(
+        client
+            .get_configuration_entries(&ConfigurationEntriesQuery::default())
+            .await
+            .unwrap(),
+        vec![entry.clone()]
+    );
+
+    let tree = client
+        .get_configuration_entry_tree(&ConfigurationEntriesQuery {
+            prefix: Some("theme".to_owned()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        tree.children["theme"].children["darkMode"].entry,
+        Some(entry)
+    );
+
+    let effective = client.get_effective_configuration().await.unwrap();
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::User);
+
+    let as_of = client
+        .get_configuration_entries(&ConfigurationEntriesQuery {
+            as_of: Some(after_insert),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(as_of[0].items_global[0].value.as_boolean, Some(false));
+
+    let diff = client.get_configuration_diff(before, None).await.unwrap();
+    assert!(!diff.entries.is_empty());
+
+    let entry = client
+        .deactivate_user_configuration_entry_items("theme.darkMode")
+        .await
+        .unwrap();
+    assert_eq!(entry.user, None);
+
+    let group = client
+        .add_configuration_group_member("beta testers", "user")
+        .await
+        .unwrap();
+    assert_eq!(group.user_ids, vec!["user".to_owned()]);
+    assert_eq!(
+        client.get_configuration_groups().await.unwrap(),
+        vec![group]
+    );
+
+    let group = client
+        .update_configuration_group(
+            "beta testers",
+            &ConfigurationGroupUpdateRequest { priority: 10 },
+        )
+        .await
+        .unwrap();
+    assert_eq!(group.priority, 10);
+    assert_eq!(
+        client
+            .get_configuration_group("beta testers")
+            .await
+            .unwrap(),
+        group
+    );
+
+    let entry = client
+        .replace_group_configuration_entry_items(
+            "theme.darkMode",
+            "beta testers",
+            &ConfigurationEntryRequest {
+                items: vec![boolean_value(false)],
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(entry.groups[0].group_id, "beta testers");
+    assert_eq!(
+        client
+            .get_group_configuration_entry("theme.darkMode", "beta testers")
+            .await
+            .unwrap(),
+        entry
+    );
+
+    let effective = client.get_effective_configuration().await.unwrap();
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::Group);
+
+    let entry = client
+        .deactivate_group_configuration_entry_items("theme.darkMode", "beta testers")
+        .await
+        .unwrap();
+    assert!(entry.groups.is_empty());
+
+    let group = client
+        .remove_configuration_group_member("beta testers", "user")
+        .await
+        .unwrap();
+    assert!(group.user_ids.is_empty());
+    assert_eq!(
+        client
+            .get_configuration_group("beta testers")
+            .await
+            .unwrap(),
+        group
+    );
+
+    let rollback = client
+        .rollback_configuration(&ConfigurationRollbackRequest {
+            audit_id: None,
+            timestamp: Some(after_insert.to_rfc3339()),
+            prefix: None,
+        })
+        .await
+        .unwrap();
+    assert!(!rollback.entries.is_empty());
+
+    let entry = client
+        .deactivate_configuration_entry_items("theme.darkMode")
+        .await
+        .unwrap();
+    assert!(entry.items_global.is_empty());
+
+    // Errors are reported with the status and message of the response
+    assert!(matches!(
+        client.get_configuration_entry("theme.missing").await,
+        Err(Error::ResponseStatus(404, _))
+    ));
+    assert!(matches!(
+        client.get_configuration_entry("theme/darkMode").await,
+        Err(Error::ConfigurationKeyNameInvalid(_))
+    ));
+
+    client.deactivate_configuration_key(key.id).await.unwrap();
+    client
+        .deactivate_configuration_type(boolean.id)
+        .await
+        .unwrap();
+
+    assert!(client.get_configuration_keys().await.unwrap().is_empty());
+    assert!(client.get_configuration_types().await.unwrap().is_empty());
+
+    // Health
+    assert_eq!(
+        client.get_health_liveness().await.unwrap().status,
+        HealthStatus::Up
+    );
+    assert_eq!(
+        client.get_health_readiness().await.unwrap().status,
+        HealthStatus::Up
+    );
+    assert!(client.get_health_migrations().await.unwrap().up_to_date);
+
+    // Metrics
+    assert!(client
+        .get_metrics()
+        .await
+        .unwrap()
+        .contains("prelude_http_requests_total"));
+
+    shutdown.notify();
+
+    Ok(())
+}
+
+#[rocket::async_test]
+#[serial]
+async fn test_events() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    let (base_url, shutdown) = launch_server(connection).await;
+
+    let client = PreludeClient::new(&base_url)
+        .unwrap()
+        .with_admin_token(ADMIN_API_TOKEN);
+
+    let mut events = client
+        .stream_configuration_events(Some("system"))
+        .await
+        .unwrap();
+
+    client
+        .insert_configuration_entry_item("system.enabled.code", &boolean_value(true))
+        .await
+        .unwrap();
+
+    let event = rocket::tokio::time::timeout(Duration::from_secs(5), events.next())
+        .await
+        .expect("timed out waiting for an event")
+        .unwrap()
+        .expect("event stream ended early");
+
+    assert_eq!(event.kind, ConfigurationEventKind::Entry);
+    assert_eq!(event.key_name, "system.enabled.code");
+    assert_eq!(
+        event.entry.unwrap().items_global[0].value.as_boolean,
+        Some(true)
+    );
+
+    assert!(matches!(
+        client
+            .stream_configuration_events(Some("not a prefix"))
+            .await,
+        Err(Error::ResponseStatus(422, _))
+    ));
+
+    shutdown.notify();
+
+    Ok(())
+}
@@ -23,9 +23,11 @@
 //! Configuration loader for environment variables.
 
 use std::{
+    collections::BTreeMap,
     error,
     fmt::{self, Display},
     num::ParseIntError,
+    str::FromStr,
 };
 
 /// Error type for this crate
@@ -36,6 +38,13 @@ pub enum Error {
         /// The errneous key
         key: &'static str,
     },
+    /// Error for when an environment variable has a value that is not allowed
+    ValueInvalid {
+        /// The errneous key
+        key: &'static str,
+        /// The value that is not allowed
+        value: String,
+    },
     /// Wrapper for dotenv errors
     DotEnvError(dotenv::Error),
     /// Wrapper for integer parsing errors
@@ -58,6 +67,9 @@ impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::KeyEmpty { key } => write!(f, "key is empty: {key:#}"),
+            Error::ValueInvalid { key, value } => {
+                write!(f, "value of key {key:#} is invalid: {value:#?}")
+            }
             Error::DotEnvError(err) => write!(f, "{err}"),
             Error::ParseError(err) => write!(f, "{err}"),
         }
@@ -66,6 +78,34 @@ impl Display for Error {
 
 impl error::Error for Error {}
 
+/// The prefix of environment variables that pin configuration keys.
+pub const CONFIGURATION_OVERRIDE_PREFIX: &str = "PRELUDE_CONFIG__";
+
+/// The format that the server writes logs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One JSON object per line, for log collectors
+    #[default]
+    Json,
+    /// Human-readable lines, for local development
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            _ => Err(Error::ValueInvalid {
+                key: "LOG_FORMAT",
+                value: value.to_owned(),
+            }),
+        }
+    }
+}
+
 /// A configuration structure that contains all of the loaded keys from the shell environment and from `.env`.
 #[derive(Debug)]
 pub struct Configuration {
@@ -80,6 +120,28 @@ pub struct Configuration {
 
     /// Loaded from `POSTGRES_PASSWORD`. The password to use to login to PostgreSQL.
     pub postgres_password: String,
+
+    /// Loaded from `CONFIGURATION_SECRET_KEY`. The base64-encoded 256-bit key used to encrypt
+    /// secret configuration values. Optional, but secret values cannot be read or written
+    /// without it.
+    pub configuration_secret_key: Option<String>,
+
+    /// Loaded from `ADMIN_API_TOKEN`. The bearer token that administrators send to manage
+    /// configuration keys, types, global values and groups and to see secret configuration
+    /// values. Optional, but administration routes are disabled without it.
+    pub admin_api_token: Option<String>,
+
+    /// Loaded from every variable that starts with `PRELUDE_CONFIG__`. Values that pin
+    /// configuration keys regardless of the database, by dotted key name. See
+    /// [`parse_configuration_override_name`] for how variable names map onto key names.
+    pub configuration_overrides: BTreeMap<String, String>,
+
+    /// Loaded from `LOG_FORMAT`. Either `json` or `text`. Optional, defaults to `json`.
+    pub log_format: LogFormat,
+
+    /// Loaded from `LOG_FILTER`. Which logs to write, as `tracing` filter directives such as
+    /// `info,db=debug`. Optional, defaults to `info,sqlx::query=warn`.
+    pub log_filter: String,
 }
 
 impl Configuration {
@@ -90,7 +152,8 @@ impl Configuration {
     /// Iff there is any issue with dotenv, an error will be returned. Some keys
     /// are for integers; if there is any issue parsing them, an error will be
     /// returned. If an environment variable is empty or undefined, an error will
-    /// be thrown.
+    /// be thrown. If `LOG_FORMAT` is not a known format, an error will be
+    /// returned.
     pub fn new() -> Result<Self, Error> {
         dotenv::dotenv()?;
 
@@ -99,6 +162,15 @@ impl Configuration {
             postgres_port: Self::get_var_safe("POSTGRES_PORT")?.parse::<u32>()?,
             postgres_user: Self::get_var_safe("POSTGRES_USER")?,
             postgres_password: Self::get_var_safe("POSTGRES_PASSWORD")?,
+            configuration_secret_key: Self::get_var_optional("CONFIGURATION_SECRET_KEY"),
+            admin_api_token: Self::get_var_optional("ADMIN_API_TOKEN"),
+            configuration_overrides: Self::get_configuration_overrides(),
+            log_format: Self::get_var_optional("LOG_FORMAT")
+                .map(|format| format.parse::<LogFormat>())
+                .transpose()?
+                .unwrap_or_default(),
+            log_filter: Self::get_var_optional("LOG_FILTER")
+                .unwrap_or_else(|| "info,sqlx::query=warn".to_owned()),
         })
     }
 
@@ -111,6 +183,42 @@ impl Configuration {
 
         Ok(result)
     }
+
+    fn get_var_optional(key: &'static str) -> Option<String> {
+        dotenv::var(key).ok().filter(|result| !result.is_empty())
+    }
+
+    fn get_configuration_overrides() -> BTreeMap<String, String> {
+        dotenv::vars()
+            .filter(|(_, value)| !value.is_empty())
+            .filter_map(|(key, value)| Some((parse_configuration_override_name(&key)?, value)))
+            .collect()
+    }
+}
+
+/// Maps the name of an environment variable onto the dotted name of the configuration key that
+/// it pins.
+///
+/// The prefix `PRELUDE_CONFIG__` is removed and the remaining segments, separated by double
+/// underscores, become the segments of the key name. Their case is kept, so
+/// `PRELUDE_CONFIG__SYSTEM__ENABLED__CODE` maps onto `SYSTEM.ENABLED.CODE`. Key names are matched
+/// without regard to case.
+///
+/// # Returns
+///
+/// The dotted key name, or `None` if the variable does not start with the prefix or has an empty
+/// segment.
+pub fn parse_configuration_override_name(variable: &str) -> Option<String> {
+    let segments = variable
+        .strip_prefix(CONFIGURATION_OVERRIDE_PREFIX)?
+        .split("__")
+        .collect::<Vec<&str>>();
+
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return None;
+    }
+
+    Some(segments.join("."))
 }
 
 #[cfg(test)]
@@ -126,4 +234,55 @@ mod tests {
         assert!(!cfg.postgres_user.is_empty());
         assert!(!cfg.postgres_password.is_empty());
     }
+
+    #[test]
+    fn configuration_override_name() {
+        assert_eq!(
+            parse_configuration_override_name("PRELUDE_CONFIG__SYSTEM__ENABLED__CODE").as_deref(),
+            Some("SYSTEM.ENABLED.CODE")
+        );
+        assert_eq!(
+            parse_configuration_override_name("PRELUDE_CONFIG__theme__darkMode").as_deref(),
+            Some("theme.darkMode")
+        );
+        assert_eq!(
+            parse_configuration_override_name("PRELUDE_CONFIG__SESSION__MAX_AGE").as_deref(),
+            Some("SESSION.MAX_AGE")
+        );
+        assert_eq!(parse_configuration_override_name("PRELUDE_CONFIG__"), None);
+        assert_eq!(
+            parse_configuration_override_name("PRELUDE_CONFIG__A____B"),
+            None
+        );
+        assert_eq!(parse_configuration_override_name("POSTGRES_HOST"), None);
+    }
+
+    #[test]
+    fn log_format() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("TEXT".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert!(matches!(
+            "xml".parse::<LogFormat>(),
+            Err(Error::ValueInvalid {
+                key: "LOG_FORMAT",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn configuration_overrides() {
+        std::env::set_var("PRELUDE_CONFIG__CONFIG_ENV__TEST", "true");
+        std::env::set_var("PRELUDE_CONFIG__CONFIG_ENV__EMPTY", "");
+
+        let cfg = Configuration::new().unwrap();
+
+        assert_eq!(
+            cfg.configuration_overrides
+                .get("CONFIG_ENV.TEST")
+                .map(String::as_str),
+            Some("true")
+        );
+        assert!(!cfg.configuration_overrides.contains_key("CONFIG_ENV.EMPTY"));
+    }
 }
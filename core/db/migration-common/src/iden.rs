@@ -38,8 +38,14 @@ static AUDIT_TABLE_COLUMNS: [&str; 8] = [
     "audit_client_query",
 ];
 
+/// The audit table columns that were added after the first audit tables were
+/// created. Audit tables only have them once the migration that adds them has
+/// run.
+static OPTIONAL_AUDIT_TABLE_COLUMNS: [&str; 1] = ["audit_request_id"];
+
 /// Asserts that the audit table identifier enum contains the same columns as
-/// the source table, plus any standard audit-specific columns.
+/// the source table, plus any standard audit-specific columns. Optional
+/// audit-specific columns like `audit_request_id` are allowed as well.
 ///
 /// # Type arguments
 ///
@@ -112,10 +118,17 @@ pub fn assert_audit_table_iden_valid<
         assert!(audit_table_iden_set.contains(audit_table_iden));
     }
 
+    let optional_column_count = OPTIONAL_AUDIT_TABLE_COLUMNS
+        .iter()
+        .filter(|x| audit_table_iden_set.contains(**x))
+        .count();
+
     // Assert that the audit table contains no extra identifiers
     assert_eq!(
         audit_table_iden_set.len(),
-        count_table_columns::<TableIdenSource>() + AUDIT_TABLE_COLUMNS.len()
+        count_table_columns::<TableIdenSource>()
+            + AUDIT_TABLE_COLUMNS.len()
+            + optional_column_count
     );
 }
 
@@ -360,6 +373,21 @@ mod tests {
         AuditClientQuery,
     }
 
+    #[derive(Iden, EnumIter, PartialEq, Debug)]
+    enum AuditOneColumnWithRequestId {
+        Table,
+        Col0,
+        AuditId,
+        AuditAction,
+        AuditTimestampTransactionStart,
+        AuditTimestampStatementStart,
+        AuditTimestampTrigger,
+        AuditClientHost,
+        AuditClientPort,
+        AuditClientQuery,
+        AuditRequestId,
+    }
+
     #[derive(Iden, EnumIter, PartialEq, Debug)]
     enum SourceTwoColumns {
         Table,
@@ -449,6 +477,11 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_assert_audit_table_iden_valid_one_column_good_with_request_id() {
+        assert_audit_table_iden_valid(SourceOneColumn::Table, AuditOneColumnWithRequestId::Table);
+    }
+
     #[test]
     fn test_assert_audit_table_iden_valid_two_columns_good() {
         assert_audit_table_iden_valid(SourceTwoColumns::Table, AuditTwoColumns::Table);
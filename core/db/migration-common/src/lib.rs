@@ -26,13 +26,16 @@
 
 use iden::assert_audit_table_iden_valid;
 use sea_orm_migration::{prelude::SchemaManager, DbErr};
-use sea_query::{Iden, IntoTableRef, TableCreateStatement};
+use sea_query::{Iden, IntoTableRef, TableAlterStatement, TableCreateStatement};
 use strum::IntoEnumIterator;
 
 pub mod iden;
 pub mod table;
 
-use table::{add_audit_columns, create_audit_trigger, create_table_from_builder, TableKind};
+use table::{
+    add_audit_columns, alter_table_from_builder, create_audit_trigger, create_table_from_builder,
+    replace_audit_trigger, TableKind,
+};
 
 /// Helper function to create an audited table.
 ///
@@ -129,3 +132,83 @@ pub async fn create_audited_table<
 
     Ok(())
 }
+
+/// Helper function to alter an audited table.
+///
+/// The same column changes are applied to both the source and the audit table,
+/// and then the audit trigger is replaced so that it copies the new set of
+/// columns. A builder function looks like this:
+///
+/// ```
+/// use sea_query::{Iden, ColumnDef, TableAlterStatement};
+/// use strum_macros::EnumIter;
+/// use migration_common::table::TableKind;
+///
+/// #[derive(Iden, EnumIter, Clone)]
+/// pub enum ConfigurationKeyReference {
+///     Table,
+///     Pattern,
+/// }
+///
+/// fn builder(
+///     table_kind: TableKind,
+///     table_alter_statement: &mut TableAlterStatement
+/// ) {
+///     table_alter_statement
+///         .add_column(ColumnDef::new(ConfigurationKeyReference::Pattern).string());
+/// }
+/// ```
+///
+/// Just like with `create_audited_table`, columns added to an audit table
+/// should be completely unconstrained.
+///
+/// # Arguments
+///
+/// * `manager` - A schema manager referenced from the SeaORM migration.
+/// * `table_iden_source` - The identifier of the source table. The enum must
+///                         list the columns of the table *after* the change.
+/// * `table_iden_audit` - The identifier of the audit table. The enum must list
+///                        the columns of the table *after* the change.
+/// * `table_builder` - A function that builds the column changes of a table.
+///
+/// # Errors
+///
+/// Returns any database errors.
+///
+/// # Panics
+///
+/// Panics if the audit table identifier enum does not contain the same columns
+/// as the source table, plus any standard audit-specific columns.
+pub async fn alter_audited_table<
+    'schema_manager,
+    TableIdenSource: IntoTableRef + Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+    TableIdenAudit: IntoTableRef + Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+    TableBuilder: Fn(TableKind, &mut TableAlterStatement),
+>(
+    manager: &SchemaManager<'schema_manager>,
+    table_iden_source: TableIdenSource,
+    table_iden_audit: TableIdenAudit,
+    table_builder: &TableBuilder,
+) -> Result<(), DbErr> {
+    assert_audit_table_iden_valid(table_iden_source.clone(), table_iden_audit.clone());
+
+    alter_table_from_builder(
+        manager,
+        table_iden_source.clone(),
+        table_builder,
+        TableKind::Source,
+    )
+    .await?;
+
+    alter_table_from_builder(
+        manager,
+        table_iden_audit.clone(),
+        table_builder,
+        TableKind::Audit,
+    )
+    .await?;
+
+    replace_audit_trigger(manager, table_iden_source, table_iden_audit).await?;
+
+    Ok(())
+}
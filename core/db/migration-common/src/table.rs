@@ -24,9 +24,16 @@
 
 use crate::iden::{find_column_with_name, get_iden_name, iterate_table_columns};
 use sea_orm_migration::{prelude::SchemaManager, sea_orm::ConnectionTrait, DbErr};
-use sea_query::{ColumnDef, Iden, IntoTableRef, Table, TableCreateStatement};
+use sea_query::{ColumnDef, Iden, IntoTableRef, Table, TableAlterStatement, TableCreateStatement};
 use strum::IntoEnumIterator;
 
+/// The PostgreSQL setting that the audit triggers read the id of the request
+/// that made a change from.
+///
+/// It should be set with `set_config(..., true)` so that it only lasts until
+/// the end of the transaction.
+pub const AUDIT_REQUEST_ID_SETTING: &str = "prelude.request_id";
+
 /// An enum to identify whether a table is an audit or source table.
 #[derive(Debug, PartialEq)]
 pub enum TableKind {
@@ -110,6 +117,42 @@ pub async fn create_table_from_builder<
     manager.create_table(source_table).await
 }
 
+/// Alters a table in a database using a table builder function.
+///
+/// This works just like `create_table_from_builder`, except that the builder
+/// function adds columns to a `TableAlterStatement` using `.add_column()` or
+/// removes them using `.drop_column()`.
+///
+/// # Arguments
+///
+/// * `manager` - A schema manager referenced from the SeaORM migration.
+/// * `table_iden` - The table identifier of the table to alter.
+/// * `table_builder` - A function that builds the column changes of a table.
+/// * `table_kind` - An enum to identify whether a table is an audit or source
+///                  table.
+///
+/// # Errors
+///
+/// Returns any database errors.
+pub async fn alter_table_from_builder<
+    'schema_manager,
+    TableIden: IntoTableRef,
+    TableBuilder: Fn(TableKind, &mut TableAlterStatement),
+>(
+    manager: &SchemaManager<'schema_manager>,
+    table_iden: TableIden,
+    table_builder: &TableBuilder,
+    table_kind: TableKind,
+) -> Result<(), DbErr> {
+    let mut table = Table::alter();
+
+    table.table(table_iden);
+
+    table_builder(table_kind, &mut table);
+
+    manager.alter_table(table).await
+}
+
 /// Add audit columns to an existing table.
 ///
 /// This function requires the table to have the following columns:
@@ -129,6 +172,10 @@ pub async fn create_table_from_builder<
 /// * `AuditClientQuery` - The text of the top-level PostgreSQL query using
 ///                        executed.
 ///
+/// The table may also have an `AuditRequestId` column for the id of the
+/// request that made the change, which is read from the
+/// [`AUDIT_REQUEST_ID_SETTING`] setting.
+///
 /// These must all be defined in the table's identifier enum. For example:
 ///
 /// ```
@@ -232,6 +279,10 @@ pub fn add_audit_columns<TableEnum: IntoEnumIterator + Iden + 'static>(
             .string()
             .not_null(),
         );
+
+    if let Some(column) = find_column_with_name::<TableEnum>("audit_request_id") {
+        table_create_statement.col(ColumnDef::new(column).string());
+    }
 }
 
 /// Creates an audit trigger for a table.
@@ -248,7 +299,7 @@ pub fn add_audit_columns<TableEnum: IntoEnumIterator + Iden + 'static>(
 pub async fn create_audit_trigger<
     'schema_manager,
     TableIdenSource: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
-    TableIdenAudit: Iden + PartialEq + Clone,
+    TableIdenAudit: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
 >(
     manager: &SchemaManager<'schema_manager>,
     table_iden_source: TableIdenSource,
@@ -263,9 +314,59 @@ pub async fn create_audit_trigger<
         .map(|_| ())
 }
 
+/// Replaces the audit trigger for a table.
+///
+/// This needs to be called whenever columns are added to or removed from an
+/// audited table, because the trigger function lists every column explicitly.
+///
+/// # Arguments
+///
+/// * `manager` - A schema manager referenced from the SeaORM migration.
+/// * `table_iden_source` - The identifier of the source table with its new set
+///                         of columns.
+/// * `table_iden_audit` - The identifier of the audit table.
+///
+/// # Errors
+///
+/// Returns any database errors.
+pub async fn replace_audit_trigger<
+    'schema_manager,
+    TableIdenSource: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+    TableIdenAudit: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+>(
+    manager: &SchemaManager<'schema_manager>,
+    table_iden_source: TableIdenSource,
+    table_iden_audit: TableIdenAudit,
+) -> Result<(), DbErr> {
+    manager
+        .get_connection()
+        .execute_unprepared(
+            format!(
+                "{}{}",
+                drop_audit_trigger_unprepared(table_iden_source.clone()),
+                create_audit_trigger_unprepared(table_iden_source, table_iden_audit)
+            )
+            .as_str(),
+        )
+        .await
+        .map(|_| ())
+}
+
+pub(crate) fn drop_audit_trigger_unprepared<TableIdenSource: Iden>(
+    table_iden_source: TableIdenSource,
+) -> String {
+    let table_name_source = get_iden_name(&table_iden_source);
+
+    format!(
+        r#"
+            DROP TRIGGER IF EXISTS trigger_audit_{table_name_source} ON {table_name_source};
+        "#,
+    )
+}
+
 pub(crate) fn create_audit_trigger_unprepared<
     TableIdenSource: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
-    TableIdenAudit: Iden + PartialEq + Clone,
+    TableIdenAudit: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
 >(
     table_iden_source: TableIdenSource,
     table_iden_audit: TableIdenAudit,
@@ -284,6 +385,18 @@ pub(crate) fn create_audit_trigger_unprepared<
         .intersperse(", ".to_owned())
         .collect::<String>();
 
+    // Audit tables only record the request id once they have a column for it
+    let (request_id_name, request_id_value) =
+        match find_column_with_name::<TableIdenAudit>("audit_request_id") {
+            Some(_) => (
+                ",\n                        audit_request_id".to_owned(),
+                format!(
+                    ",\n                        NULLIF(current_setting('{AUDIT_REQUEST_ID_SETTING}', true), '')"
+                ),
+            ),
+            None => (String::new(), String::new()),
+        };
+
     format!(
         r#"
             CREATE OR REPLACE FUNCTION function_audit_trigger_{table_name_source}()
@@ -297,7 +410,7 @@ pub(crate) fn create_audit_trigger_unprepared<
                         audit_timestamp_trigger,
                         audit_client_host,
                         audit_client_port,
-                        audit_client_query
+                        audit_client_query{request_id_name}
                     )
                     VALUES (
                         {column_values},
@@ -312,7 +425,7 @@ pub(crate) fn create_audit_trigger_unprepared<
                         clock_timestamp(),
                         inet_client_addr(),
                         inet_client_port(),
-                        current_query()
+                        current_query(){request_id_value}
                     );
 
                     IF TG_OP = 'DELETE' THEN
@@ -330,9 +443,128 @@ pub(crate) fn create_audit_trigger_unprepared<
     )
 }
 
+/// Creates a trigger that sends a notification for every change to a table.
+///
+/// The notification is sent with `pg_notify` after the row is written, so
+/// listeners only receive it once the transaction commits. Its payload is a
+/// JSON object like this:
+///
+/// ```json
+/// { "table": "configuration_entries", "action": "U", "id": 42 }
+/// ```
+///
+/// The action is `'I'` for inserts, `'U'` for updates and `'D'` for deletes,
+/// just like in audit tables.
+///
+/// # Arguments
+///
+/// * `manager` - A schema manager referenced from the SeaORM migration.
+/// * `table_iden_source` - The identifier of the table.
+/// * `channel` - The name of the channel to send notifications on.
+///
+/// # Errors
+///
+/// Returns any database errors.
+///
+/// # Panics
+///
+/// Panics if the table does not have an `id` column.
+pub async fn create_notify_trigger<
+    'schema_manager,
+    TableIdenSource: Iden + IntoEnumIterator + 'static,
+>(
+    manager: &SchemaManager<'schema_manager>,
+    table_iden_source: TableIdenSource,
+    channel: &str,
+) -> Result<(), DbErr> {
+    manager
+        .get_connection()
+        .execute_unprepared(create_notify_trigger_unprepared(table_iden_source, channel).as_str())
+        .await
+        .map(|_| ())
+}
+
+/// Drops the notification trigger of a table along with its function.
+///
+/// # Arguments
+///
+/// * `manager` - A schema manager referenced from the SeaORM migration.
+/// * `table_iden_source` - The identifier of the table.
+///
+/// # Errors
+///
+/// Returns any database errors.
+pub async fn drop_notify_trigger<'schema_manager, TableIdenSource: Iden>(
+    manager: &SchemaManager<'schema_manager>,
+    table_iden_source: TableIdenSource,
+) -> Result<(), DbErr> {
+    manager
+        .get_connection()
+        .execute_unprepared(drop_notify_trigger_unprepared(table_iden_source).as_str())
+        .await
+        .map(|_| ())
+}
+
+pub(crate) fn create_notify_trigger_unprepared<
+    TableIdenSource: Iden + IntoEnumIterator + 'static,
+>(
+    table_iden_source: TableIdenSource,
+    channel: &str,
+) -> String {
+    let table_name_source = get_iden_name(&table_iden_source);
+
+    find_column_with_name::<TableIdenSource>("id")
+        .expect("required column 'id' missing from notified table");
+
+    format!(
+        r#"
+            CREATE OR REPLACE FUNCTION function_notify_trigger_{table_name_source}()
+            RETURNS TRIGGER AS $body$
+                BEGIN
+                    PERFORM pg_notify(
+                        '{channel}',
+                        json_build_object(
+                            'table', TG_TABLE_NAME,
+                            'action', CASE TG_OP
+                                WHEN 'INSERT' THEN 'I'
+                                WHEN 'UPDATE' THEN 'U'
+                                WHEN 'DELETE' THEN 'D'
+                                ELSE '?'
+                            END,
+                            'id', CASE TG_OP WHEN 'DELETE' THEN OLD.id ELSE NEW.id END
+                        )::text
+                    );
+
+                    RETURN NULL;
+                END
+            $body$ LANGUAGE 'plpgsql';
+
+            CREATE TRIGGER trigger_notify_{table_name_source}
+            AFTER INSERT OR UPDATE OR DELETE ON {table_name_source}
+            FOR EACH ROW EXECUTE PROCEDURE function_notify_trigger_{table_name_source}();
+        "#,
+    )
+}
+
+pub(crate) fn drop_notify_trigger_unprepared<TableIdenSource: Iden>(
+    table_iden_source: TableIdenSource,
+) -> String {
+    let table_name_source = get_iden_name(&table_iden_source);
+
+    format!(
+        r#"
+            DROP TRIGGER IF EXISTS trigger_notify_{table_name_source} ON {table_name_source};
+            DROP FUNCTION IF EXISTS function_notify_trigger_{table_name_source}();
+        "#,
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    use super::create_audit_trigger_unprepared;
+    use super::{
+        create_audit_trigger_unprepared, create_notify_trigger_unprepared,
+        drop_audit_trigger_unprepared, drop_notify_trigger_unprepared,
+    };
     use sea_query::Iden;
     use strum_macros::EnumIter;
 
@@ -479,4 +711,147 @@ mod tests {
             create_audit_trigger_unprepared(SourceTwoColumns::Table, AuditTwoColumns::Table)
         );
     }
+
+    #[derive(Iden, EnumIter, PartialEq, Clone, Debug)]
+    enum AuditOneColumnWithRequestId {
+        Table,
+        Col0,
+        AuditId,
+        AuditAction,
+        AuditTimestampTransactionStart,
+        AuditTimestampStatementStart,
+        AuditTimestampTrigger,
+        AuditClientHost,
+        AuditClientPort,
+        AuditClientQuery,
+        AuditRequestId,
+    }
+
+    #[test]
+    fn test_create_audit_trigger_unprepared_with_request_id() {
+        let expected = r#"
+            CREATE OR REPLACE FUNCTION function_audit_trigger_source_one_column()
+            RETURNS TRIGGER AS $body$
+                BEGIN
+                    INSERT INTO audit_one_column_with_request_id (
+                        col0,
+                        audit_action,
+                        audit_timestamp_transaction_start,
+                        audit_timestamp_statement_start,
+                        audit_timestamp_trigger,
+                        audit_client_host,
+                        audit_client_port,
+                        audit_client_query,
+                        audit_request_id
+                    )
+                    VALUES (
+                        CASE TG_OP WHEN 'INSERT' THEN NEW.col0 ELSE OLD.col0 END,
+                        CASE TG_OP
+                            WHEN 'INSERT' THEN 'I'
+                            WHEN 'UPDATE' THEN 'U'
+                            WHEN 'DELETE' THEN 'D'
+                            ELSE '?'
+                        END,
+                        current_timestamp,
+                        statement_timestamp(),
+                        clock_timestamp(),
+                        inet_client_addr(),
+                        inet_client_port(),
+                        current_query(),
+                        NULLIF(current_setting('prelude.request_id', true), '')
+                    );
+
+                    IF TG_OP = 'DELETE' THEN
+                        RETURN OLD;
+                    ELSE
+                        RETURN NEW;
+                    END IF;
+                END
+            $body$ LANGUAGE 'plpgsql';
+
+            CREATE TRIGGER trigger_audit_source_one_column
+            BEFORE INSERT OR UPDATE OR DELETE ON source_one_column
+            FOR EACH ROW EXECUTE PROCEDURE function_audit_trigger_source_one_column();
+        "#;
+
+        assert_eq!(
+            expected,
+            create_audit_trigger_unprepared(
+                SourceOneColumn::Table,
+                AuditOneColumnWithRequestId::Table
+            )
+        );
+    }
+
+    #[test]
+    fn test_drop_audit_trigger_unprepared() {
+        let expected = r#"
+            DROP TRIGGER IF EXISTS trigger_audit_source_one_column ON source_one_column;
+        "#;
+
+        assert_eq!(
+            expected,
+            drop_audit_trigger_unprepared(SourceOneColumn::Table)
+        );
+    }
+
+    #[derive(Iden, EnumIter, PartialEq, Clone, Debug)]
+    enum SourceWithId {
+        Table,
+        Id,
+    }
+
+    #[test]
+    fn test_create_notify_trigger_unprepared() {
+        let expected = r#"
+            CREATE OR REPLACE FUNCTION function_notify_trigger_source_with_id()
+            RETURNS TRIGGER AS $body$
+                BEGIN
+                    PERFORM pg_notify(
+                        'changes',
+                        json_build_object(
+                            'table', TG_TABLE_NAME,
+                            'action', CASE TG_OP
+                                WHEN 'INSERT' THEN 'I'
+                                WHEN 'UPDATE' THEN 'U'
+                                WHEN 'DELETE' THEN 'D'
+                                ELSE '?'
+                            END,
+                            'id', CASE TG_OP WHEN 'DELETE' THEN OLD.id ELSE NEW.id END
+                        )::text
+                    );
+
+                    RETURN NULL;
+                END
+            $body$ LANGUAGE 'plpgsql';
+
+            CREATE TRIGGER trigger_notify_source_with_id
+            AFTER INSERT OR UPDATE OR DELETE ON source_with_id
+            FOR EACH ROW EXECUTE PROCEDURE function_notify_trigger_source_with_id();
+        "#;
+
+        assert_eq!(
+            expected,
+            create_notify_trigger_unprepared(SourceWithId::Table, "changes")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "required column 'id' missing from notified table")]
+    fn test_create_notify_trigger_unprepared_without_id() {
+        create_notify_trigger_unprepared(SourceOneColumn::Table, "changes");
+    }
+
+    #[test]
+    fn test_drop_notify_trigger_unprepared() {
+        let expected = r#"
+            DROP TRIGGER IF EXISTS trigger_notify_source_with_id ON source_with_id;
+            DROP FUNCTION IF EXISTS function_notify_trigger_source_with_id();
+        "#;
+
+        assert_eq!(
+            expected,
+            drop_notify_trigger_unprepared(SourceWithId::Table)
+        );
+    }
 }
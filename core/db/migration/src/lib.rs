@@ -1,10 +1,19 @@
 //! Migration crate for SeaORM
 
-use sea_orm_migration::prelude::*;
+pub use sea_orm_migration::prelude::*;
 
 mod m20230218_120854_create_configuration_type_reference_table;
 mod m20230218_120923_create_configuration_key_reference_table;
 mod m20230219_142203_create_configuration_entries_table;
+mod m20230305_120000_add_configuration_key_reference_constraints;
+mod m20230312_120000_insert_configuration_types;
+mod m20230319_120000_insert_secret_configuration_type;
+mod m20230326_120000_add_configuration_change_notifications;
+mod m20230402_120000_add_configuration_groups;
+mod m20230409_120000_add_configuration_key_reference_rollout;
+mod m20230416_120000_add_audit_request_id;
+mod m20230423_120000_add_configuration_group_priorities;
+mod m20230430_120000_add_configuration_group_member_unique_index;
 
 /// SeaORM migrator
 pub struct Migrator;
@@ -16,6 +25,15 @@ impl MigratorTrait for Migrator {
             Box::new(m20230218_120854_create_configuration_type_reference_table::Migration),
             Box::new(m20230218_120923_create_configuration_key_reference_table::Migration),
             Box::new(m20230219_142203_create_configuration_entries_table::Migration),
+            Box::new(m20230305_120000_add_configuration_key_reference_constraints::Migration),
+            Box::new(m20230312_120000_insert_configuration_types::Migration),
+            Box::new(m20230319_120000_insert_secret_configuration_type::Migration),
+            Box::new(m20230326_120000_add_configuration_change_notifications::Migration),
+            Box::new(m20230402_120000_add_configuration_groups::Migration),
+            Box::new(m20230409_120000_add_configuration_key_reference_rollout::Migration),
+            Box::new(m20230416_120000_add_audit_request_id::Migration),
+            Box::new(m20230423_120000_add_configuration_group_priorities::Migration),
+            Box::new(m20230430_120000_add_configuration_group_member_unique_index::Migration),
         ]
     }
 }
@@ -113,7 +113,7 @@ impl MigrationTrait for Migration {
 
 /// Learn more at https://docs.rs/sea-query#iden
 #[derive(Iden, EnumIter, Clone, PartialEq)]
-enum ConfigurationEntries {
+pub enum ConfigurationEntries {
     Table,
     Id,
     KeyId,
@@ -124,7 +124,7 @@ enum ConfigurationEntries {
 }
 
 #[derive(Iden, EnumIter, Clone, PartialEq)]
-enum ConfigurationEntriesAudit {
+pub enum ConfigurationEntriesAudit {
     Table,
     Id,
     KeyId,
@@ -0,0 +1,114 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::m20230218_120923_create_configuration_key_reference_table as previous;
+use migration_common::{alter_audited_table, table::TableKind};
+use sea_orm_migration::prelude::*;
+use strum_macros::EnumIter;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        alter_audited_table(
+            manager,
+            ConfigurationKeyReference::Table,
+            ConfigurationKeyReferenceAudit::Table,
+            &|_: TableKind, table_alter_statement: &mut TableAlterStatement| {
+                table_alter_statement
+                    .add_column(ColumnDef::new(ConfigurationKeyReference::Minimum).double())
+                    .add_column(ColumnDef::new(ConfigurationKeyReference::Maximum).double())
+                    .add_column(ColumnDef::new(ConfigurationKeyReference::Pattern).string())
+                    .add_column(
+                        ColumnDef::new(ConfigurationKeyReference::AllowedValues)
+                            .array(ColumnType::String(None)),
+                    );
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        alter_audited_table(
+            manager,
+            previous::ConfigurationKeyReference::Table,
+            previous::ConfigurationKeyReferenceAudit::Table,
+            &|_: TableKind, table_alter_statement: &mut TableAlterStatement| {
+                table_alter_statement
+                    .drop_column(ConfigurationKeyReference::Minimum)
+                    .drop_column(ConfigurationKeyReference::Maximum)
+                    .drop_column(ConfigurationKeyReference::Pattern)
+                    .drop_column(ConfigurationKeyReference::AllowedValues);
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationKeyReference {
+    Table,
+    Id,
+    Name,
+    Description,
+    TypeId,
+    Optional,
+    AllowsMultiple,
+    AllowsUserOverride,
+    DeactivateTimestamp,
+    Minimum,
+    Maximum,
+    Pattern,
+    AllowedValues,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationKeyReferenceAudit {
+    Table,
+    Id,
+    Name,
+    Description,
+    TypeId,
+    Optional,
+    AllowsMultiple,
+    AllowsUserOverride,
+    DeactivateTimestamp,
+    Minimum,
+    Maximum,
+    Pattern,
+    AllowedValues,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+}
@@ -0,0 +1,99 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::{
+    m20230218_120854_create_configuration_type_reference_table::ConfigurationTypeReference,
+    m20230218_120923_create_configuration_key_reference_table::ConfigurationKeyReference,
+};
+use sea_orm_migration::prelude::*;
+
+/// The supported configuration types, as (name, description)
+const CONFIGURATION_TYPES: [(&str, &str); 9] = [
+    ("boolean", "A true/false value"),
+    ("integer", "A signed integer number"),
+    ("float", "A floating-point number"),
+    ("string", "A string value"),
+    ("duration", "A duration like \"1h 30m\""),
+    ("url", "An absolute URL"),
+    ("datetime", "An RFC 3339 timestamp"),
+    ("json", "Any JSON value"),
+    ("enum", "One of a fixed set of values"),
+];
+
+/// The configuration types added by this migration, which are removed again
+/// when it is rolled back
+const NEW_CONFIGURATION_TYPE_NAMES: [&str; 5] = ["duration", "url", "datetime", "json", "enum"];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut insert_statement = Query::insert();
+
+        insert_statement
+            .into_table(ConfigurationTypeReference::Table)
+            .columns([
+                ConfigurationTypeReference::Name,
+                ConfigurationTypeReference::Description,
+            ])
+            .on_conflict(
+                OnConflict::column(ConfigurationTypeReference::Name)
+                    .do_nothing()
+                    .to_owned(),
+            );
+
+        for (name, description) in CONFIGURATION_TYPES {
+            insert_statement.values_panic([name.into(), description.into()]);
+        }
+
+        manager.exec_stmt(insert_statement).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Types that are still used by keys are left in place
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(ConfigurationTypeReference::Table)
+                    .and_where(
+                        Expr::col(ConfigurationTypeReference::Name)
+                            .is_in(NEW_CONFIGURATION_TYPE_NAMES),
+                    )
+                    .and_where(
+                        Expr::col(ConfigurationTypeReference::Id).not_in_subquery(
+                            Query::select()
+                                .column(ConfigurationKeyReference::TypeId)
+                                .from(ConfigurationKeyReference::Table)
+                                .to_owned(),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::{
+    m20230218_120854_create_configuration_type_reference_table::ConfigurationTypeReference,
+    m20230218_120923_create_configuration_key_reference_table::ConfigurationKeyReference,
+};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(ConfigurationTypeReference::Table)
+                    .columns([
+                        ConfigurationTypeReference::Name,
+                        ConfigurationTypeReference::Description,
+                    ])
+                    .values_panic(["secret".into(), "A string that is encrypted at rest".into()])
+                    .on_conflict(
+                        OnConflict::column(ConfigurationTypeReference::Name)
+                            .do_nothing()
+                            .to_owned(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The type is left in place if it is still used by keys
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(ConfigurationTypeReference::Table)
+                    .and_where(Expr::col(ConfigurationTypeReference::Name).eq("secret"))
+                    .and_where(
+                        Expr::col(ConfigurationTypeReference::Id).not_in_subquery(
+                            Query::select()
+                                .column(ConfigurationKeyReference::TypeId)
+                                .from(ConfigurationKeyReference::Table)
+                                .to_owned(),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
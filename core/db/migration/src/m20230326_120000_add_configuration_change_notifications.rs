@@ -0,0 +1,86 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use migration_common::table::{create_notify_trigger, drop_notify_trigger};
+use sea_orm_migration::prelude::*;
+use strum_macros::EnumIter;
+
+/// The channel that changes to the configuration tables are sent on
+pub const CONFIGURATION_CHANGES_CHANNEL: &str = "configuration_changes";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        create_notify_trigger(
+            manager,
+            ConfigurationTypeReference::Table,
+            CONFIGURATION_CHANGES_CHANNEL,
+        )
+        .await?;
+
+        create_notify_trigger(
+            manager,
+            ConfigurationKeyReference::Table,
+            CONFIGURATION_CHANGES_CHANNEL,
+        )
+        .await?;
+
+        create_notify_trigger(
+            manager,
+            ConfigurationEntries::Table,
+            CONFIGURATION_CHANGES_CHANNEL,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_notify_trigger(manager, ConfigurationEntries::Table).await?;
+        drop_notify_trigger(manager, ConfigurationKeyReference::Table).await?;
+        drop_notify_trigger(manager, ConfigurationTypeReference::Table).await?;
+
+        Ok(())
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+enum ConfigurationTypeReference {
+    Table,
+    Id,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+enum ConfigurationKeyReference {
+    Table,
+    Id,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+enum ConfigurationEntries {
+    Table,
+    Id,
+}
@@ -0,0 +1,203 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::{
+    m20230219_142203_create_configuration_entries_table as previous,
+    m20230326_120000_add_configuration_change_notifications::CONFIGURATION_CHANGES_CHANNEL,
+};
+use migration_common::{
+    alter_audited_table, create_audited_table,
+    table::{create_notify_trigger, drop_notify_trigger, TableKind},
+};
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+use strum_macros::EnumIter;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        alter_audited_table(
+            manager,
+            ConfigurationEntries::Table,
+            ConfigurationEntriesAudit::Table,
+            &|_: TableKind, table_alter_statement: &mut TableAlterStatement| {
+                table_alter_statement
+                    .add_column(ColumnDef::new(ConfigurationEntries::GroupId).string());
+            },
+        )
+        .await?;
+
+        // An item is either global, a group override or a user override
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                    ALTER TABLE configuration_entries
+                    ADD CONSTRAINT check_configuration_entries_scope
+                    CHECK (user_id IS NULL OR group_id IS NULL);
+                "#,
+            )
+            .await?;
+
+        create_audited_table(
+            manager,
+            ConfigurationGroupMembers::Table,
+            ConfigurationGroupMembersAudit::Table,
+            &|table_kind, table_create_statement| {
+                table_create_statement
+                    .col(
+                        match (
+                            &table_kind,
+                            ColumnDef::new(ConfigurationGroupMembers::Id).integer(),
+                        ) {
+                            (TableKind::Source, x) => x.not_null().auto_increment().primary_key(),
+                            (TableKind::Audit, x) => x,
+                        },
+                    )
+                    .col(
+                        match (
+                            &table_kind,
+                            ColumnDef::new(ConfigurationGroupMembers::GroupId).string(),
+                        ) {
+                            (TableKind::Source, x) => x.not_null(),
+                            (TableKind::Audit, x) => x,
+                        },
+                    )
+                    .col(
+                        match (
+                            &table_kind,
+                            ColumnDef::new(ConfigurationGroupMembers::UserId).string(),
+                        ) {
+                            (TableKind::Source, x) => x.not_null(),
+                            (TableKind::Audit, x) => x,
+                        },
+                    )
+                    .col(
+                        ColumnDef::new(ConfigurationGroupMembers::DeactivateTimestamp).timestamp(),
+                    );
+            },
+        )
+        .await?;
+
+        create_notify_trigger(
+            manager,
+            ConfigurationGroupMembers::Table,
+            CONFIGURATION_CHANGES_CHANNEL,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_notify_trigger(manager, ConfigurationGroupMembers::Table).await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ConfigurationGroupMembers::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ConfigurationGroupMembersAudit::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        alter_audited_table(
+            manager,
+            previous::ConfigurationEntries::Table,
+            previous::ConfigurationEntriesAudit::Table,
+            &|_: TableKind, table_alter_statement: &mut TableAlterStatement| {
+                table_alter_statement.drop_column(ConfigurationEntries::GroupId);
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationEntries {
+    Table,
+    Id,
+    KeyId,
+    UserId,
+    OrderIndex,
+    Value,
+    DeactivateTimestamp,
+    GroupId,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationEntriesAudit {
+    Table,
+    Id,
+    KeyId,
+    UserId,
+    OrderIndex,
+    Value,
+    DeactivateTimestamp,
+    GroupId,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationGroupMembers {
+    Table,
+    Id,
+    GroupId,
+    UserId,
+    DeactivateTimestamp,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationGroupMembersAudit {
+    Table,
+    Id,
+    GroupId,
+    UserId,
+    DeactivateTimestamp,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+}
@@ -0,0 +1,288 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::{
+    m20230218_120854_create_configuration_type_reference_table as previous_type_reference,
+    m20230402_120000_add_configuration_groups as previous_groups,
+    m20230409_120000_add_configuration_key_reference_rollout as previous_key_reference,
+};
+use migration_common::{iden::assert_audit_table_iden_valid, table::replace_audit_trigger};
+use sea_orm_migration::prelude::*;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        add_request_id_column(
+            manager,
+            ConfigurationTypeReference::Table,
+            ConfigurationTypeReferenceAudit::Table,
+        )
+        .await?;
+
+        add_request_id_column(
+            manager,
+            ConfigurationKeyReference::Table,
+            ConfigurationKeyReferenceAudit::Table,
+        )
+        .await?;
+
+        add_request_id_column(
+            manager,
+            ConfigurationEntries::Table,
+            ConfigurationEntriesAudit::Table,
+        )
+        .await?;
+
+        add_request_id_column(
+            manager,
+            ConfigurationGroupMembers::Table,
+            ConfigurationGroupMembersAudit::Table,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_request_id_column(
+            manager,
+            previous_type_reference::ConfigurationTypeReference::Table,
+            previous_type_reference::ConfigurationTypeReferenceAudit::Table,
+        )
+        .await?;
+
+        drop_request_id_column(
+            manager,
+            previous_key_reference::ConfigurationKeyReference::Table,
+            previous_key_reference::ConfigurationKeyReferenceAudit::Table,
+        )
+        .await?;
+
+        drop_request_id_column(
+            manager,
+            previous_groups::ConfigurationEntries::Table,
+            previous_groups::ConfigurationEntriesAudit::Table,
+        )
+        .await?;
+
+        drop_request_id_column(
+            manager,
+            previous_groups::ConfigurationGroupMembers::Table,
+            previous_groups::ConfigurationGroupMembersAudit::Table,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Add the request id column to an audit table and replace its trigger so
+/// that it fills the column in.
+///
+/// Only the audit table changes, so this does not use `alter_audited_table`.
+async fn add_request_id_column<
+    TableIdenSource: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+    TableIdenAudit: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+>(
+    manager: &SchemaManager<'_>,
+    table_iden_source: TableIdenSource,
+    table_iden_audit: TableIdenAudit,
+) -> Result<(), DbErr> {
+    assert_audit_table_iden_valid(table_iden_source.clone(), table_iden_audit.clone());
+
+    manager
+        .alter_table(
+            Table::alter()
+                .table(table_iden_audit.clone())
+                .add_column(ColumnDef::new(Alias::new("audit_request_id")).string())
+                .to_owned(),
+        )
+        .await?;
+
+    replace_audit_trigger(manager, table_iden_source, table_iden_audit).await
+}
+
+/// Replace the trigger of an audit table with one that does not fill in the
+/// request id column, and then drop the column.
+async fn drop_request_id_column<
+    TableIdenSource: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+    TableIdenAudit: Iden + IntoEnumIterator + PartialEq + Clone + 'static,
+>(
+    manager: &SchemaManager<'_>,
+    table_iden_source: TableIdenSource,
+    table_iden_audit: TableIdenAudit,
+) -> Result<(), DbErr> {
+    replace_audit_trigger(manager, table_iden_source, table_iden_audit.clone()).await?;
+
+    manager
+        .alter_table(
+            Table::alter()
+                .table(table_iden_audit)
+                .drop_column(Alias::new("audit_request_id"))
+                .to_owned(),
+        )
+        .await
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationTypeReference {
+    Table,
+    Id,
+    Name,
+    Description,
+    DeactivateTimestamp,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationTypeReferenceAudit {
+    Table,
+    Id,
+    Name,
+    Description,
+    DeactivateTimestamp,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+    AuditRequestId,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationKeyReference {
+    Table,
+    Id,
+    Name,
+    Description,
+    TypeId,
+    Optional,
+    AllowsMultiple,
+    AllowsUserOverride,
+    DeactivateTimestamp,
+    Minimum,
+    Maximum,
+    Pattern,
+    AllowedValues,
+    RolloutPercentage,
+    RolloutAllowUserIds,
+    RolloutDenyUserIds,
+    RolloutGroupIds,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationKeyReferenceAudit {
+    Table,
+    Id,
+    Name,
+    Description,
+    TypeId,
+    Optional,
+    AllowsMultiple,
+    AllowsUserOverride,
+    DeactivateTimestamp,
+    Minimum,
+    Maximum,
+    Pattern,
+    AllowedValues,
+    RolloutPercentage,
+    RolloutAllowUserIds,
+    RolloutDenyUserIds,
+    RolloutGroupIds,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+    AuditRequestId,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationEntries {
+    Table,
+    Id,
+    KeyId,
+    UserId,
+    OrderIndex,
+    Value,
+    DeactivateTimestamp,
+    GroupId,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationEntriesAudit {
+    Table,
+    Id,
+    KeyId,
+    UserId,
+    OrderIndex,
+    Value,
+    DeactivateTimestamp,
+    GroupId,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+    AuditRequestId,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationGroupMembers {
+    Table,
+    Id,
+    GroupId,
+    UserId,
+    DeactivateTimestamp,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationGroupMembersAudit {
+    Table,
+    Id,
+    GroupId,
+    UserId,
+    DeactivateTimestamp,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+    AuditRequestId,
+}
@@ -0,0 +1,127 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use super::m20230326_120000_add_configuration_change_notifications::CONFIGURATION_CHANGES_CHANNEL;
+use migration_common::{
+    create_audited_table,
+    table::{create_notify_trigger, drop_notify_trigger, TableKind},
+};
+use sea_orm_migration::prelude::*;
+use strum_macros::EnumIter;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Groups without a row here have the default priority of 0
+        create_audited_table(
+            manager,
+            ConfigurationGroups::Table,
+            ConfigurationGroupsAudit::Table,
+            &|table_kind, table_create_statement| {
+                table_create_statement
+                    .col(
+                        match (
+                            &table_kind,
+                            ColumnDef::new(ConfigurationGroups::Id).integer(),
+                        ) {
+                            (TableKind::Source, x) => x.not_null().auto_increment().primary_key(),
+                            (TableKind::Audit, x) => x,
+                        },
+                    )
+                    .col(
+                        match (
+                            &table_kind,
+                            ColumnDef::new(ConfigurationGroups::GroupId).string(),
+                        ) {
+                            (TableKind::Source, x) => x.not_null().unique_key(),
+                            (TableKind::Audit, x) => x,
+                        },
+                    )
+                    .col(
+                        match (
+                            &table_kind,
+                            ColumnDef::new(ConfigurationGroups::Priority).integer(),
+                        ) {
+                            (TableKind::Source, x) => x.not_null().default(0),
+                            (TableKind::Audit, x) => x,
+                        },
+                    );
+            },
+        )
+        .await?;
+
+        create_notify_trigger(
+            manager,
+            ConfigurationGroups::Table,
+            CONFIGURATION_CHANGES_CHANNEL,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_notify_trigger(manager, ConfigurationGroups::Table).await?;
+
+        manager
+            .drop_table(Table::drop().table(ConfigurationGroups::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ConfigurationGroupsAudit::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationGroups {
+    Table,
+    Id,
+    GroupId,
+    Priority,
+}
+
+#[derive(Iden, EnumIter, Clone, PartialEq)]
+pub enum ConfigurationGroupsAudit {
+    Table,
+    Id,
+    GroupId,
+    Priority,
+    AuditId,
+    AuditAction,
+    AuditTimestampTransactionStart,
+    AuditTimestampStatementStart,
+    AuditTimestampTrigger,
+    AuditClientHost,
+    AuditClientPort,
+    AuditClientQuery,
+    AuditRequestId,
+}
@@ -0,0 +1,72 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Keep the oldest of any duplicate active memberships so the index can
+        // be created
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                    UPDATE configuration_group_members AS duplicate
+                    SET deactivate_timestamp = now()
+                    FROM configuration_group_members AS kept
+                    WHERE duplicate.deactivate_timestamp IS NULL
+                        AND kept.deactivate_timestamp IS NULL
+                        AND duplicate.group_id = kept.group_id
+                        AND duplicate.user_id = kept.user_id
+                        AND duplicate.id > kept.id;
+                "#,
+            )
+            .await?;
+
+        // A user is an active member of a group at most once
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                    CREATE UNIQUE INDEX index_configuration_group_members_active
+                    ON configuration_group_members (group_id, user_id)
+                    WHERE deactivate_timestamp IS NULL;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP INDEX index_configuration_group_members_active;")
+            .await?;
+
+        Ok(())
+    }
+}
@@ -12,6 +12,7 @@ pub struct Model {
     pub order_index: i32,
     pub value: String,
     pub deactivate_timestamp: Option<DateTime>,
+    pub group_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
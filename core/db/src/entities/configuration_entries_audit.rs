@@ -11,6 +11,7 @@ pub struct Model {
     pub order_index: Option<i32>,
     pub value: Option<String>,
     pub deactivate_timestamp: Option<DateTime>,
+    pub group_id: Option<String>,
     #[sea_orm(primary_key)]
     pub audit_id: i32,
     pub audit_action: String,
@@ -20,6 +21,7 @@ pub struct Model {
     pub audit_client_host: String,
     pub audit_client_port: i32,
     pub audit_client_query: String,
+    pub audit_request_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
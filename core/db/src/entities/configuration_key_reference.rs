@@ -2,7 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "configuration_key_reference")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -15,6 +15,17 @@ pub struct Model {
     pub allows_multiple: bool,
     pub allows_user_override: bool,
     pub deactivate_timestamp: Option<DateTime>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub minimum: Option<f64>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub maximum: Option<f64>,
+    pub pattern: Option<String>,
+    pub allowed_values: Option<Vec<String>>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub rollout_percentage: Option<f64>,
+    pub rollout_allow_user_ids: Option<Vec<String>>,
+    pub rollout_deny_user_ids: Option<Vec<String>>,
+    pub rollout_group_ids: Option<Vec<String>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
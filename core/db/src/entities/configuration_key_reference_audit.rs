@@ -2,7 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "configuration_key_reference_audit")]
 pub struct Model {
     pub id: Option<i32>,
@@ -22,6 +22,18 @@ pub struct Model {
     pub audit_client_host: String,
     pub audit_client_port: i32,
     pub audit_client_query: String,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub minimum: Option<f64>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub maximum: Option<f64>,
+    pub pattern: Option<String>,
+    pub allowed_values: Option<Vec<String>>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub rollout_percentage: Option<f64>,
+    pub rollout_allow_user_ids: Option<Vec<String>>,
+    pub rollout_deny_user_ids: Option<Vec<String>>,
+    pub rollout_group_ids: Option<Vec<String>>,
+    pub audit_request_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
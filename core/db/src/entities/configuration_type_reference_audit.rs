@@ -18,6 +18,7 @@ pub struct Model {
     pub audit_client_host: String,
     pub audit_client_port: i32,
     pub audit_client_query: String,
+    pub audit_request_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
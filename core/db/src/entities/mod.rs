@@ -4,6 +4,10 @@ pub mod prelude;
 
 pub mod configuration_entries;
 pub mod configuration_entries_audit;
+pub mod configuration_group_members;
+pub mod configuration_group_members_audit;
+pub mod configuration_groups;
+pub mod configuration_groups_audit;
 pub mod configuration_key_reference;
 pub mod configuration_key_reference_audit;
 pub mod configuration_type_reference;
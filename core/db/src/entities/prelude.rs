@@ -2,6 +2,10 @@
 
 pub use super::configuration_entries::Entity as ConfigurationEntries;
 pub use super::configuration_entries_audit::Entity as ConfigurationEntriesAudit;
+pub use super::configuration_group_members::Entity as ConfigurationGroupMembers;
+pub use super::configuration_group_members_audit::Entity as ConfigurationGroupMembersAudit;
+pub use super::configuration_groups::Entity as ConfigurationGroups;
+pub use super::configuration_groups_audit::Entity as ConfigurationGroupsAudit;
 pub use super::configuration_key_reference::Entity as ConfigurationKeyReference;
 pub use super::configuration_key_reference_audit::Entity as ConfigurationKeyReferenceAudit;
 pub use super::configuration_type_reference::Entity as ConfigurationTypeReference;
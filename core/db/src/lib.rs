@@ -27,6 +27,8 @@
 
 pub mod entities;
 pub mod queries;
+pub mod request_id;
+pub mod secrets;
 pub mod seeding;
 pub mod testing;
 
@@ -50,18 +52,98 @@ pub type DateTime = chrono::DateTime<Utc>;
 pub enum Error {
     /// A configuration type was not found for the given id
     ConfigurationTypeNotFound(i32),
+    /// A configuration type was not found for the given name
+    ConfigurationTypeNotFoundByName(String),
+    /// A configuration type name does not match any supported kind of value
+    ConfigurationTypeUnsupported(String),
+    /// A configuration type with the given name already exists
+    ConfigurationTypeAlreadyExists(String),
+    /// A configuration type is still referenced by active configuration keys
+    ConfigurationTypeInUse(String),
     /// A configuration key was not found for the given id
     ConfigurationKeyNotFound(i32),
+    /// A configuration key was not found for the given name
+    ConfigurationKeyNotFoundByName(String),
+    /// A configuration key name does not match the allowed format
+    ConfigurationKeyNameInvalid(String),
+    /// A configuration key with the given name already exists
+    ConfigurationKeyAlreadyExists(String),
+    /// The type of a configuration key cannot be changed because some of its
+    /// active values would no longer parse
+    ConfigurationKeyTypeChangeIncompatible(String),
+    /// A user or group override was written for a configuration key that does
+    /// not allow them
+    ConfigurationKeyUserOverrideNotAllowed(String),
+    /// More than one value was written for a configuration key that does not
+    /// allow multiple values
+    ConfigurationKeyMultipleNotAllowed(String),
+    /// A configuration key that is not optional would be left without a global
+    /// value
+    ConfigurationKeyRequired(String),
+    /// The value constraints of a configuration key are invalid for its type,
+    /// along with the reason
+    ConfigurationKeyConstraintsInvalid(String, String),
+    /// The rollout rule of a configuration key is invalid for its type, along
+    /// with the reason
+    ConfigurationKeyRolloutInvalid(String, String),
     /// Could not parse a boolean configuration value
     ConfigurationValueParseErrorBoolean(String),
+    /// A configuration value did not match the type of its configuration key
+    ConfigurationValueTypeMismatch(String),
+    /// A numeric configuration value is outside of the range allowed by its
+    /// configuration key
+    ConfigurationValueOutOfRange(String),
+    /// A string configuration value does not match the pattern required by its
+    /// configuration key
+    ConfigurationValuePatternMismatch(String),
+    /// A configuration value is not one of the values allowed by its
+    /// configuration key
+    ConfigurationValueNotAllowed(String),
     /// Wrapper for integer parsing errors
     NumParseIntError(ParseIntError),
     /// Wrapper for float parsing errors
     NumParseFloatError(ParseFloatError),
+    /// No key is configured to encrypt or decrypt secret configuration values
+    ConfigurationSecretKeyMissing,
+    /// The key configured for secret configuration values is invalid
+    ConfigurationSecretKeyInvalid,
+    /// A secret configuration value could not be encrypted
+    ConfigurationSecretEncryptionFailed,
+    /// A stored secret configuration value could not be decrypted
+    ConfigurationSecretDecryptionFailed,
+    /// An audit record is missing columns of its source row, along with the
+    /// source table name and the audit id
+    AuditRecordIncomplete(String, i32),
+    /// The start of a range of audit timestamps is after its end, along with
+    /// the start and the end
+    AuditRangeInvalid(String, String),
+    /// A configuration document describes the same type, key or entry more
+    /// than once
+    ConfigurationDocumentDuplicate(String),
+    /// Changes to the configuration cannot be listened for because the
+    /// database connection is not a PostgreSQL connection pool, or because no
+    /// watcher is running
+    ConfigurationChangesUnavailable,
+    /// A listener fell behind the changes to the configuration, along with the
+    /// number of changes that it missed
+    ConfigurationChangesLagged(u64),
+    /// An environment variable pins a configuration key to a value that cannot
+    /// be used, along with the dotted key name and the reason
+    ConfigurationOverrideInvalid(String, Box<Error>),
+    /// Wrapper for duration parsing errors
+    HumantimeDurationError(humantime::DurationError),
+    /// Wrapper for URL parsing errors
+    UrlParseError(url::ParseError),
+    /// Wrapper for datetime parsing errors
+    ChronoParseError(chrono::ParseError),
+    /// Wrapper for JSON errors
+    SerdeJsonError(serde_json::Error),
     /// Wrapper for config-env errors
     ConfigEnvError(config_env::Error),
     /// Wrapper for SeaORM errors
     SeaORMDbErr(DbErr),
+    /// Wrapper for SQLx errors
+    SqlxError(sqlx::Error),
     /// Wrapper for validator errors
     ValidatorValidationErrors(ValidationErrors),
 }
@@ -78,6 +160,30 @@ impl From<ParseFloatError> for Error {
     }
 }
 
+impl From<humantime::DurationError> for Error {
+    fn from(value: humantime::DurationError) -> Self {
+        Self::HumantimeDurationError(value)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(value: url::ParseError) -> Self {
+        Self::UrlParseError(value)
+    }
+}
+
+impl From<chrono::ParseError> for Error {
+    fn from(value: chrono::ParseError) -> Self {
+        Self::ChronoParseError(value)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::SerdeJsonError(value)
+    }
+}
+
 impl From<config_env::Error> for Error {
     fn from(value: config_env::Error) -> Self {
         Self::ConfigEnvError(value)
@@ -90,6 +196,12 @@ impl From<DbErr> for Error {
     }
 }
 
+impl From<sqlx::Error> for Error {
+    fn from(value: sqlx::Error) -> Self {
+        Self::SqlxError(value)
+    }
+}
+
 impl From<ValidationErrors> for Error {
     fn from(value: ValidationErrors) -> Self {
         Self::ValidatorValidationErrors(value)
@@ -102,16 +214,138 @@ impl Display for Error {
             Error::ConfigurationTypeNotFound(id) => {
                 write!(f, "configuration type not found for id {id}")
             }
+            Error::ConfigurationTypeNotFoundByName(name) => {
+                write!(f, "configuration type not found for name {name:#?}")
+            }
+            Error::ConfigurationTypeUnsupported(name) => {
+                write!(f, "configuration type {name:#?} is not supported")
+            }
+            Error::ConfigurationTypeAlreadyExists(name) => {
+                write!(f, "configuration type already exists with name {name:#?}")
+            }
+            Error::ConfigurationTypeInUse(name) => {
+                write!(f, "configuration type {name:#?} is used by active keys")
+            }
             Error::ConfigurationKeyNotFound(id) => {
                 write!(f, "configuration key not found for id {id}")
             }
+            Error::ConfigurationKeyNotFoundByName(name) => {
+                write!(f, "configuration key not found for name {name:#?}")
+            }
+            Error::ConfigurationKeyNameInvalid(name) => {
+                write!(f, "configuration key name {name:#?} is invalid")
+            }
+            Error::ConfigurationKeyAlreadyExists(name) => {
+                write!(f, "configuration key already exists with name {name:#?}")
+            }
+            Error::ConfigurationKeyTypeChangeIncompatible(name) => {
+                write!(
+                    f,
+                    "configuration key {name:#?} has active values that do not parse as the new type"
+                )
+            }
+            Error::ConfigurationKeyUserOverrideNotAllowed(name) => {
+                write!(
+                    f,
+                    "configuration key {name:#?} does not allow user overrides"
+                )
+            }
+            Error::ConfigurationKeyMultipleNotAllowed(name) => {
+                write!(
+                    f,
+                    "configuration key {name:#?} does not allow multiple values"
+                )
+            }
+            Error::ConfigurationKeyRequired(name) => {
+                write!(f, "configuration key {name:#?} is not optional")
+            }
+            Error::ConfigurationKeyConstraintsInvalid(name, reason) => {
+                write!(
+                    f,
+                    "configuration key {name:#?} has invalid constraints: {reason}"
+                )
+            }
+            Error::ConfigurationKeyRolloutInvalid(name, reason) => {
+                write!(
+                    f,
+                    "configuration key {name:#?} has an invalid rollout rule: {reason}"
+                )
+            }
             Error::ConfigurationValueParseErrorBoolean(text) => {
                 write!(f, "could not parse {text:#?} as a boolean")
             }
+            Error::ConfigurationValueTypeMismatch(type_name) => {
+                write!(f, "configuration value does not match type {type_name:#?}")
+            }
+            Error::ConfigurationValueOutOfRange(name) => {
+                write!(f, "configuration value is out of range for key {name:#?}")
+            }
+            Error::ConfigurationValuePatternMismatch(name) => {
+                write!(
+                    f,
+                    "configuration value does not match the pattern for key {name:#?}"
+                )
+            }
+            Error::ConfigurationValueNotAllowed(name) => {
+                write!(f, "configuration value is not allowed for key {name:#?}")
+            }
             Error::NumParseIntError(err) => write!(f, "{err}"),
             Error::NumParseFloatError(err) => write!(f, "{err}"),
+            Error::ConfigurationSecretKeyMissing => {
+                write!(f, "no key is configured for secret configuration values")
+            }
+            Error::ConfigurationSecretKeyInvalid => {
+                write!(
+                    f,
+                    "the key for secret configuration values must be 256 bits encoded as base64"
+                )
+            }
+            Error::ConfigurationSecretEncryptionFailed => {
+                write!(f, "could not encrypt secret configuration value")
+            }
+            Error::ConfigurationSecretDecryptionFailed => {
+                write!(f, "could not decrypt secret configuration value")
+            }
+            Error::AuditRecordIncomplete(table, audit_id) => {
+                write!(
+                    f,
+                    "audit record {audit_id} for table {table:#?} is incomplete"
+                )
+            }
+            Error::AuditRangeInvalid(from, to) => {
+                write!(f, "audit range start {from} is after its end {to}")
+            }
+            Error::ConfigurationDocumentDuplicate(name) => {
+                write!(
+                    f,
+                    "configuration document describes {name:#?} more than once"
+                )
+            }
+            Error::ConfigurationChangesUnavailable => {
+                write!(
+                    f,
+                    "configuration changes can only be listened for while they are watched on a PostgreSQL connection pool"
+                )
+            }
+            Error::ConfigurationOverrideInvalid(name, error) => {
+                write!(
+                    f,
+                    "configuration override for {name:#?} is invalid: {error}"
+                )
+            }
+            Error::ConfigurationChangesLagged(count) => {
+                write!(
+                    f,
+                    "configuration change listener fell behind and missed {count} changes"
+                )
+            }
+            Error::HumantimeDurationError(err) => write!(f, "{err}"),
+            Error::UrlParseError(err) => write!(f, "{err}"),
+            Error::ChronoParseError(err) => write!(f, "{err}"),
+            Error::SerdeJsonError(err) => write!(f, "{err}"),
             Error::ConfigEnvError(err) => write!(f, "{err}"),
             Error::SeaORMDbErr(err) => write!(f, "{err}"),
+            Error::SqlxError(err) => write!(f, "{err}"),
             Error::ValidatorValidationErrors(err) => write!(f, "{err}"),
         }
     }
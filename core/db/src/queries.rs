@@ -21,3 +21,4 @@
 // SOFTWARE.
 
 pub mod configuration;
+pub mod migrations;
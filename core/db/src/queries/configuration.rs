@@ -20,20 +20,142 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+pub mod cache;
+pub mod document;
+pub mod events;
+pub mod groups;
+pub mod history;
+pub mod value;
+
 use crate::{
-    entities::{configuration_entries, configuration_key_reference, configuration_type_reference},
+    entities::{
+        configuration_entries, configuration_group_members, configuration_key_reference,
+        configuration_type_reference,
+    },
+    request_id::begin_transaction,
+    secrets::SecretCipher,
     Error,
 };
+use chrono::Utc;
 use domain_api::configuration::{
-    ConfigurationEntryItemResponse, ConfigurationEntryResponse, ConfigurationEntrySetResponse,
-    ConfigurationEntryUserResponse, ConfigurationKeyResponse, ConfigurationKeySetResponse,
-    ConfigurationTypeResponse, ConfigurationTypeSetResponse, ConfigurationValueResponse,
+    ConfigurationEffectiveEntryResponse, ConfigurationEffectiveSetResponse,
+    ConfigurationEffectiveSource, ConfigurationEntryGroupResponse, ConfigurationEntryItemResponse,
+    ConfigurationEntryResponse, ConfigurationEntrySetResponse, ConfigurationEntryTreeResponse,
+    ConfigurationEntryUserResponse, ConfigurationKeyConstraintsResponse,
+    ConfigurationKeyCreateRequest, ConfigurationKeyResponse, ConfigurationKeyRolloutResponse,
+    ConfigurationKeySetResponse, ConfigurationKeyUpdateRequest, ConfigurationTypeCreateRequest,
+    ConfigurationTypeResponse, ConfigurationTypeSetResponse, ConfigurationTypeUpdateRequest,
+    ConfigurationValueResponse, CONFIGURATION_KEY_NAME_REGEX,
 };
+use regex::Regex;
 use sea_orm::{
-    ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Select,
+    sea_query::{Expr, LikeExpr, Query},
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Select, Set, TransactionTrait,
+};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    fmt::{self, Display},
 };
-use std::collections::{hash_map::Entry, HashMap};
+use tracing::{instrument, warn};
 use validator::Validate;
+use value::{format_configuration_value, parse_configuration_value, ConfigurationTypeKind};
+
+/// A violation of a configuration key's constraints by the stored entries
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConfigurationConstraintViolation {
+    /// A key that is not optional has no active global items
+    Required {
+        /// The name of the configuration key
+        key_name: String,
+    },
+    /// A key that does not allow multiple values has more than one active item
+    /// in a single scope
+    MultipleNotAllowed {
+        /// The name of the configuration key
+        key_name: String,
+        /// The scope of the items
+        scope: ConfigurationEntryScope,
+        /// The ids of the active items in the scope
+        entry_ids: Vec<i32>,
+    },
+}
+
+/// The scope that a configuration entry item applies to
+///
+/// Group and user items are both overrides of the global items, so they are
+/// only used for keys that allow user overrides. User items take precedence
+/// over group items.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub enum ConfigurationEntryScope {
+    /// The item applies to every user
+    Global,
+    /// The item applies to the members of a group
+    Group(String),
+    /// The item applies to a single user
+    User(String),
+}
+
+impl ConfigurationEntryScope {
+    /// The scope of a user's items, or the global scope if there is no user
+    pub fn of_user(user_id: Option<&str>) -> Self {
+        match user_id {
+            Some(user_id) => ConfigurationEntryScope::User(user_id.to_owned()),
+            None => ConfigurationEntryScope::Global,
+        }
+    }
+
+    /// The scope of a configuration entry row
+    pub(crate) fn of_row(row: &configuration_entries::Model) -> Self {
+        match (&row.user_id, &row.group_id) {
+            (Some(user_id), _) => ConfigurationEntryScope::User(user_id.clone()),
+            (None, Some(group_id)) => ConfigurationEntryScope::Group(group_id.clone()),
+            (None, None) => ConfigurationEntryScope::Global,
+        }
+    }
+
+    /// The user id of a user scope
+    pub fn user_id(&self) -> Option<&str> {
+        match self {
+            ConfigurationEntryScope::User(user_id) => Some(user_id),
+            _ => None,
+        }
+    }
+
+    /// The group id of a group scope
+    pub fn group_id(&self) -> Option<&str> {
+        match self {
+            ConfigurationEntryScope::Group(group_id) => Some(group_id),
+            _ => None,
+        }
+    }
+
+    /// Build a condition that matches configuration entry rows in exactly this
+    /// scope.
+    pub(crate) fn condition(&self) -> Condition {
+        match self {
+            ConfigurationEntryScope::Global => Condition::all()
+                .add(configuration_entries::Column::UserId.is_null())
+                .add(configuration_entries::Column::GroupId.is_null()),
+            ConfigurationEntryScope::Group(group_id) => {
+                Condition::all().add(configuration_entries::Column::GroupId.eq(group_id.as_str()))
+            }
+            ConfigurationEntryScope::User(user_id) => {
+                Condition::all().add(configuration_entries::Column::UserId.eq(user_id.as_str()))
+            }
+        }
+    }
+}
+
+impl Display for ConfigurationEntryScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigurationEntryScope::Global => write!(f, "global"),
+            ConfigurationEntryScope::Group(group_id) => write!(f, "group {group_id:#?}"),
+            ConfigurationEntryScope::User(user_id) => write!(f, "user {user_id:#?}"),
+        }
+    }
+}
 
 /// Get all configuration types from the database
 ///
@@ -48,8 +170,9 @@ use validator::Validate;
 /// # Errors
 ///
 /// Returns any database errors.
-pub async fn get_all_configuration_types(
-    connection: &DatabaseConnection,
+#[instrument(level = "debug", skip_all)]
+pub async fn get_all_configuration_types<C: ConnectionTrait>(
+    connection: &C,
 ) -> Result<ConfigurationTypeSetResponse, Error> {
     configuration_type_reference::Entity::find()
         .order_by_asc(configuration_type_reference::Column::Id)
@@ -57,17 +180,7 @@ pub async fn get_all_configuration_types(
         .all(connection)
         .await?
         .into_iter()
-        .map(|row| {
-            let configuration_type_response = ConfigurationTypeResponse {
-                id: row.id,
-                name: row.name,
-                description: row.description,
-            };
-
-            configuration_type_response.validate()?;
-
-            Ok(configuration_type_response)
-        })
+        .map(build_configuration_type_response)
         .collect::<Result<ConfigurationTypeSetResponse, Error>>()
 }
 
@@ -86,8 +199,9 @@ pub async fn get_all_configuration_types(
 ///
 /// Returns any database errors. If there is a configuration type id referenced
 /// that is not in the set of configuration types, an error is returned.
-pub async fn get_all_configuration_keys(
-    connection: &DatabaseConnection,
+#[instrument(level = "debug", skip_all)]
+pub async fn get_all_configuration_keys<C: ConnectionTrait>(
+    connection: &C,
     type_set: &ConfigurationTypeSetResponse,
 ) -> Result<ConfigurationKeySetResponse, Error> {
     configuration_key_reference::Entity::find()
@@ -96,35 +210,23 @@ pub async fn get_all_configuration_keys(
         .all(connection)
         .await?
         .into_iter()
-        .map(|row| {
-            let configuration_key_response = ConfigurationKeyResponse {
-                id: row.id,
-                name: row.name,
-                description: row.description,
-                configuration_type: type_set
-                    .iter()
-                    .find(|t| t.id == row.type_id)
-                    .ok_or(Error::ConfigurationTypeNotFound(row.type_id))?
-                    .clone(),
-                optional: row.optional,
-                allows_multiple: row.allows_multiple,
-                allows_user_override: row.allows_user_override,
-            };
-
-            configuration_key_response.validate()?;
-
-            Ok(configuration_key_response)
-        })
+        .map(|row| build_configuration_key_response(row, type_set))
         .collect::<Result<ConfigurationKeySetResponse, Error>>()
 }
 
 /// Get all configuration entries from the database
 ///
+/// Secret values are redacted.
+///
 /// # Arguments
 ///
 /// * `connection` - The database connection
-/// * `type_set` - The set of already loaded configuration types
 /// * `key_set` - The set of already loaded configuration keys
+/// * `user_id` - The user id to select the configuration entries for. If this
+///               value is null, return only global configuration entries.
+///               Otherwise, the items of the user's groups are returned too.
+/// * `prefix` - The dotted key name prefix to select the configuration entries
+///              for. If this value is null, return entries for all keys.
 ///
 /// # Returns
 ///
@@ -132,141 +234,2121 @@ pub async fn get_all_configuration_keys(
 ///
 /// # Errors
 ///
-/// Returns any database errors. If there is a configuration type id referenced
-/// that is not in the set of configuration types, an error is returned. This is
-/// true for configuration keys as well.
+/// Returns any database errors. If the prefix is not a valid key name, an error
+/// is returned. If there is a configuration key id referenced that is not in
+/// the set of configuration keys, an error is returned.
+#[instrument(level = "debug", skip_all, fields(user_id = user_id, prefix = prefix))]
 pub async fn get_all_configuration_entries(
     connection: &DatabaseConnection,
     key_set: &ConfigurationKeySetResponse,
     user_id: Option<&str>,
+    prefix: Option<&str>,
 ) -> Result<ConfigurationEntrySetResponse, Error> {
     // Build query
-    let query = build_configuration_entries_query(user_id);
+    let query =
+        build_configuration_entries_query(&ConfigurationEntryScope::of_user(user_id), prefix)?;
 
-    // Create cache
-    let mut configuration_entries_map: HashMap<i32, ConfigurationEntryResponse> = HashMap::new();
+    // Group rows into entries
+    Ok(collect_configuration_entries(
+        query.all(connection).await?,
+        key_set,
+        &groups::get_configuration_group_priorities(connection).await?,
+        None,
+    )?
+    .into_values()
+    .collect::<ConfigurationEntrySetResponse>())
+}
 
-    // Iterate over query response rows
-    for row in query.all(connection).await? {
-        // Find the entry in the cache
-        let configuration_entries_map_entry = configuration_entries_map.entry(row.key_id);
+/// Get all configuration entries from the database with secret values
+/// decrypted
+///
+/// Only use this when the caller is allowed to see secret values.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key_set` - The set of already loaded configuration keys
+/// * `user_id` - The user id to select the configuration entries for. If this
+///               value is null, return only global configuration entries.
+///               Otherwise, the items of the user's groups are returned too.
+/// * `prefix` - The dotted key name prefix to select the configuration entries
+///              for. If this value is null, return entries for all keys.
+///
+/// # Returns
+///
+/// The set of configuration entries.
+///
+/// # Errors
+///
+/// Returns any database errors. If the prefix is not a valid key name, an error
+/// is returned. If there is no valid key configured for secret values or if any
+/// secret value cannot be decrypted, an error is returned.
+#[instrument(level = "debug", skip_all, fields(user_id = user_id, prefix = prefix))]
+pub async fn get_all_configuration_entries_with_secrets(
+    connection: &DatabaseConnection,
+    key_set: &ConfigurationKeySetResponse,
+    user_id: Option<&str>,
+    prefix: Option<&str>,
+) -> Result<ConfigurationEntrySetResponse, Error> {
+    let secret_cipher = SecretCipher::from_configuration()?;
 
-        // Populate it if it does not already exist
-        let configuration_entry = match configuration_entries_map_entry {
-            Entry::Occupied(configuration_entry_occupied) => {
-                configuration_entry_occupied.into_mut()
-            }
-            Entry::Vacant(configuration_entry_vacant) => {
-                configuration_entry_vacant.insert(ConfigurationEntryResponse {
-                    key: key_set
-                        .iter()
-                        .find(|k| k.id == row.key_id)
-                        .ok_or(Error::ConfigurationKeyNotFound(row.key_id))
-                        .map(|x| x.clone())?,
-                    items_global: Vec::new(),
-                    user: None,
-                })
+    // Build query
+    let query =
+        build_configuration_entries_query(&ConfigurationEntryScope::of_user(user_id), prefix)?;
+
+    // Group rows into entries
+    Ok(collect_configuration_entries(
+        query.all(connection).await?,
+        key_set,
+        &groups::get_configuration_group_priorities(connection).await?,
+        Some(&secret_cipher),
+    )?
+    .into_values()
+    .collect::<ConfigurationEntrySetResponse>())
+}
+
+/// Get the configuration entry for a single configuration key from the
+/// database
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key` - The already loaded configuration key
+/// * `user_id` - The user id to select the configuration entry for. If this
+///               value is null, return only global configuration entry items.
+///               Otherwise, the items of the user's groups are returned too.
+///
+/// # Returns
+///
+/// The configuration entry. If there are no active items for the key, the
+/// entry is returned with no items.
+///
+/// # Errors
+///
+/// Returns any database errors. If any of the stored values cannot be parsed as
+/// the key's type, an error is returned.
+#[instrument(level = "debug", skip_all, fields(key = %key.name, user_id = user_id))]
+pub async fn get_configuration_entry<C: ConnectionTrait>(
+    connection: &C,
+    key: &ConfigurationKeyResponse,
+    user_id: Option<&str>,
+) -> Result<ConfigurationEntryResponse, Error> {
+    get_configuration_entry_in_scope(connection, key, &ConfigurationEntryScope::of_user(user_id))
+        .await
+}
+
+/// Get the configuration entry for a single configuration key from the
+/// database as it is seen from a scope
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key` - The already loaded configuration key
+/// * `scope` - The scope to select the configuration entry for. The global
+///             items are always returned. The items of a group scope, or of a
+///             user scope and the user's groups, are returned too.
+///
+/// # Returns
+///
+/// The configuration entry. If there are no active items for the key, the
+/// entry is returned with no items.
+///
+/// # Errors
+///
+/// Returns any database errors. If any of the stored values cannot be parsed as
+/// the key's type, an error is returned.
+#[instrument(level = "debug", skip_all, fields(key = %key.name))]
+pub async fn get_configuration_entry_in_scope<C: ConnectionTrait>(
+    connection: &C,
+    key: &ConfigurationKeyResponse,
+    scope: &ConfigurationEntryScope,
+) -> Result<ConfigurationEntryResponse, Error> {
+    // Build query
+    let query = build_configuration_entries_query(scope, None)?
+        .filter(configuration_entries::Column::KeyId.eq(key.id));
+
+    // Group rows into entries
+    Ok(collect_configuration_entries(
+        query.all(connection).await?,
+        &vec![key.clone()],
+        &groups::get_configuration_group_priorities(connection).await?,
+        None,
+    )?
+    .remove(&key.id)
+    .unwrap_or_else(|| ConfigurationEntryResponse {
+        key: key.clone(),
+        items_global: Vec::new(),
+        groups: Vec::new(),
+        user: None,
+    }))
+}
+
+/// Get a single active configuration key from the database by name
+///
+/// Only the key and its type are loaded.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `name` - The dotted name of the configuration key
+///
+/// # Returns
+///
+/// The configuration key.
+///
+/// # Errors
+///
+/// Returns any database errors. If the name does not match
+/// `CONFIGURATION_KEY_NAME_REGEX`, an error is returned. If there is no active
+/// configuration key with the given name, an error is returned.
+#[instrument(level = "debug", skip_all, fields(name = name))]
+pub async fn get_configuration_key_by_name<C: ConnectionTrait>(
+    connection: &C,
+    name: &str,
+) -> Result<ConfigurationKeyResponse, Error> {
+    if !CONFIGURATION_KEY_NAME_REGEX.is_match(name) {
+        return Err(Error::ConfigurationKeyNameInvalid(name.to_owned()));
+    }
+
+    let row = configuration_key_reference::Entity::find()
+        .filter(configuration_key_reference::Column::Name.eq(name))
+        .filter(configuration_key_reference::Column::DeactivateTimestamp.is_null())
+        .one(connection)
+        .await?
+        .ok_or_else(|| Error::ConfigurationKeyNotFoundByName(name.to_owned()))?;
+
+    let configuration_type = build_configuration_type_response(
+        find_active_configuration_type_row(connection, row.type_id).await?,
+    )?;
+
+    build_configuration_key_response(row, &vec![configuration_type])
+}
+
+/// Get the effective configuration for a user from the database
+///
+/// Every configuration key resolves to one effective value as described by
+/// [`resolve_effective_configuration_entry`] and
+/// [`apply_configuration_key_rollout`], unless it is pinned by an environment
+/// variable as described by [`load_configuration_environment_overrides`].
+/// Secret values are redacted.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key_set` - The set of already loaded configuration keys
+/// * `environment_overrides` - The values that environment variables pin keys
+///                             to by dotted key name, as loaded by
+///                             `config-env`
+/// * `user_id` - The user id to resolve the configuration for. If this value is
+///               null, only global configuration entries are used. Otherwise,
+///               the entries of the user's groups are used too.
+///
+/// # Returns
+///
+/// The effective configuration, with one entry per key in the same order as
+/// the key set.
+///
+/// # Errors
+///
+/// Returns any database errors. If any of the stored values cannot be parsed as
+/// their key's type, an error is returned.
+#[instrument(level = "debug", skip_all, fields(user_id = user_id))]
+pub async fn get_effective_configuration(
+    connection: &DatabaseConnection,
+    key_set: &ConfigurationKeySetResponse,
+    environment_overrides: &BTreeMap<String, String>,
+    user_id: Option<&str>,
+) -> Result<ConfigurationEffectiveSetResponse, Error> {
+    // Build query
+    let query =
+        build_configuration_entries_query(&ConfigurationEntryScope::of_user(user_id), None)?;
+
+    // Group rows into entries
+    let mut configuration_entries_map = collect_configuration_entries(
+        query.all(connection).await?,
+        key_set,
+        &groups::get_configuration_group_priorities(connection).await?,
+        None,
+    )?;
+
+    let mut environment_overrides =
+        load_configuration_environment_overrides(key_set, environment_overrides);
+
+    let group_ids = match user_id {
+        Some(user_id) => groups::get_configuration_group_ids(connection, user_id).await?,
+        None => Vec::new(),
+    };
+
+    // Resolve every key, including the ones without any items
+    Ok(key_set
+        .iter()
+        .map(|key| {
+            let mut entry = resolve_effective_configuration_entry(
+                configuration_entries_map
+                    .remove(&key.id)
+                    .unwrap_or_else(|| ConfigurationEntryResponse {
+                        key: key.clone(),
+                        items_global: Vec::new(),
+                        groups: Vec::new(),
+                        user: None,
+                    }),
+            );
+
+            apply_configuration_key_rollout(&mut entry, user_id, &group_ids);
+
+            if let Some(value) = environment_overrides.remove(&key.id) {
+                pin_effective_configuration_entry(&mut entry, value);
             }
-        };
 
-        // Create entry item from parsed text value
-        let entry_item = ConfigurationEntryItemResponse {
-            id: row.id,
-            value: parse_configuration_value(
-                &row.value,
-                &configuration_entry.key.configuration_type,
-            )?,
-        };
+            entry
+        })
+        .collect())
+}
 
-        // Push entry item into correct vector in entry
-        if let Some(user_id) = row.user_id {
-            let user =
-                configuration_entry
-                    .user
-                    .get_or_insert_with(|| ConfigurationEntryUserResponse {
-                        user_id,
-                        items: Vec::new(),
-                    });
+/// Resolve the effective value of a configuration entry
+///
+/// User items replace the global items as a whole when there are any and the
+/// key allows user overrides. Otherwise, the items of the first group that has
+/// any replace the global items the same way. Groups are expected in the order
+/// that their items take precedence: the highest priority first, and groups
+/// with the same priority by id, as described by
+/// [`groups::compare_configuration_group_precedence`]. Overrides left over from before a
+/// key stopped allowing them are ignored. Keys that allow multiple values
+/// resolve to all items of the winning scope in order. Other keys resolve to
+/// the last item of the winning scope.
+///
+/// # Arguments
+///
+/// * `entry` - The configuration entry with its global, group and user items
+///
+/// # Returns
+///
+/// The effective configuration entry.
+pub fn resolve_effective_configuration_entry(
+    entry: ConfigurationEntryResponse,
+) -> ConfigurationEffectiveEntryResponse {
+    let allows_user_override = entry.key.allows_user_override;
 
-            user.items.push(entry_item);
-        } else {
-            configuration_entry.items_global.push(entry_item);
+    let user_items = entry
+        .user
+        .map(|user| user.items)
+        .filter(|items| allows_user_override && !items.is_empty());
+
+    let group_items = entry
+        .groups
+        .into_iter()
+        .map(|group| group.items)
+        .find(|items| allows_user_override && !items.is_empty());
+
+    let (source, items) = match (user_items, group_items) {
+        (Some(items), _) => (ConfigurationEffectiveSource::User, items),
+        (None, Some(items)) => (ConfigurationEffectiveSource::Group, items),
+        (None, None) if !entry.items_global.is_empty() => {
+            (ConfigurationEffectiveSource::Global, entry.items_global)
         }
+        (None, None) => (ConfigurationEffectiveSource::Unset, Vec::new()),
+    };
+
+    let mut values = items
+        .into_iter()
+        .map(|item| item.value)
+        .collect::<Vec<ConfigurationValueResponse>>();
+
+    if !entry.key.allows_multiple && values.len() > 1 {
+        values.drain(..values.len() - 1);
     }
 
-    // Return cache as vector
-    Ok(configuration_entries_map
-        .into_values()
-        .collect::<ConfigurationEntrySetResponse>())
+    ConfigurationEffectiveEntryResponse {
+        value: if entry.key.allows_multiple {
+            None
+        } else {
+            values.first().cloned()
+        },
+        key: entry.key,
+        source,
+        values,
+    }
 }
 
-/// Build a query that selects all configuration entries from the database for
-/// a given user.
+/// Decide whether a user is selected by the rollout rule of a key
+///
+/// Denied users are never selected. Otherwise, users are selected if they are
+/// allowed explicitly, if they are a member of any of the rule's groups, or if
+/// they fall within the rule's percentage. Requests without a user are never
+/// selected.
+///
+/// Users are placed within the percentage by a stable hash of the key name and
+/// their user id, so the same users stay selected as the percentage grows and
+/// each key selects a different set of users.
 ///
 /// # Arguments
 ///
-/// * `user_id` - The user id to select the configuration entries for. If this
-///               value is null, return only global configuration entries.
-fn build_configuration_entries_query(
+/// * `key_name` - The dotted name of the configuration key
+/// * `rollout` - The rollout rule of the configuration key
+/// * `user_id` - The user id to evaluate the rule for
+/// * `group_ids` - The ids of the groups that the user is a member of
+///
+/// # Returns
+///
+/// Whether the user is selected.
+pub fn evaluate_configuration_key_rollout(
+    key_name: &str,
+    rollout: &ConfigurationKeyRolloutResponse,
     user_id: Option<&str>,
-) -> Select<configuration_entries::Entity> {
-    let mut query = configuration_entries::Entity::find()
-        .order_by_asc(configuration_entries::Column::KeyId)
-        .order_by_asc(configuration_entries::Column::OrderIndex)
-        .filter(configuration_entries::Column::DeactivateTimestamp.is_null());
+    group_ids: &[String],
+) -> bool {
+    let Some(user_id) = user_id else {
+        return false;
+    };
 
-    if let Some(user_id) = user_id {
-        query = query.filter(
-            Condition::any()
-                .add(configuration_entries::Column::UserId.is_null())
-                .add(configuration_entries::Column::UserId.eq(user_id)),
-        );
-    } else {
-        query = query.filter(configuration_entries::Column::UserId.is_null());
+    if rollout.deny_user_ids.iter().any(|x| x == user_id) {
+        return false;
+    }
+
+    if rollout.allow_user_ids.iter().any(|x| x == user_id)
+        || rollout.group_ids.iter().any(|x| group_ids.contains(x))
+    {
+        return true;
     }
 
-    query
+    // Buckets of a hundredth of a percent allow fractional percentages
+    let bucket = hash_configuration_key_rollout_user(key_name, user_id) % 10_000;
+
+    (bucket as f64) < rollout.percentage * 100.0
 }
 
-/// Parse a configuration value from a string.
+/// Apply the rollout rule of a key to its effective entry
+///
+/// Rollout rules only narrow down who sees a `true` value from the global or
+/// group items. A `false` value turns the key off for everyone, and user
+/// overrides are kept as they are. Users that are not selected by the rule as
+/// described by [`evaluate_configuration_key_rollout`] see `false` instead.
 ///
 /// # Arguments
 ///
-/// * `text` - The text to parse
-/// * `configuration_type` - The configuration type to parse the text as
+/// * `entry` - The effective configuration entry
+/// * `user_id` - The user id that the entry was resolved for
+/// * `group_ids` - The ids of the groups that the user is a member of
+pub fn apply_configuration_key_rollout(
+    entry: &mut ConfigurationEffectiveEntryResponse,
+    user_id: Option<&str>,
+    group_ids: &[String],
+) {
+    let Some(rollout) = &entry.key.rollout else {
+        return;
+    };
+
+    if !matches!(
+        entry.source,
+        ConfigurationEffectiveSource::Global | ConfigurationEffectiveSource::Group
+    ) || entry.value.as_ref().and_then(|value| value.as_boolean) != Some(true)
+    {
+        return;
+    }
+
+    if !evaluate_configuration_key_rollout(&entry.key.name, rollout, user_id, group_ids) {
+        let value = ConfigurationValueResponse {
+            as_boolean: Some(false),
+            ..Default::default()
+        };
+
+        entry.value = Some(value.clone());
+        entry.values = vec![value];
+    }
+
+    entry.source = ConfigurationEffectiveSource::Rollout;
+}
+
+/// Hash a user id for the rollout rule of a key.
+///
+/// This is 64-bit FNV-1a, which unlike the hashers of the standard library is
+/// stable across processes and releases.
+fn hash_configuration_key_rollout_user(key_name: &str, user_id: &str) -> u64 {
+    key_name
+        .bytes()
+        .chain([0])
+        .chain(user_id.bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Check the values that environment variables pin configuration keys to
+///
+/// This is meant to be called once when the server starts, so that a value
+/// that cannot be used stops the server instead of being skipped by every read.
+/// Variables for names that do not match an active key are logged and ignored,
+/// since their keys may be created later.
 ///
-/// # Returns
+/// # Arguments
 ///
-/// A configuration value response.
+/// * `key_set` - The set of already loaded configuration keys
+/// * `environment_overrides` - The values that environment variables pin keys
+///                             to by dotted key name, as loaded by
+///                             `config-env`
 ///
 /// # Errors
 ///
-/// Returns an error if the text cannot be parsed as the given configuration.
-fn parse_configuration_value(
+/// If a value cannot be parsed as its key's type or violates its key's value
+/// constraints, an error is returned.
+pub fn check_configuration_environment_overrides(
+    key_set: &ConfigurationKeySetResponse,
+    environment_overrides: &BTreeMap<String, String>,
+) -> Result<(), Error> {
+    for (name, text) in environment_overrides {
+        let Some(key) = find_configuration_environment_override_key(key_set, name) else {
+            warn!(
+                key_name = %name,
+                "configuration override does not match any configuration key"
+            );
+            continue;
+        };
+
+        parse_configuration_environment_override(key, text)
+            .map_err(|error| Error::ConfigurationOverrideInvalid(name.clone(), Box::new(error)))?;
+    }
+
+    Ok(())
+}
+
+/// Load the values that environment variables pin configuration keys to
+///
+/// The variables are read by `config-env` and take precedence over every
+/// stored value. They are matched to keys by name without regard to case, and
+/// are parsed and checked against the constraints of their key like stored
+/// values. Variables for names that do not match an active key are ignored.
+///
+/// Values are checked when the server starts by
+/// [`check_configuration_environment_overrides`]. A value that stops being
+/// valid later, such as when its key's type changes, is logged and ignored so
+/// that reading the configuration does not fail.
+///
+/// # Arguments
+///
+/// * `key_set` - The set of already loaded configuration keys
+/// * `environment_overrides` - The values that environment variables pin keys
+///                             to by dotted key name, as loaded by
+///                             `config-env`
+///
+/// # Returns
+///
+/// The pinned values by key id. Secret values are redacted.
+pub fn load_configuration_environment_overrides(
+    key_set: &ConfigurationKeySetResponse,
+    environment_overrides: &BTreeMap<String, String>,
+) -> HashMap<i32, ConfigurationValueResponse> {
+    environment_overrides
+        .iter()
+        .filter_map(|(name, text)| {
+            let key = find_configuration_environment_override_key(key_set, name)?;
+
+            match parse_configuration_environment_override(key, text) {
+                Ok(value) => Some((key.id, value)),
+                Err(error) => {
+                    warn!(
+                        key_name = %key.name,
+                        error = %error,
+                        "configuration override is invalid and is ignored"
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Find the key that an environment variable pins, without regard to case.
+fn find_configuration_environment_override_key<'a>(
+    key_set: &'a ConfigurationKeySetResponse,
+    name: &str,
+) -> Option<&'a ConfigurationKeyResponse> {
+    key_set
+        .iter()
+        .find(|key| key.name.eq_ignore_ascii_case(name))
+}
+
+/// Parse the value that an environment variable pins a key to and check it
+/// against the key's value constraints.
+fn parse_configuration_environment_override(
+    key: &ConfigurationKeyResponse,
     text: &str,
-    configuration_type: &ConfigurationTypeResponse,
 ) -> Result<ConfigurationValueResponse, Error> {
-    match configuration_type.name.as_str() {
-        "boolean" => Ok(ConfigurationValueResponse {
-            as_boolean: match text {
-                "true" => Ok(Some(true)),
-                "false" => Ok(Some(false)),
-                _ => Err(Error::ConfigurationValueParseErrorBoolean(text.to_owned())),
-            }?,
-            ..Default::default()
-        }),
-        "integer" => Ok(ConfigurationValueResponse {
-            as_integer: Some(text.parse::<i64>()?),
-            ..Default::default()
-        }),
-        "float" => Ok(ConfigurationValueResponse {
-            as_float: Some(text.parse::<f64>()?),
-            ..Default::default()
-        }),
-        "string" => Ok(ConfigurationValueResponse {
-            as_string: Some(text.to_owned()),
-            ..Default::default()
-        }),
-        _ => Err(Error::ConfigurationTypeNotFound(configuration_type.id)),
-    }
+    // Secret values are encrypted first so that they parse like stored ones
+    let text = encrypt_configuration_entry_text(key, text.to_owned())?;
+
+    let value = parse_configuration_value(&text, &key.configuration_type)?;
+
+    check_configuration_value_constraints(&key.name, &key.constraints, &value)?;
+
+    Ok(value)
+}
+
+/// Pin an effective configuration entry to a value from the environment
+///
+/// # Arguments
+///
+/// * `entry` - The effective configuration entry
+/// * `value` - The value that the entry's key is pinned to
+pub fn pin_effective_configuration_entry(
+    entry: &mut ConfigurationEffectiveEntryResponse,
+    value: ConfigurationValueResponse,
+) {
+    entry.source = ConfigurationEffectiveSource::Environment;
+    entry.value = if entry.key.allows_multiple {
+        None
+    } else {
+        Some(value.clone())
+    };
+    entry.values = vec![value];
+}
+
+/// Arrange configuration entries into a tree by the dotted segments of their
+/// key names
+///
+/// # Arguments
+///
+/// * `entry_set` - The configuration entries
+///
+/// # Returns
+///
+/// The root of the tree. Each entry is stored in the node reached by following
+/// the segments of its key name from the root, so a key can have both an entry
+/// and children.
+pub fn build_configuration_entry_tree(
+    entry_set: ConfigurationEntrySetResponse,
+) -> ConfigurationEntryTreeResponse {
+    let mut root = ConfigurationEntryTreeResponse::default();
+
+    for entry in entry_set {
+        let node = entry.key.name.split('.').fold(&mut root, |node, segment| {
+            node.children.entry(segment.to_owned()).or_default()
+        });
+
+        node.entry = Some(entry);
+    }
+
+    root
+}
+
+/// Find a configuration key by name within a set of already loaded keys
+///
+/// # Arguments
+///
+/// * `key_set` - The set of already loaded configuration keys
+/// * `name` - The name of the configuration key
+///
+/// # Returns
+///
+/// The configuration key.
+///
+/// # Errors
+///
+/// Returns an error if there is no configuration key with the given name.
+pub fn find_configuration_key_by_name<'key_set>(
+    key_set: &'key_set ConfigurationKeySetResponse,
+    name: &str,
+) -> Result<&'key_set ConfigurationKeyResponse, Error> {
+    key_set
+        .iter()
+        .find(|k| k.name == name)
+        .ok_or_else(|| Error::ConfigurationKeyNotFoundByName(name.to_owned()))
+}
+
+/// Append an item to a configuration entry
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key` - The already loaded configuration key
+/// * `user_id` - The user id to write the item for. If this value is null, the
+///               item is written as a global item.
+/// * `value` - The value of the new item
+///
+/// # Returns
+///
+/// The configuration entry after the item was inserted.
+///
+/// # Errors
+///
+/// Returns any database errors. If the value does not match the key's type, an
+/// error is returned. If the value violates the key's value constraints, an
+/// error is returned. If a user id is given and the key does not allow user
+/// overrides, an error is returned. If the key does not allow multiple values
+/// and already has an item, an error is returned.
+#[instrument(level = "debug", skip_all, fields(key = %key.name, user_id = user_id))]
+pub async fn insert_configuration_entry_item(
+    connection: &DatabaseConnection,
+    key: &ConfigurationKeyResponse,
+    user_id: Option<&str>,
+    value: &ConfigurationValueResponse,
+) -> Result<ConfigurationEntryResponse, Error> {
+    let scope = ConfigurationEntryScope::of_user(user_id);
+
+    check_user_override_allowed(key, &scope)?;
+
+    let text = encrypt_configuration_entry_text(
+        key,
+        format_configuration_value(value, &key.configuration_type)?,
+    )?;
+
+    check_configuration_entry_item_text(key, &text)?;
+
+    let transaction = begin_transaction(connection).await?;
+
+    lock_configuration_key_rows(&transaction, &[key.id]).await?;
+
+    let rows = configuration_entries::Entity::find()
+        .filter(configuration_entries::Column::KeyId.eq(key.id))
+        .filter(scope.condition())
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+        .order_by_desc(configuration_entries::Column::OrderIndex)
+        .all(&transaction)
+        .await?;
+
+    check_configuration_entry_item_count(
+        &key.name,
+        key.optional,
+        key.allows_multiple,
+        &scope,
+        rows.len() + 1,
+    )?;
+
+    let order_index = rows.first().map_or(1, |row| row.order_index + 1);
+
+    insert_configuration_entry_row(&transaction, key.id, order_index, &scope, text).await?;
+
+    let configuration_entry = get_configuration_entry_in_scope(&transaction, key, &scope).await?;
+
+    transaction.commit().await?;
+
+    Ok(configuration_entry)
+}
+
+/// Replace all of the items of a configuration entry
+///
+/// The existing items are deactivated and the new items are inserted in the
+/// order given.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key` - The already loaded configuration key
+/// * `user_id` - The user id to write the items for. If this value is null, the
+///               global items are replaced.
+/// * `values` - The values of the new items
+///
+/// # Returns
+///
+/// The configuration entry after the items were replaced.
+///
+/// # Errors
+///
+/// Returns any database errors. If any of the values do not match the key's
+/// type or violate its value constraints, an error is returned and nothing is
+/// written. If a user id is given and
+/// the key does not allow user overrides, an error is returned. If the number
+/// of values is not allowed by the key, an error is returned.
+#[instrument(level = "debug", skip_all, fields(key = %key.name, user_id = user_id))]
+pub async fn replace_configuration_entry_items(
+    connection: &DatabaseConnection,
+    key: &ConfigurationKeyResponse,
+    user_id: Option<&str>,
+    values: &[ConfigurationValueResponse],
+) -> Result<ConfigurationEntryResponse, Error> {
+    replace_configuration_entry_items_in_scope(
+        connection,
+        key,
+        &ConfigurationEntryScope::of_user(user_id),
+        values,
+    )
+    .await
+}
+
+/// Replace all of the items of a configuration entry in any scope
+///
+/// This is the same as `replace_configuration_entry_items`, except that group
+/// items can be written too.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key` - The already loaded configuration key
+/// * `scope` - The scope to write the items for
+/// * `values` - The values of the new items
+///
+/// # Returns
+///
+/// The configuration entry after the items were replaced, as it is seen from
+/// the scope.
+///
+/// # Errors
+///
+/// Returns the errors of `replace_configuration_entry_items`. If the scope is
+/// not global and the key does not allow user overrides, an error is returned.
+#[instrument(level = "debug", skip_all, fields(key = %key.name))]
+pub async fn replace_configuration_entry_items_in_scope(
+    connection: &DatabaseConnection,
+    key: &ConfigurationKeyResponse,
+    scope: &ConfigurationEntryScope,
+    values: &[ConfigurationValueResponse],
+) -> Result<ConfigurationEntryResponse, Error> {
+    check_user_override_allowed(key, scope)?;
+
+    check_configuration_entry_item_count(
+        &key.name,
+        key.optional,
+        key.allows_multiple,
+        scope,
+        values.len(),
+    )?;
+
+    let texts = values
+        .iter()
+        .map(|value| {
+            let text = encrypt_configuration_entry_text(
+                key,
+                format_configuration_value(value, &key.configuration_type)?,
+            )?;
+
+            check_configuration_entry_item_text(key, &text)?;
+
+            Ok(text)
+        })
+        .collect::<Result<Vec<String>, Error>>()?;
+
+    let transaction = begin_transaction(connection).await?;
+
+    lock_configuration_key_rows(&transaction, &[key.id]).await?;
+
+    deactivate_configuration_entry_rows(&transaction, key.id, scope).await?;
+
+    for (order_index, text) in (1..).zip(texts) {
+        insert_configuration_entry_row(&transaction, key.id, order_index, scope, text).await?;
+    }
+
+    let configuration_entry = get_configuration_entry_in_scope(&transaction, key, scope).await?;
+
+    transaction.commit().await?;
+
+    Ok(configuration_entry)
+}
+
+/// Deactivate all of the items of a configuration entry
+///
+/// Clearing user overrides is allowed even if the key does not allow user
+/// overrides, so that stale overrides can be cleaned up.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key` - The already loaded configuration key
+/// * `user_id` - The user id to deactivate the items for. If this value is
+///               null, the global items are deactivated.
+///
+/// # Returns
+///
+/// The configuration entry after the items were deactivated.
+///
+/// # Errors
+///
+/// Returns any database errors. If the global items of a key that is not
+/// optional are deactivated, an error is returned.
+#[instrument(level = "debug", skip_all, fields(key = %key.name, user_id = user_id))]
+pub async fn deactivate_configuration_entry_items(
+    connection: &DatabaseConnection,
+    key: &ConfigurationKeyResponse,
+    user_id: Option<&str>,
+) -> Result<ConfigurationEntryResponse, Error> {
+    deactivate_configuration_entry_items_in_scope(
+        connection,
+        key,
+        &ConfigurationEntryScope::of_user(user_id),
+    )
+    .await
+}
+
+/// Deactivate all of the items of a configuration entry in any scope
+///
+/// This is the same as `deactivate_configuration_entry_items`, except that
+/// group items can be deactivated too.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key` - The already loaded configuration key
+/// * `scope` - The scope to deactivate the items for
+///
+/// # Returns
+///
+/// The configuration entry after the items were deactivated, as it is seen
+/// from the scope.
+///
+/// # Errors
+///
+/// Returns the errors of `deactivate_configuration_entry_items`.
+#[instrument(level = "debug", skip_all, fields(key = %key.name))]
+pub async fn deactivate_configuration_entry_items_in_scope(
+    connection: &DatabaseConnection,
+    key: &ConfigurationKeyResponse,
+    scope: &ConfigurationEntryScope,
+) -> Result<ConfigurationEntryResponse, Error> {
+    check_configuration_entry_item_count(&key.name, key.optional, key.allows_multiple, scope, 0)?;
+
+    let transaction = begin_transaction(connection).await?;
+
+    lock_configuration_key_rows(&transaction, &[key.id]).await?;
+
+    deactivate_configuration_entry_rows(&transaction, key.id, scope).await?;
+
+    let configuration_entry = get_configuration_entry_in_scope(&transaction, key, scope).await?;
+
+    transaction.commit().await?;
+
+    Ok(configuration_entry)
+}
+
+/// Find all configuration entries in the database that violate the constraints
+/// of their keys.
+///
+/// This is meant to find legacy data that was written before the constraints
+/// were enforced, or that was written around the database layer.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key_set` - The set of already loaded configuration keys
+///
+/// # Returns
+///
+/// The list of violations, ordered by key.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_configuration_constraint_violations(
+    connection: &DatabaseConnection,
+    key_set: &ConfigurationKeySetResponse,
+) -> Result<Vec<ConfigurationConstraintViolation>, Error> {
+    // Group active entry ids by key and scope
+    let mut scopes: BTreeMap<(i32, ConfigurationEntryScope), Vec<i32>> = BTreeMap::new();
+
+    for row in configuration_entries::Entity::find()
+        .order_by_asc(configuration_entries::Column::KeyId)
+        .order_by_asc(configuration_entries::Column::OrderIndex)
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+        .all(connection)
+        .await?
+    {
+        scopes
+            .entry((row.key_id, ConfigurationEntryScope::of_row(&row)))
+            .or_default()
+            .push(row.id);
+    }
+
+    let mut violations = Vec::new();
+
+    for key in key_set {
+        if !key.optional && !scopes.contains_key(&(key.id, ConfigurationEntryScope::Global)) {
+            violations.push(ConfigurationConstraintViolation::Required {
+                key_name: key.name.clone(),
+            });
+        }
+
+        if !key.allows_multiple {
+            violations.extend(
+                scopes
+                    .iter()
+                    .filter(|((key_id, _), entry_ids)| *key_id == key.id && entry_ids.len() > 1)
+                    .map(|((_, scope), entry_ids)| {
+                        ConfigurationConstraintViolation::MultipleNotAllowed {
+                            key_name: key.name.clone(),
+                            scope: scope.clone(),
+                            entry_ids: entry_ids.clone(),
+                        }
+                    }),
+            );
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Make sure that the number of items in one scope of an entry is allowed by
+/// its key.
+///
+/// # Arguments
+///
+/// * `key_name` - The name of the configuration key
+/// * `optional` - Whether the configuration key is optional
+/// * `allows_multiple` - Whether the configuration key allows multiple values
+/// * `scope` - The scope of the items
+/// * `count` - The number of active items the scope would have after the write
+///
+/// # Errors
+///
+/// Returns an error if there would be more than one item for a key that does
+/// not allow multiple values, or if there would be no global items for a key
+/// that is not optional.
+pub(crate) fn check_configuration_entry_item_count(
+    key_name: &str,
+    optional: bool,
+    allows_multiple: bool,
+    scope: &ConfigurationEntryScope,
+    count: usize,
+) -> Result<(), Error> {
+    if count > 1 && !allows_multiple {
+        return Err(Error::ConfigurationKeyMultipleNotAllowed(
+            key_name.to_owned(),
+        ));
+    }
+
+    if count == 0 && !optional && scope == &ConfigurationEntryScope::Global {
+        return Err(Error::ConfigurationKeyRequired(key_name.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// Make sure that a value is allowed by the value constraints of its key.
+///
+/// The value is expected to already match the key's type and to be in its
+/// canonical form, as returned by `parse_configuration_value`.
+///
+/// # Arguments
+///
+/// * `key_name` - The name of the configuration key
+/// * `constraints` - The value constraints of the configuration key
+/// * `value` - The value to check
+///
+/// # Errors
+///
+/// Returns an error if a numeric value is outside of the minimum and maximum,
+/// if a string value does not match the pattern, or if the value is not one of
+/// the allowed values.
+fn check_configuration_value_constraints(
+    key_name: &str,
+    constraints: &ConfigurationKeyConstraintsResponse,
+    value: &ConfigurationValueResponse,
+) -> Result<(), Error> {
+    // Integers are compared as floats so that the same bounds work for both
+    // numeric types
+    let number = value
+        .as_float
+        .or_else(|| value.as_integer.map(|x| x as f64));
+
+    if let Some(number) = number {
+        if constraints
+            .minimum
+            .map_or(false, |minimum| number < minimum)
+            || constraints
+                .maximum
+                .map_or(false, |maximum| number > maximum)
+        {
+            return Err(Error::ConfigurationValueOutOfRange(key_name.to_owned()));
+        }
+    }
+
+    if let (Some(pattern), Some(text)) = (&constraints.pattern, &value.as_string) {
+        let regex = Regex::new(pattern).map_err(|err| {
+            Error::ConfigurationKeyConstraintsInvalid(key_name.to_owned(), err.to_string())
+        })?;
+
+        if !regex.is_match(text) {
+            return Err(Error::ConfigurationValuePatternMismatch(
+                key_name.to_owned(),
+            ));
+        }
+    }
+
+    if let Some(allowed_values) = &constraints.allowed_values {
+        if !allowed_values.contains(value) {
+            return Err(Error::ConfigurationValueNotAllowed(key_name.to_owned()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Prepare formatted configuration entry text for storage by encrypting it if
+/// the key is secret.
+///
+/// # Arguments
+///
+/// * `key` - The configuration key of the entry
+/// * `text` - The formatted text of the value
+///
+/// # Returns
+///
+/// The text as it should be stored in the database.
+///
+/// # Errors
+///
+/// Returns an error if the key is secret and the value cannot be encrypted.
+pub(crate) fn encrypt_configuration_entry_text(
+    key: &ConfigurationKeyResponse,
+    text: String,
+) -> Result<String, Error> {
+    match ConfigurationTypeKind::of(&key.configuration_type)? {
+        ConfigurationTypeKind::Secret => SecretCipher::from_configuration()?.encrypt(&text),
+        _ => Ok(text),
+    }
+}
+
+/// Parse a stored configuration entry value and make sure that it is allowed
+/// by its key.
+///
+/// # Arguments
+///
+/// * `key` - The configuration key of the entry
+/// * `text` - The stored text of the value
+///
+/// # Errors
+///
+/// Returns an error if the text does not parse as the key's type or if the
+/// value violates the key's value constraints.
+pub(crate) fn check_configuration_entry_item_text(
+    key: &ConfigurationKeyResponse,
+    text: &str,
+) -> Result<(), Error> {
+    let value = parse_configuration_value(text, &key.configuration_type)?;
+
+    check_configuration_value_constraints(&key.name, &key.constraints, &value)
+}
+
+/// Create a configuration type
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `request` - The new configuration type
+///
+/// # Returns
+///
+/// The newly created configuration type.
+///
+/// # Errors
+///
+/// Returns any database errors. If the name does not match a supported kind of
+/// value, an error is returned. If a configuration type with the same name
+/// already exists, even if it is deactivated, an error is returned.
+#[instrument(level = "debug", skip_all, fields(name = %request.name))]
+pub async fn create_configuration_type<C: ConnectionTrait + TransactionTrait>(
+    connection: &C,
+    request: &ConfigurationTypeCreateRequest,
+) -> Result<ConfigurationTypeResponse, Error> {
+    request.validate()?;
+
+    if ConfigurationTypeKind::from_name(&request.name).is_none() {
+        return Err(Error::ConfigurationTypeUnsupported(request.name.clone()));
+    }
+
+    let transaction = begin_transaction(connection).await?;
+
+    if configuration_type_reference::Entity::find()
+        .filter(configuration_type_reference::Column::Name.eq(request.name.as_str()))
+        .one(&transaction)
+        .await?
+        .is_some()
+    {
+        return Err(Error::ConfigurationTypeAlreadyExists(request.name.clone()));
+    }
+
+    let row = configuration_type_reference::ActiveModel {
+        name: Set(request.name.clone()),
+        description: Set(request.description.clone()),
+        ..Default::default()
+    }
+    .insert(&transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    build_configuration_type_response(row)
+}
+
+/// Update the description of a configuration type
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `id` - The id of the active configuration type
+/// * `request` - The new values
+///
+/// # Returns
+///
+/// The updated configuration type.
+///
+/// # Errors
+///
+/// Returns any database errors. If there is no active configuration type with
+/// the given id, an error is returned.
+#[instrument(level = "debug", skip_all, fields(id = id))]
+pub async fn update_configuration_type<C: ConnectionTrait + TransactionTrait>(
+    connection: &C,
+    id: i32,
+    request: &ConfigurationTypeUpdateRequest,
+) -> Result<ConfigurationTypeResponse, Error> {
+    request.validate()?;
+
+    let transaction = begin_transaction(connection).await?;
+
+    let mut row: configuration_type_reference::ActiveModel =
+        find_active_configuration_type_row(&transaction, id)
+            .await?
+            .into();
+
+    row.description = Set(request.description.clone());
+
+    let row = row.update(&transaction).await?;
+
+    transaction.commit().await?;
+
+    build_configuration_type_response(row)
+}
+
+/// Deactivate a configuration type
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `id` - The id of the active configuration type
+///
+/// # Errors
+///
+/// Returns any database errors. If there is no active configuration type with
+/// the given id, an error is returned. If any active configuration key still
+/// uses the type, an error is returned.
+#[instrument(level = "debug", skip_all, fields(id = id))]
+pub async fn deactivate_configuration_type<C: ConnectionTrait + TransactionTrait>(
+    connection: &C,
+    id: i32,
+) -> Result<(), Error> {
+    let transaction = begin_transaction(connection).await?;
+
+    let row = find_active_configuration_type_row(&transaction, id).await?;
+
+    if configuration_key_reference::Entity::find()
+        .filter(configuration_key_reference::Column::TypeId.eq(id))
+        .filter(configuration_key_reference::Column::DeactivateTimestamp.is_null())
+        .one(&transaction)
+        .await?
+        .is_some()
+    {
+        return Err(Error::ConfigurationTypeInUse(row.name));
+    }
+
+    let mut row: configuration_type_reference::ActiveModel = row.into();
+
+    row.deactivate_timestamp = Set(Some(Utc::now().naive_utc()));
+
+    row.update(&transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Create a configuration key
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `type_set` - The set of already loaded configuration types
+/// * `request` - The new configuration key
+///
+/// # Returns
+///
+/// The newly created configuration key.
+///
+/// # Errors
+///
+/// Returns any database errors. If a configuration key with the same name
+/// already exists, even if it is deactivated, an error is returned. If the type
+/// id is not in the set of configuration types, an error is returned. If the
+/// value constraints or the rollout rule are not valid for the type, an error
+/// is returned.
+#[instrument(level = "debug", skip_all, fields(name = %request.name))]
+pub async fn create_configuration_key<C: ConnectionTrait + TransactionTrait>(
+    connection: &C,
+    type_set: &ConfigurationTypeSetResponse,
+    request: &ConfigurationKeyCreateRequest,
+) -> Result<ConfigurationKeyResponse, Error> {
+    request.validate()?;
+
+    let configuration_type = type_set
+        .iter()
+        .find(|t| t.id == request.type_id)
+        .ok_or(Error::ConfigurationTypeNotFound(request.type_id))?;
+
+    let allowed_values = format_configuration_key_constraints(
+        &request.name,
+        configuration_type,
+        &request.constraints,
+    )?;
+
+    check_configuration_key_rollout(
+        &request.name,
+        configuration_type,
+        request.allows_multiple,
+        request.rollout.as_ref(),
+    )?;
+
+    let transaction = begin_transaction(connection).await?;
+
+    if configuration_key_reference::Entity::find()
+        .filter(configuration_key_reference::Column::Name.eq(request.name.as_str()))
+        .one(&transaction)
+        .await?
+        .is_some()
+    {
+        return Err(Error::ConfigurationKeyAlreadyExists(request.name.clone()));
+    }
+
+    let row = configuration_key_reference::ActiveModel {
+        name: Set(request.name.clone()),
+        description: Set(request.description.clone()),
+        type_id: Set(request.type_id),
+        optional: Set(request.optional),
+        allows_multiple: Set(request.allows_multiple),
+        allows_user_override: Set(request.allows_user_override),
+        minimum: Set(request.constraints.minimum),
+        maximum: Set(request.constraints.maximum),
+        pattern: Set(request.constraints.pattern.clone()),
+        allowed_values: Set(allowed_values),
+        rollout_percentage: Set(request.rollout.as_ref().map(|rollout| rollout.percentage)),
+        rollout_allow_user_ids: Set(request
+            .rollout
+            .as_ref()
+            .map(|rollout| rollout.allow_user_ids.clone())),
+        rollout_deny_user_ids: Set(request
+            .rollout
+            .as_ref()
+            .map(|rollout| rollout.deny_user_ids.clone())),
+        rollout_group_ids: Set(request
+            .rollout
+            .as_ref()
+            .map(|rollout| rollout.group_ids.clone())),
+        ..Default::default()
+    }
+    .insert(&transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    build_configuration_key_response(row, type_set)
+}
+
+/// Update the description, type, flags, value constraints and rollout rule of a
+/// configuration key
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `type_set` - The set of already loaded configuration types
+/// * `id` - The id of the active configuration key
+/// * `request` - The new values
+///
+/// # Returns
+///
+/// The updated configuration key.
+///
+/// # Errors
+///
+/// Returns any database errors. If there is no active configuration key with
+/// the given id, or if the type id is not in the set of configuration types, an
+/// error is returned. If the type is changed and any active value does not
+/// parse as the new type, an error is returned. If the value constraints or the
+/// rollout rule are not valid for the type, an error is returned. If the active
+/// values would violate the new flags or value constraints, an error is
+/// returned.
+#[instrument(level = "debug", skip_all, fields(id = id))]
+pub async fn update_configuration_key<C: ConnectionTrait + TransactionTrait>(
+    connection: &C,
+    type_set: &ConfigurationTypeSetResponse,
+    id: i32,
+    request: &ConfigurationKeyUpdateRequest,
+) -> Result<ConfigurationKeyResponse, Error> {
+    request.validate()?;
+
+    let configuration_type = type_set
+        .iter()
+        .find(|t| t.id == request.type_id)
+        .ok_or(Error::ConfigurationTypeNotFound(request.type_id))?;
+
+    let transaction = begin_transaction(connection).await?;
+
+    let row = find_active_configuration_key_row(&transaction, id).await?;
+
+    let allowed_values =
+        format_configuration_key_constraints(&row.name, configuration_type, &request.constraints)?;
+
+    check_configuration_key_rollout(
+        &row.name,
+        configuration_type,
+        request.allows_multiple,
+        request.rollout.as_ref(),
+    )?;
+
+    // Compare against the canonical allowed values, just like the stored values
+    let constraints = ConfigurationKeyConstraintsResponse {
+        allowed_values: parse_configuration_key_allowed_values(
+            allowed_values.as_deref(),
+            configuration_type,
+        )?,
+        ..request.constraints.clone()
+    };
+
+    let entry_rows = configuration_entries::Entity::find()
+        .filter(configuration_entries::Column::KeyId.eq(id))
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+        .all(&transaction)
+        .await?;
+
+    // Secret values cannot be converted to or from other types, because they
+    // are stored encrypted
+    let is_secret_change = row.type_id != request.type_id
+        && type_set
+            .iter()
+            .filter(|t| t.id == row.type_id || t.id == request.type_id)
+            .any(|t| {
+                ConfigurationTypeKind::from_name(&t.name) == Some(ConfigurationTypeKind::Secret)
+            });
+
+    if is_secret_change && !entry_rows.is_empty() {
+        return Err(Error::ConfigurationKeyTypeChangeIncompatible(row.name));
+    }
+
+    // Make sure the active values still parse if the type changes and that
+    // they do not violate the new value constraints
+    for entry_row in &entry_rows {
+        let value = parse_configuration_value(&entry_row.value, configuration_type)
+            .map_err(|_| Error::ConfigurationKeyTypeChangeIncompatible(row.name.clone()))?;
+
+        check_configuration_value_constraints(&row.name, &constraints, &value)?;
+    }
+
+    // Make sure the active values do not violate the new flags
+    let mut scope_counts: HashMap<ConfigurationEntryScope, usize> =
+        HashMap::from([(ConfigurationEntryScope::Global, 0)]);
+
+    for entry_row in &entry_rows {
+        *scope_counts
+            .entry(ConfigurationEntryScope::of_row(entry_row))
+            .or_default() += 1;
+    }
+
+    for (scope, count) in scope_counts {
+        check_configuration_entry_item_count(
+            &row.name,
+            request.optional,
+            request.allows_multiple,
+            &scope,
+            count,
+        )?;
+    }
+
+    let mut row: configuration_key_reference::ActiveModel = row.into();
+
+    row.description = Set(request.description.clone());
+    row.type_id = Set(request.type_id);
+    row.optional = Set(request.optional);
+    row.allows_multiple = Set(request.allows_multiple);
+    row.allows_user_override = Set(request.allows_user_override);
+    row.minimum = Set(request.constraints.minimum);
+    row.maximum = Set(request.constraints.maximum);
+    row.pattern = Set(request.constraints.pattern.clone());
+    row.allowed_values = Set(allowed_values);
+    row.rollout_percentage = Set(request.rollout.as_ref().map(|rollout| rollout.percentage));
+    row.rollout_allow_user_ids = Set(request
+        .rollout
+        .as_ref()
+        .map(|rollout| rollout.allow_user_ids.clone()));
+    row.rollout_deny_user_ids = Set(request
+        .rollout
+        .as_ref()
+        .map(|rollout| rollout.deny_user_ids.clone()));
+    row.rollout_group_ids = Set(request
+        .rollout
+        .as_ref()
+        .map(|rollout| rollout.group_ids.clone()));
+
+    let row = row.update(&transaction).await?;
+
+    transaction.commit().await?;
+
+    build_configuration_key_response(row, type_set)
+}
+
+/// Deactivate a configuration key along with all of its entries
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `id` - The id of the active configuration key
+///
+/// # Errors
+///
+/// Returns any database errors. If there is no active configuration key with
+/// the given id, an error is returned.
+#[instrument(level = "debug", skip_all, fields(id = id))]
+pub async fn deactivate_configuration_key<C: ConnectionTrait + TransactionTrait>(
+    connection: &C,
+    id: i32,
+) -> Result<(), Error> {
+    let transaction = begin_transaction(connection).await?;
+
+    let mut row: configuration_key_reference::ActiveModel =
+        find_active_configuration_key_row(&transaction, id)
+            .await?
+            .into();
+
+    let now = Utc::now().naive_utc();
+
+    configuration_entries::Entity::update_many()
+        .col_expr(
+            configuration_entries::Column::DeactivateTimestamp,
+            Expr::value(now),
+        )
+        .filter(configuration_entries::Column::KeyId.eq(id))
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+        .exec(&transaction)
+        .await?;
+
+    row.deactivate_timestamp = Set(Some(now));
+
+    row.update(&transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Find an active configuration type row by id.
+async fn find_active_configuration_type_row<C: ConnectionTrait>(
+    connection: &C,
+    id: i32,
+) -> Result<configuration_type_reference::Model, Error> {
+    configuration_type_reference::Entity::find_by_id(id)
+        .filter(configuration_type_reference::Column::DeactivateTimestamp.is_null())
+        .one(connection)
+        .await?
+        .ok_or(Error::ConfigurationTypeNotFound(id))
+}
+
+/// Find an active configuration key row by id.
+async fn find_active_configuration_key_row<C: ConnectionTrait>(
+    connection: &C,
+    id: i32,
+) -> Result<configuration_key_reference::Model, Error> {
+    configuration_key_reference::Entity::find_by_id(id)
+        .filter(configuration_key_reference::Column::DeactivateTimestamp.is_null())
+        .one(connection)
+        .await?
+        .ok_or(Error::ConfigurationKeyNotFound(id))
+}
+
+/// Build a validated configuration type response from a database row.
+pub(crate) fn build_configuration_type_response(
+    row: configuration_type_reference::Model,
+) -> Result<ConfigurationTypeResponse, Error> {
+    let configuration_type_response = ConfigurationTypeResponse {
+        id: row.id,
+        name: row.name,
+        description: row.description,
+    };
+
+    configuration_type_response.validate()?;
+
+    Ok(configuration_type_response)
+}
+
+/// Build a validated configuration key response from a database row.
+///
+/// # Errors
+///
+/// If the row's type id is not in the set of configuration types, an error is
+/// returned.
+pub(crate) fn build_configuration_key_response(
+    row: configuration_key_reference::Model,
+    type_set: &ConfigurationTypeSetResponse,
+) -> Result<ConfigurationKeyResponse, Error> {
+    let configuration_type = type_set
+        .iter()
+        .find(|t| t.id == row.type_id)
+        .ok_or(Error::ConfigurationTypeNotFound(row.type_id))?
+        .clone();
+
+    let allowed_values =
+        parse_configuration_key_allowed_values(row.allowed_values.as_deref(), &configuration_type)?;
+
+    // A rule is stored whenever the percentage is set
+    let rollout = row
+        .rollout_percentage
+        .map(|percentage| ConfigurationKeyRolloutResponse {
+            percentage,
+            allow_user_ids: row.rollout_allow_user_ids.unwrap_or_default(),
+            deny_user_ids: row.rollout_deny_user_ids.unwrap_or_default(),
+            group_ids: row.rollout_group_ids.unwrap_or_default(),
+        });
+
+    let configuration_key_response = ConfigurationKeyResponse {
+        id: row.id,
+        name: row.name,
+        description: row.description,
+        configuration_type,
+        optional: row.optional,
+        allows_multiple: row.allows_multiple,
+        allows_user_override: row.allows_user_override,
+        constraints: ConfigurationKeyConstraintsResponse {
+            minimum: row.minimum,
+            maximum: row.maximum,
+            pattern: row.pattern,
+            allowed_values,
+        },
+        rollout,
+    };
+
+    configuration_key_response.validate()?;
+
+    Ok(configuration_key_response)
+}
+
+/// Group configuration entry rows by key into configuration entries.
+///
+/// # Arguments
+///
+/// * `rows` - The configuration entry rows, ordered by key and order index
+/// * `key_set` - The set of already loaded configuration keys
+/// * `group_priorities` - The priorities of the groups that have one set, to
+///                        order the groups of each entry by precedence
+/// * `secret_cipher` - The cipher to decrypt secret values with. If this value
+///                     is null, secret values are redacted.
+///
+/// # Returns
+///
+/// A map from key id to configuration entry, with the groups of each entry in
+/// the order that their items take precedence. The items of keys with an
+/// unsupported type are skipped as described by
+/// [`is_configuration_key_readable`].
+///
+/// # Errors
+///
+/// If there is a configuration key id referenced that is not in the set of
+/// configuration keys, an error is returned. If any value cannot be parsed as
+/// its key's type, an error is returned.
+fn collect_configuration_entries(
+    rows: Vec<configuration_entries::Model>,
+    key_set: &ConfigurationKeySetResponse,
+    group_priorities: &HashMap<String, i32>,
+    secret_cipher: Option<&SecretCipher>,
+) -> Result<HashMap<i32, ConfigurationEntryResponse>, Error> {
+    // Create cache
+    let mut configuration_entries_map: HashMap<i32, ConfigurationEntryResponse> = HashMap::new();
+
+    // Whether the items of each key can be read by key id
+    let mut readable_key_ids: HashMap<i32, bool> = HashMap::new();
+
+    // Iterate over query response rows
+    for row in rows {
+        // Find the entry in the cache
+        let configuration_entries_map_entry = configuration_entries_map.entry(row.key_id);
+
+        // Populate it if it does not already exist
+        let configuration_entry = match configuration_entries_map_entry {
+            Entry::Occupied(configuration_entry_occupied) => {
+                configuration_entry_occupied.into_mut()
+            }
+            Entry::Vacant(configuration_entry_vacant) => {
+                let key = key_set
+                    .iter()
+                    .find(|k| k.id == row.key_id)
+                    .ok_or(Error::ConfigurationKeyNotFound(row.key_id))?;
+
+                // Skip keys of unsupported types instead of failing the read
+                if !*readable_key_ids
+                    .entry(row.key_id)
+                    .or_insert_with(|| is_configuration_key_readable(key))
+                {
+                    continue;
+                }
+
+                configuration_entry_vacant.insert(ConfigurationEntryResponse {
+                    key: key.clone(),
+                    items_global: Vec::new(),
+                    groups: Vec::new(),
+                    user: None,
+                })
+            }
+        };
+
+        // Create entry item from parsed text value
+        let value =
+            parse_configuration_value(&row.value, &configuration_entry.key.configuration_type)?;
+
+        let entry_item = ConfigurationEntryItemResponse {
+            id: row.id,
+            value: match secret_cipher {
+                Some(secret_cipher) if value.redacted => ConfigurationValueResponse {
+                    as_secret: Some(secret_cipher.decrypt(&row.value)?),
+                    ..Default::default()
+                },
+                _ => value,
+            },
+        };
+
+        // Push entry item into correct vector in entry
+        match ConfigurationEntryScope::of_row(&row) {
+            ConfigurationEntryScope::User(user_id) => {
+                let user = configuration_entry.user.get_or_insert_with(|| {
+                    ConfigurationEntryUserResponse {
+                        user_id,
+                        items: Vec::new(),
+                    }
+                });
+
+                user.items.push(entry_item);
+            }
+            ConfigurationEntryScope::Group(group_id) => {
+                match configuration_entry
+                    .groups
+                    .iter_mut()
+                    .find(|group| group.group_id == group_id)
+                {
+                    Some(group) => group.items.push(entry_item),
+                    None => configuration_entry
+                        .groups
+                        .push(ConfigurationEntryGroupResponse {
+                            group_id,
+                            items: vec![entry_item],
+                        }),
+                }
+            }
+            ConfigurationEntryScope::Global => configuration_entry.items_global.push(entry_item),
+        }
+    }
+
+    // Groups take precedence by priority, then in the order of their ids
+    for configuration_entry in configuration_entries_map.values_mut() {
+        configuration_entry.groups.sort_by(|a, b| {
+            groups::compare_configuration_group_precedence(
+                &a.group_id,
+                &b.group_id,
+                group_priorities,
+            )
+        });
+    }
+
+    Ok(configuration_entries_map)
+}
+
+/// Check whether the items of a configuration key can be read.
+///
+/// Types are only created for the supported kinds of values, but rows written
+/// around the database layer may still reference a type that is not supported.
+/// Reads skip the items of such keys and log a warning instead of failing.
+///
+/// # Arguments
+///
+/// * `key` - The configuration key
+///
+/// # Returns
+///
+/// Whether the key's type is supported.
+pub(crate) fn is_configuration_key_readable(key: &ConfigurationKeyResponse) -> bool {
+    let readable = ConfigurationTypeKind::from_name(&key.configuration_type.name).is_some();
+
+    if !readable {
+        warn!(
+            key_name = %key.name,
+            type_name = %key.configuration_type.name,
+            "configuration key has an unsupported type and its items are skipped"
+        );
+    }
+
+    readable
+}
+
+/// Make sure that the value constraints of a key are valid for its type and
+/// format its allowed values as they are stored in the database.
+///
+/// # Arguments
+///
+/// * `key_name` - The name of the configuration key
+/// * `configuration_type` - The configuration type of the key
+/// * `constraints` - The value constraints to check
+///
+/// # Returns
+///
+/// The formatted allowed values, if there are any.
+///
+/// # Errors
+///
+/// Returns an error if a minimum or maximum is given for a non-numeric type, if
+/// the minimum is greater than the maximum, if a pattern is given for a
+/// non-string type or does not compile, or if any allowed value does not match
+/// the type. Keys of the enum type must have allowed values, and keys of the
+/// secret type must not.
+fn format_configuration_key_constraints(
+    key_name: &str,
+    configuration_type: &ConfigurationTypeResponse,
+    constraints: &ConfigurationKeyConstraintsResponse,
+) -> Result<Option<Vec<String>>, Error> {
+    let invalid = |reason: &str| {
+        Error::ConfigurationKeyConstraintsInvalid(key_name.to_owned(), reason.to_owned())
+    };
+
+    let kind = ConfigurationTypeKind::of(configuration_type)?;
+
+    let is_numeric = matches!(
+        kind,
+        ConfigurationTypeKind::Integer | ConfigurationTypeKind::Float
+    );
+
+    if constraints.minimum.is_some() || constraints.maximum.is_some() {
+        if !is_numeric {
+            return Err(invalid("minimum and maximum require a numeric type"));
+        }
+
+        if constraints
+            .minimum
+            .iter()
+            .chain(constraints.maximum.iter())
+            .any(|x| !x.is_finite())
+        {
+            return Err(invalid("minimum and maximum must be finite"));
+        }
+    }
+
+    if let (Some(minimum), Some(maximum)) = (constraints.minimum, constraints.maximum) {
+        if minimum > maximum {
+            return Err(invalid("minimum is greater than maximum"));
+        }
+    }
+
+    if let Some(pattern) = &constraints.pattern {
+        if kind != ConfigurationTypeKind::String {
+            return Err(invalid("pattern requires the string type"));
+        }
+
+        Regex::new(pattern).map_err(|err| invalid(&err.to_string()))?;
+    }
+
+    if kind == ConfigurationTypeKind::Enum && constraints.allowed_values.is_none() {
+        return Err(invalid("the enum type requires allowed values"));
+    }
+
+    // Allowed values are stored in plaintext, so they would leak secrets
+    if kind == ConfigurationTypeKind::Secret && constraints.allowed_values.is_some() {
+        return Err(invalid("the secret type does not allow allowed values"));
+    }
+
+    constraints
+        .allowed_values
+        .as_ref()
+        .map(|allowed_values| {
+            allowed_values
+                .iter()
+                .map(|value| {
+                    format_configuration_value(value, configuration_type)
+                        .map_err(|_| invalid("allowed values must match the type"))
+                })
+                .collect::<Result<Vec<String>, Error>>()
+        })
+        .transpose()
+}
+
+/// Make sure that the rollout rule of a key is valid for its type.
+///
+/// # Arguments
+///
+/// * `key_name` - The name of the configuration key
+/// * `configuration_type` - The configuration type of the key
+/// * `allows_multiple` - Whether the key allows multiple values
+/// * `rollout` - The rollout rule to check, if there is one
+///
+/// # Errors
+///
+/// Returns an error if there is a rule and the key is not a single boolean
+/// value, or if its percentage is not between 0 and 100.
+fn check_configuration_key_rollout(
+    key_name: &str,
+    configuration_type: &ConfigurationTypeResponse,
+    allows_multiple: bool,
+    rollout: Option<&ConfigurationKeyRolloutResponse>,
+) -> Result<(), Error> {
+    let Some(rollout) = rollout else {
+        return Ok(());
+    };
+
+    let invalid = |reason: &str| {
+        Error::ConfigurationKeyRolloutInvalid(key_name.to_owned(), reason.to_owned())
+    };
+
+    if ConfigurationTypeKind::of(configuration_type)? != ConfigurationTypeKind::Boolean {
+        return Err(invalid("rollout rules require the boolean type"));
+    }
+
+    if allows_multiple {
+        return Err(invalid("rollout rules do not allow multiple values"));
+    }
+
+    if !(0.0..=100.0).contains(&rollout.percentage) {
+        return Err(invalid("percentage must be between 0 and 100"));
+    }
+
+    Ok(())
+}
+
+/// Parse the allowed values of a key as they are stored in the database.
+///
+/// # Arguments
+///
+/// * `allowed_values` - The formatted allowed values, if there are any
+/// * `configuration_type` - The configuration type of the key
+///
+/// # Errors
+///
+/// Returns an error if any allowed value cannot be parsed as the type.
+fn parse_configuration_key_allowed_values(
+    allowed_values: Option<&[String]>,
+    configuration_type: &ConfigurationTypeResponse,
+) -> Result<Option<Vec<ConfigurationValueResponse>>, Error> {
+    allowed_values
+        .map(|allowed_values| {
+            allowed_values
+                .iter()
+                .map(|text| parse_configuration_value(text, configuration_type))
+                .collect::<Result<Vec<ConfigurationValueResponse>, Error>>()
+        })
+        .transpose()
+}
+
+/// Make sure that a write is allowed for a key in the given scope.
+///
+/// # Arguments
+///
+/// * `key` - The configuration key being written
+/// * `scope` - The scope being written
+///
+/// # Errors
+///
+/// Returns an error if the scope is not global and the key does not allow user
+/// overrides.
+fn check_user_override_allowed(
+    key: &ConfigurationKeyResponse,
+    scope: &ConfigurationEntryScope,
+) -> Result<(), Error> {
+    if scope != &ConfigurationEntryScope::Global && !key.allows_user_override {
+        return Err(Error::ConfigurationKeyUserOverrideNotAllowed(
+            key.name.clone(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Lock the rows of configuration keys until the end of the transaction.
+///
+/// Writers of a key's entries take this lock before reading or deactivating
+/// the existing items, so that concurrent writes cannot pick the same order
+/// index and a deactivation cannot interleave with a replacement. The rows are
+/// locked in order of id so that writers of several keys cannot deadlock.
+///
+/// # Arguments
+///
+/// * `connection` - The transaction to hold the locks in
+/// * `key_ids` - The ids of the configuration keys
+///
+/// # Errors
+///
+/// Returns any database errors. If any of the configuration keys do not exist,
+/// an error is returned.
+async fn lock_configuration_key_rows<C: ConnectionTrait>(
+    connection: &C,
+    key_ids: &[i32],
+) -> Result<(), Error> {
+    let locked_ids = configuration_key_reference::Entity::find()
+        .filter(configuration_key_reference::Column::Id.is_in(key_ids.iter().copied()))
+        .order_by_asc(configuration_key_reference::Column::Id)
+        .lock_exclusive()
+        .all(connection)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<Vec<i32>>();
+
+    match key_ids.iter().find(|key_id| !locked_ids.contains(key_id)) {
+        Some(key_id) => Err(Error::ConfigurationKeyNotFound(*key_id)),
+        None => Ok(()),
+    }
+}
+
+/// Insert a single configuration entry row.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection or transaction
+/// * `key_id` - The id of the configuration key
+/// * `order_index` - The position of the item within the entry
+/// * `scope` - The scope of the item
+/// * `text` - The already formatted value
+///
+/// # Returns
+///
+/// The id of the new row.
+async fn insert_configuration_entry_row<C: ConnectionTrait>(
+    connection: &C,
+    key_id: i32,
+    order_index: i32,
+    scope: &ConfigurationEntryScope,
+    text: String,
+) -> Result<i32, Error> {
+    Ok(
+        configuration_entries::Entity::insert(configuration_entries::ActiveModel {
+            key_id: Set(key_id),
+            order_index: Set(order_index),
+            value: Set(text),
+            user_id: Set(scope.user_id().map(|x| x.to_owned())),
+            group_id: Set(scope.group_id().map(|x| x.to_owned())),
+            ..Default::default()
+        })
+        .exec(connection)
+        .await?
+        .last_insert_id,
+    )
+}
+
+/// Deactivate all active configuration entry rows for a key within a scope.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection or transaction
+/// * `key_id` - The id of the configuration key
+/// * `scope` - The scope whose rows to deactivate
+async fn deactivate_configuration_entry_rows<C: ConnectionTrait>(
+    connection: &C,
+    key_id: i32,
+    scope: &ConfigurationEntryScope,
+) -> Result<(), Error> {
+    configuration_entries::Entity::update_many()
+        .col_expr(
+            configuration_entries::Column::DeactivateTimestamp,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(configuration_entries::Column::KeyId.eq(key_id))
+        .filter(scope.condition())
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+        .exec(connection)
+        .await?;
+
+    Ok(())
+}
+
+/// Build a query that selects all configuration entries from the database as
+/// they are seen from a scope.
+///
+/// # Arguments
+///
+/// * `scope` - The scope to select the configuration entries for. Global
+///             entries are always selected. The entries of a group scope, or of
+///             a user scope and the groups that the user is a member of, are
+///             selected too.
+/// * `prefix` - The dotted key name prefix to select the configuration entries
+///              for. A key matches if its name is the prefix or starts with the
+///              prefix followed by a dot. If this value is null, all keys
+///              match.
+///
+/// # Errors
+///
+/// Returns an error if the prefix is not a valid key name.
+fn build_configuration_entries_query(
+    scope: &ConfigurationEntryScope,
+    prefix: Option<&str>,
+) -> Result<Select<configuration_entries::Entity>, Error> {
+    let mut query = configuration_entries::Entity::find()
+        .order_by_asc(configuration_entries::Column::KeyId)
+        .order_by_asc(configuration_entries::Column::OrderIndex)
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null());
+
+    query = query.filter(match scope {
+        ConfigurationEntryScope::Global => ConfigurationEntryScope::Global.condition(),
+        ConfigurationEntryScope::Group(_) => Condition::any()
+            .add(ConfigurationEntryScope::Global.condition())
+            .add(scope.condition()),
+        ConfigurationEntryScope::User(user_id) => Condition::any()
+            .add(ConfigurationEntryScope::Global.condition())
+            .add(scope.condition())
+            .add(
+                configuration_entries::Column::GroupId.in_subquery(
+                    Query::select()
+                        .column(configuration_group_members::Column::GroupId)
+                        .from(configuration_group_members::Entity)
+                        .cond_where(
+                            Condition::all()
+                                .add(
+                                    configuration_group_members::Column::UserId
+                                        .eq(user_id.as_str()),
+                                )
+                                .add(
+                                    configuration_group_members::Column::DeactivateTimestamp
+                                        .is_null(),
+                                ),
+                        )
+                        .to_owned(),
+                ),
+            ),
+    });
+
+    if let Some(prefix) = prefix {
+        let prefix = check_configuration_key_prefix(prefix)?;
+
+        // Underscores are wildcards in LIKE patterns
+        let pattern = format!("{}.%", prefix.replace('_', "\\_"));
+
+        query = query.filter(
+            configuration_entries::Column::KeyId.in_subquery(
+                Query::select()
+                    .column(configuration_key_reference::Column::Id)
+                    .from(configuration_key_reference::Entity)
+                    .cond_where(
+                        Condition::any()
+                            .add(configuration_key_reference::Column::Name.eq(prefix))
+                            .add(
+                                Expr::col(configuration_key_reference::Column::Name)
+                                    .like(LikeExpr::new(pattern).escape('\\')),
+                            ),
+                    )
+                    .to_owned(),
+            ),
+        );
+    }
+
+    Ok(query)
+}
+
+/// Make sure that a dotted key name prefix is valid.
+///
+/// # Returns
+///
+/// The prefix without a trailing dot.
+///
+/// # Errors
+///
+/// Returns an error if the prefix is not a valid key name.
+pub(crate) fn check_configuration_key_prefix(prefix: &str) -> Result<&str, Error> {
+    let prefix = prefix.strip_suffix('.').unwrap_or(prefix);
+
+    if CONFIGURATION_KEY_NAME_REGEX.is_match(prefix) {
+        Ok(prefix)
+    } else {
+        Err(Error::ConfigurationKeyNameInvalid(prefix.to_owned()))
+    }
+}
+
+/// Check whether a key name is a dotted prefix or starts with it followed by a
+/// dot.
+pub(crate) fn matches_configuration_key_prefix(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .map_or(false, |rest| rest.is_empty() || rest.starts_with('.'))
 }
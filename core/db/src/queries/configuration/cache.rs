@@ -0,0 +1,737 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! An in-process cache of the current configuration.
+//!
+//! Reading the configuration takes a query for each of the types, the keys and
+//! the entries. The cache keeps the results of those queries in a snapshot that
+//! is shared by every reader until one of the configuration tables changes.
+//! Changes are detected from the notifications sent by the tables' triggers,
+//! so writes made by other processes invalidate the cache as well.
+//!
+//! The cache is only kept while a [`ConfigurationCacheWatcher`] is running.
+//! Without one, every snapshot is loaded from the database. The watcher also
+//! passes each change on to the [`ConfigurationEventListener`]s subscribed to
+//! the cache.
+//!
+//! [`ConfigurationEventListener`]: super::events::ConfigurationEventListener
+
+use super::{
+    apply_configuration_key_rollout, check_configuration_key_prefix,
+    evaluate_configuration_key_rollout,
+    events::{listen_for_configuration_changes, ConfigurationChangeNotification},
+    get_all_configuration_keys, get_all_configuration_types,
+    groups::{compare_configuration_group_precedence, get_configuration_group_priorities},
+    is_configuration_key_readable, load_configuration_environment_overrides,
+    matches_configuration_key_prefix, pin_effective_configuration_entry,
+    resolve_effective_configuration_entry,
+    value::{parse_configuration_value, ConfigurationTypeKind},
+    ConfigurationEntryScope,
+};
+use crate::{
+    entities::{configuration_entries, configuration_group_members},
+    Error,
+};
+use domain_api::configuration::{
+    ConfigurationEffectiveEntryResponse, ConfigurationEffectiveSetResponse,
+    ConfigurationEntryGroupResponse, ConfigurationEntryItemResponse, ConfigurationEntryResponse,
+    ConfigurationEntrySetResponse, ConfigurationEntryUserResponse, ConfigurationKeyResponse,
+    ConfigurationKeySetResponse, ConfigurationTypeSetResponse, ConfigurationValueResponse,
+    CONFIGURATION_KEY_NAME_REGEX,
+};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+};
+use sqlx::postgres::PgListener;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError, RwLock,
+    },
+};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tracing::warn;
+
+/// The number of changes that are kept for each event listener before the
+/// slowest ones miss changes
+const CONFIGURATION_CHANGES_CAPACITY: usize = 1024;
+
+/// The active configuration as it was when it was loaded
+///
+/// Secret values are redacted.
+#[derive(Debug)]
+pub struct ConfigurationSnapshot {
+    types: ConfigurationTypeSetResponse,
+    keys: ConfigurationKeySetResponse,
+    /// The index of each key in `keys` by name
+    key_indices: HashMap<String, usize>,
+    /// The global items of each key by key id
+    items_global: HashMap<i32, Vec<ConfigurationEntryItemResponse>>,
+    /// The override items of each key by group id, then by key id
+    items_group: HashMap<String, HashMap<i32, Vec<ConfigurationEntryItemResponse>>>,
+    /// The override items of each key by user id, then by key id
+    items_user: HashMap<String, HashMap<i32, Vec<ConfigurationEntryItemResponse>>>,
+    /// The ids of the groups that each user is a member of by user id, in the
+    /// order that their items take precedence
+    group_ids: HashMap<String, Vec<String>>,
+    /// The values pinned by environment variables by key id
+    environment_overrides: HashMap<i32, ConfigurationValueResponse>,
+}
+
+impl ConfigurationSnapshot {
+    /// Load a snapshot of the active configuration from the database
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The database connection
+    /// * `environment_overrides` - The values that environment variables pin
+    ///                             keys to by dotted key name, as loaded by
+    ///                             `config-env`
+    ///
+    /// # Returns
+    ///
+    /// The snapshot, including the override items of every group and user, the
+    /// members of every group, and the values pinned by environment variables
+    /// as described by [`load_configuration_environment_overrides`]. The items
+    /// of keys with an unsupported type are skipped as described by
+    /// [`is_configuration_key_readable`].
+    ///
+    /// # Errors
+    ///
+    /// Returns any database errors. If any of the stored values cannot be
+    /// parsed as their key's type, an error is returned.
+    pub async fn load<C: ConnectionTrait>(
+        connection: &C,
+        environment_overrides: &BTreeMap<String, String>,
+    ) -> Result<Self, Error> {
+        let types = get_all_configuration_types(connection).await?;
+        let keys = get_all_configuration_keys(connection, &types).await?;
+
+        let key_indices = keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| (key.name.clone(), index))
+            .collect::<HashMap<String, usize>>();
+
+        let key_ids = keys
+            .iter()
+            .map(|key| (key.id, key))
+            .collect::<HashMap<i32, &ConfigurationKeyResponse>>();
+
+        let rows = configuration_entries::Entity::find()
+            .order_by_asc(configuration_entries::Column::KeyId)
+            .order_by_asc(configuration_entries::Column::OrderIndex)
+            .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+            .all(connection)
+            .await?;
+
+        let mut items_global: HashMap<i32, Vec<ConfigurationEntryItemResponse>> = HashMap::new();
+        let mut items_group: HashMap<String, HashMap<i32, Vec<ConfigurationEntryItemResponse>>> =
+            HashMap::new();
+        let mut items_user: HashMap<String, HashMap<i32, Vec<ConfigurationEntryItemResponse>>> =
+            HashMap::new();
+
+        // Whether the items of each key can be read by key id
+        let mut readable_key_ids: HashMap<i32, bool> = HashMap::new();
+
+        for row in rows {
+            let key = key_ids
+                .get(&row.key_id)
+                .ok_or(Error::ConfigurationKeyNotFound(row.key_id))?;
+
+            // Skip keys of unsupported types instead of failing the load
+            if !*readable_key_ids
+                .entry(row.key_id)
+                .or_insert_with(|| is_configuration_key_readable(key))
+            {
+                continue;
+            }
+
+            let item = ConfigurationEntryItemResponse {
+                id: row.id,
+                value: parse_configuration_value(&row.value, &key.configuration_type)?,
+            };
+
+            let items = match ConfigurationEntryScope::of_row(&row) {
+                ConfigurationEntryScope::User(user_id) => items_user.entry(user_id).or_default(),
+                ConfigurationEntryScope::Group(group_id) => {
+                    items_group.entry(group_id).or_default()
+                }
+                ConfigurationEntryScope::Global => &mut items_global,
+            };
+
+            items.entry(row.key_id).or_default().push(item);
+        }
+
+        let mut group_ids: HashMap<String, Vec<String>> = HashMap::new();
+
+        for row in configuration_group_members::Entity::find()
+            .filter(configuration_group_members::Column::DeactivateTimestamp.is_null())
+            .all(connection)
+            .await?
+        {
+            group_ids.entry(row.user_id).or_default().push(row.group_id);
+        }
+
+        let group_priorities = get_configuration_group_priorities(connection).await?;
+
+        for user_group_ids in group_ids.values_mut() {
+            user_group_ids
+                .sort_by(|a, b| compare_configuration_group_precedence(a, b, &group_priorities));
+        }
+
+        let environment_overrides =
+            load_configuration_environment_overrides(&keys, environment_overrides);
+
+        Ok(Self {
+            types,
+            keys,
+            key_indices,
+            items_global,
+            items_group,
+            items_user,
+            group_ids,
+            environment_overrides,
+        })
+    }
+
+    /// The active configuration types, ordered by id
+    pub fn types(&self) -> &ConfigurationTypeSetResponse {
+        &self.types
+    }
+
+    /// The active configuration keys, ordered by id
+    pub fn keys(&self) -> &ConfigurationKeySetResponse {
+        &self.keys
+    }
+
+    /// Find an active configuration key by name
+    ///
+    /// # Errors
+    ///
+    /// If the name does not match `CONFIGURATION_KEY_NAME_REGEX`, an error is
+    /// returned. If there is no active configuration key with the given name,
+    /// an error is returned.
+    pub fn key(&self, name: &str) -> Result<&ConfigurationKeyResponse, Error> {
+        if !CONFIGURATION_KEY_NAME_REGEX.is_match(name) {
+            return Err(Error::ConfigurationKeyNameInvalid(name.to_owned()));
+        }
+
+        self.key_indices
+            .get(name)
+            .map(|index| &self.keys[*index])
+            .ok_or_else(|| Error::ConfigurationKeyNotFoundByName(name.to_owned()))
+    }
+
+    /// Get the configuration entry for a single configuration key
+    ///
+    /// This is the cached equivalent of
+    /// [`get_configuration_entry`](super::get_configuration_entry).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The dotted name of the configuration key
+    /// * `user_id` - The user id to select the configuration entry for. If this
+    ///               value is null, return only global configuration entry
+    ///               items. Otherwise, the items of the user's groups are
+    ///               returned too.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ConfigurationSnapshot::key`].
+    pub fn entry(
+        &self,
+        name: &str,
+        user_id: Option<&str>,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        Ok(self.build_entry(self.key(name)?, &ConfigurationEntryScope::of_user(user_id)))
+    }
+
+    /// Get the configuration entry for a single configuration key as it is
+    /// seen from a scope
+    ///
+    /// This is the cached equivalent of
+    /// [`get_configuration_entry_in_scope`](super::get_configuration_entry_in_scope).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The dotted name of the configuration key
+    /// * `scope` - The scope to select the configuration entry for
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ConfigurationSnapshot::key`].
+    pub fn entry_in_scope(
+        &self,
+        name: &str,
+        scope: &ConfigurationEntryScope,
+    ) -> Result<ConfigurationEntryResponse, Error> {
+        Ok(self.build_entry(self.key(name)?, scope))
+    }
+
+    /// Get all configuration entries that have items
+    ///
+    /// This is the cached equivalent of
+    /// [`get_all_configuration_entries`](super::get_all_configuration_entries).
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user id to select the configuration entries for. If
+    ///               this value is null, return only global configuration
+    ///               entries. Otherwise, the items of the user's groups are
+    ///               returned too.
+    /// * `prefix` - The dotted key name prefix to select the configuration
+    ///              entries for. If this value is null, return entries for all
+    ///              keys.
+    ///
+    /// # Returns
+    ///
+    /// The set of configuration entries, ordered by key id.
+    ///
+    /// # Errors
+    ///
+    /// If the prefix is not a valid key name, an error is returned.
+    pub fn entries(
+        &self,
+        user_id: Option<&str>,
+        prefix: Option<&str>,
+    ) -> Result<ConfigurationEntrySetResponse, Error> {
+        let prefix = prefix.map(check_configuration_key_prefix).transpose()?;
+        let scope = ConfigurationEntryScope::of_user(user_id);
+
+        Ok(self
+            .keys
+            .iter()
+            .filter(|key| {
+                prefix.map_or(true, |prefix| {
+                    matches_configuration_key_prefix(&key.name, prefix)
+                })
+            })
+            .map(|key| self.build_entry(key, &scope))
+            .filter(|entry| {
+                !entry.items_global.is_empty() || !entry.groups.is_empty() || entry.user.is_some()
+            })
+            .collect())
+    }
+
+    /// Get the effective configuration for a user
+    ///
+    /// This is the cached equivalent of
+    /// [`get_effective_configuration`](super::get_effective_configuration).
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user id to resolve the configuration for. If this
+    ///               value is null, only global configuration entries are used.
+    ///               Otherwise, the entries of the user's groups are used too.
+    ///
+    /// # Returns
+    ///
+    /// The effective configuration, with one entry per key ordered by key id.
+    pub fn effective(&self, user_id: Option<&str>) -> ConfigurationEffectiveSetResponse {
+        self.keys
+            .iter()
+            .map(|key| self.build_effective_entry(key, user_id))
+            .collect()
+    }
+
+    /// Get the effective value of a single configuration key for a user
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The dotted name of the configuration key
+    /// * `user_id` - The user id to resolve the value for. If this value is
+    ///               null, only global configuration entry items are used.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ConfigurationSnapshot::key`].
+    pub fn effective_entry(
+        &self,
+        name: &str,
+        user_id: Option<&str>,
+    ) -> Result<ConfigurationEffectiveEntryResponse, Error> {
+        Ok(self.build_effective_entry(self.key(name)?, user_id))
+    }
+
+    /// Get the effective value of a boolean configuration key for a user
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The dotted name of the configuration key
+    /// * `user_id` - The user id to resolve the value for. If this value is
+    ///               null, only global configuration entry items are used.
+    ///
+    /// # Returns
+    ///
+    /// The value pinned by an environment variable if there is one. Otherwise,
+    /// the value of the winning items as described by
+    /// [`resolve_effective_configuration_entry`] and
+    /// [`apply_configuration_key_rollout`], or null if the key has no items. If
+    /// the key allows multiple values, the last one is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ConfigurationSnapshot::key`]. If the key is not
+    /// a boolean, an error is returned.
+    pub fn get_boolean(&self, name: &str, user_id: Option<&str>) -> Result<Option<bool>, Error> {
+        let key = self.key(name)?;
+
+        if ConfigurationTypeKind::of(&key.configuration_type)? != ConfigurationTypeKind::Boolean {
+            return Err(Error::ConfigurationValueTypeMismatch("boolean".to_owned()));
+        }
+
+        if let Some(value) = self.environment_overrides.get(&key.id) {
+            return Ok(value.as_boolean);
+        }
+
+        let last_value = |items: &Vec<ConfigurationEntryItemResponse>| {
+            items.last().and_then(|item| item.value.as_boolean)
+        };
+
+        if let Some(items) = user_id
+            .filter(|_| key.allows_user_override)
+            .and_then(|user_id| self.items_user.get(user_id))
+            .and_then(|items_user| items_user.get(&key.id))
+            .filter(|items| !items.is_empty())
+        {
+            return Ok(last_value(items));
+        }
+
+        let group_ids = self.user_group_ids(user_id);
+
+        let value = group_ids
+            .iter()
+            .filter(|_| key.allows_user_override)
+            .find_map(|group_id| {
+                self.items_group
+                    .get(group_id)?
+                    .get(&key.id)
+                    .filter(|items| !items.is_empty())
+            })
+            .or_else(|| self.items_global.get(&key.id))
+            .and_then(last_value);
+
+        Ok(match (&key.rollout, value) {
+            (Some(rollout), Some(true)) => Some(evaluate_configuration_key_rollout(
+                &key.name, rollout, user_id, group_ids,
+            )),
+            _ => value,
+        })
+    }
+
+    /// Build the effective entry of a key from its cached items and
+    /// environment variables.
+    fn build_effective_entry(
+        &self,
+        key: &ConfigurationKeyResponse,
+        user_id: Option<&str>,
+    ) -> ConfigurationEffectiveEntryResponse {
+        let mut entry = resolve_effective_configuration_entry(
+            self.build_entry(key, &ConfigurationEntryScope::of_user(user_id)),
+        );
+
+        apply_configuration_key_rollout(&mut entry, user_id, self.user_group_ids(user_id));
+
+        if let Some(value) = self.environment_overrides.get(&key.id) {
+            pin_effective_configuration_entry(&mut entry, value.clone());
+        }
+
+        entry
+    }
+
+    /// The ids of the groups that a user is a member of, in the order that
+    /// their items take precedence.
+    fn user_group_ids(&self, user_id: Option<&str>) -> &[String] {
+        user_id
+            .and_then(|user_id| self.group_ids.get(user_id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Build the entry of a key from its cached items as it is seen from a
+    /// scope.
+    fn build_entry(
+        &self,
+        key: &ConfigurationKeyResponse,
+        scope: &ConfigurationEntryScope,
+    ) -> ConfigurationEntryResponse {
+        let group_ids = match scope {
+            ConfigurationEntryScope::Global => &[][..],
+            ConfigurationEntryScope::Group(group_id) => std::slice::from_ref(group_id),
+            ConfigurationEntryScope::User(user_id) => self.user_group_ids(Some(user_id)),
+        };
+
+        ConfigurationEntryResponse {
+            key: key.clone(),
+            items_global: self.items_global.get(&key.id).cloned().unwrap_or_default(),
+            groups: group_ids
+                .iter()
+                .filter_map(|group_id| {
+                    self.items_group
+                        .get(group_id)
+                        .and_then(|items_group| items_group.get(&key.id))
+                        .map(|items| ConfigurationEntryGroupResponse {
+                            group_id: group_id.clone(),
+                            items: items.clone(),
+                        })
+                })
+                .collect(),
+            user: scope.user_id().and_then(|user_id| {
+                self.items_user
+                    .get(user_id)
+                    .and_then(|items_user| items_user.get(&key.id))
+                    .map(|items| ConfigurationEntryUserResponse {
+                        user_id: user_id.to_owned(),
+                        items: items.clone(),
+                    })
+            }),
+        }
+    }
+}
+
+/// A shared cache of the active configuration
+///
+/// Clones share the same cache, so it can be kept both in server state and in
+/// background tasks.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigurationCache {
+    state: Arc<ConfigurationCacheState>,
+}
+
+/// The state shared by the clones of a [`ConfigurationCache`]
+#[derive(Debug, Default)]
+struct ConfigurationCacheState {
+    /// The cached snapshot, or null if it has been invalidated
+    snapshot: RwLock<Option<Arc<ConfigurationSnapshot>>>,
+    /// Incremented whenever the cache is invalidated, so that snapshots loaded
+    /// across an invalidation are not kept
+    generation: AtomicU64,
+    /// The number of running watchers
+    watchers: AtomicUsize,
+    /// The channel that watchers pass changes on to event listeners through,
+    /// or null if no watcher is running
+    changes: Mutex<Option<Sender<ConfigurationChangeNotification>>>,
+    /// The values that environment variables pin keys to by dotted key name
+    environment_overrides: BTreeMap<String, String>,
+}
+
+impl ConfigurationCache {
+    /// Create an empty cache
+    ///
+    /// # Arguments
+    ///
+    /// * `environment_overrides` - The values that environment variables pin
+    ///                             keys to by dotted key name, as loaded by
+    ///                             `config-env`. Every snapshot is loaded with
+    ///                             them.
+    pub fn new(environment_overrides: BTreeMap<String, String>) -> Self {
+        Self {
+            state: Arc::new(ConfigurationCacheState {
+                environment_overrides,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Get a snapshot of the active configuration
+    ///
+    /// The cached snapshot is returned if there is one. Otherwise, a new one is
+    /// loaded and cached if a watcher is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The database connection to load the snapshot with
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ConfigurationSnapshot::load`].
+    pub async fn snapshot(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<Arc<ConfigurationSnapshot>, Error> {
+        if let Some(snapshot) = self
+            .state
+            .snapshot
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            return Ok(snapshot.clone());
+        }
+
+        let generation = self.state.generation.load(Ordering::SeqCst);
+
+        let snapshot = Arc::new(
+            ConfigurationSnapshot::load(connection, &self.state.environment_overrides).await?,
+        );
+
+        if self.state.watchers.load(Ordering::SeqCst) > 0 {
+            let mut cached = self
+                .state
+                .snapshot
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            if self.state.generation.load(Ordering::SeqCst) == generation {
+                *cached = Some(snapshot.clone());
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Drop the cached snapshot so that the next one is loaded from the
+    /// database
+    ///
+    /// Watchers do this when the configuration tables change. Call it directly
+    /// after writing to the configuration to read the write back without
+    /// waiting for its notification.
+    pub fn invalidate(&self) {
+        self.state.generation.fetch_add(1, Ordering::SeqCst);
+
+        *self
+            .state
+            .snapshot
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Start watching the configuration tables for changes
+    ///
+    /// The cache keeps snapshots from when this returns until the returned
+    /// watcher is dropped. The watcher must be run for changes to invalidate
+    /// the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The database connection
+    ///
+    /// # Errors
+    ///
+    /// Returns any database errors. If the connection is not a PostgreSQL
+    /// connection pool, an error is returned.
+    pub async fn watch(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<ConfigurationCacheWatcher, Error> {
+        let listener = listen_for_configuration_changes(connection).await?;
+
+        let changes = {
+            let mut changes = self
+                .state
+                .changes
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            self.state.watchers.fetch_add(1, Ordering::SeqCst);
+
+            changes
+                .get_or_insert_with(|| broadcast::channel(CONFIGURATION_CHANGES_CAPACITY).0)
+                .clone()
+        };
+
+        self.invalidate();
+
+        Ok(ConfigurationCacheWatcher {
+            listener,
+            changes,
+            cache: self.clone(),
+        })
+    }
+
+    /// Receive the changes that the running watchers see
+    ///
+    /// # Errors
+    ///
+    /// If no watcher is running, an error is returned.
+    pub(crate) fn subscribe(&self) -> Result<Receiver<ConfigurationChangeNotification>, Error> {
+        self.state
+            .changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(Sender::subscribe)
+            .ok_or(Error::ConfigurationChangesUnavailable)
+    }
+}
+
+/// Invalidates a [`ConfigurationCache`] whenever the configuration changes,
+/// and passes the changes on to its event listeners
+pub struct ConfigurationCacheWatcher {
+    listener: PgListener,
+    changes: Sender<ConfigurationChangeNotification>,
+    cache: ConfigurationCache,
+}
+
+impl ConfigurationCacheWatcher {
+    /// Invalidate the cache on every change until the listener fails
+    ///
+    /// # Errors
+    ///
+    /// Returns any database errors that happen while listening.
+    pub async fn run(mut self) -> Result<(), Error> {
+        loop {
+            // Notifications can be lost while the connection is down, so a
+            // reconnect counts as a change too
+            let notification = self.listener.try_recv().await?;
+
+            self.cache.invalidate();
+
+            let Some(notification) = notification else {
+                continue;
+            };
+
+            match serde_json::from_str::<ConfigurationChangeNotification>(notification.payload()) {
+                // Sending only fails if there are no event listeners
+                Ok(change) => {
+                    let _ = self.changes.send(change);
+                }
+                Err(error) => warn!(
+                    error = %error,
+                    payload = notification.payload(),
+                    "configuration change notification is invalid"
+                ),
+            }
+        }
+    }
+}
+
+impl Drop for ConfigurationCacheWatcher {
+    fn drop(&mut self) {
+        {
+            let mut changes = self
+                .cache
+                .state
+                .changes
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            // Event listeners are closed once the last watcher stops
+            if self.cache.state.watchers.fetch_sub(1, Ordering::SeqCst) == 1 {
+                *changes = None;
+            }
+        }
+
+        self.cache.invalidate();
+    }
+}
@@ -0,0 +1,962 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Declarative descriptions of the configuration.
+//!
+//! A configuration document lists every active configuration type, key and
+//! entry. It can be exported from the database, kept in version control, and
+//! applied back to a database. Applying a document plans the changes that make
+//! the database match it and writes them in a single transaction, so anything
+//! active that is not in the document is deactivated.
+//!
+//! Values are written as the text that is stored in the database, except that
+//! booleans and numbers may also be written as native values. Secret values are
+//! exported encrypted and may be written either encrypted or in plaintext.
+
+use super::{
+    check_configuration_entry_item_count, check_configuration_entry_item_text,
+    check_user_override_allowed, create_configuration_key, create_configuration_type,
+    deactivate_configuration_entry_rows, deactivate_configuration_key,
+    deactivate_configuration_type, encrypt_configuration_entry_text, get_all_configuration_keys,
+    get_all_configuration_types, insert_configuration_entry_row, lock_configuration_key_rows,
+    update_configuration_key, update_configuration_type,
+    value::{format_configuration_value, parse_configuration_value, ConfigurationTypeKind},
+    ConfigurationEntryScope,
+};
+use crate::{
+    entities::configuration_entries,
+    request_id::begin_transaction,
+    secrets::{is_encrypted, SecretCipher},
+    Error,
+};
+use domain_api::configuration::{
+    ConfigurationKeyConstraintsResponse, ConfigurationKeyCreateRequest, ConfigurationKeyResponse,
+    ConfigurationKeyRolloutResponse, ConfigurationKeyUpdateRequest, ConfigurationTypeCreateRequest,
+    ConfigurationTypeResponse, ConfigurationTypeUpdateRequest,
+};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::{self, Display},
+};
+use tracing::instrument;
+
+/// A declarative description of all active configuration types, keys and
+/// entries
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct ConfigurationDocument {
+    /// The configuration types
+    pub types: Vec<ConfigurationDocumentType>,
+    /// The configuration keys
+    #[serde(default)]
+    pub keys: Vec<ConfigurationDocumentKey>,
+    /// The configuration entries, at most one per key
+    #[serde(default)]
+    pub entries: Vec<ConfigurationDocumentEntry>,
+}
+
+/// A configuration type in a configuration document
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ConfigurationDocumentType {
+    /// The name of the type
+    pub name: String,
+    /// The description of the type
+    pub description: String,
+}
+
+/// A configuration key in a configuration document
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ConfigurationDocumentKey {
+    /// The dotted name of the key
+    pub name: String,
+    /// The description of the key
+    pub description: String,
+    /// The name of the type of the key
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// Whether the key may have no global items
+    #[serde(default)]
+    pub optional: bool,
+    /// Whether the key may have more than one item per scope
+    #[serde(default, rename = "allowsMultiple")]
+    pub allows_multiple: bool,
+    /// Whether users may override the global items
+    #[serde(default, rename = "allowsUserOverride")]
+    pub allows_user_override: bool,
+    /// The value constraints of the key
+    #[serde(
+        default,
+        skip_serializing_if = "ConfigurationDocumentKeyConstraints::is_empty"
+    )]
+    pub constraints: ConfigurationDocumentKeyConstraints,
+    /// The rollout rule of the key, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<ConfigurationKeyRolloutResponse>,
+}
+
+/// The value constraints of a configuration key in a configuration document
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct ConfigurationDocumentKeyConstraints {
+    /// The smallest allowed number, or the shortest allowed string length
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    /// The largest allowed number, or the longest allowed string length
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    /// A regular expression that string values must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The only values that are allowed
+    #[serde(rename = "allowedValues", skip_serializing_if = "Option::is_none")]
+    pub allowed_values: Option<Vec<ConfigurationDocumentValue>>,
+}
+
+impl ConfigurationDocumentKeyConstraints {
+    /// Check whether there are no constraints.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// The items of a configuration entry in a configuration document
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ConfigurationDocumentEntry {
+    /// The dotted name of the key
+    pub key: String,
+    /// The global items, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global: Vec<ConfigurationDocumentValue>,
+    /// The group override items by group id, in order
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Vec<ConfigurationDocumentValue>>,
+    /// The user override items by user id, in order
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub users: BTreeMap<String, Vec<ConfigurationDocumentValue>>,
+}
+
+/// A configuration value in a configuration document
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum ConfigurationDocumentValue {
+    /// A native boolean
+    Boolean(bool),
+    /// A native integer
+    Integer(i64),
+    /// A native floating-point number
+    Float(f64),
+    /// The text of any value as it is stored in the database
+    Text(String),
+}
+
+/// A single change that applying a configuration document makes
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConfigurationDocumentChange {
+    /// A configuration type is created
+    InsertType(ConfigurationDocumentType),
+    /// The description of a configuration type is updated
+    UpdateType(ConfigurationDocumentType),
+    /// A configuration type that is not in the document is deactivated
+    DeactivateType(String),
+    /// A configuration key is created
+    InsertKey(ConfigurationDocumentKey),
+    /// A configuration key is updated
+    UpdateKey(ConfigurationDocumentKey),
+    /// A configuration key that is not in the document is deactivated along
+    /// with its entries
+    DeactivateKey(String),
+    /// The items of one scope of a configuration entry are replaced
+    ReplaceEntryItems {
+        /// The name of the configuration key
+        key_name: String,
+        /// The scope of the items
+        scope: ConfigurationEntryScope,
+        /// Whether the values are secret and must not be displayed
+        secret: bool,
+        /// The stored text of the current items
+        old: Vec<String>,
+        /// The canonical text of the new items. If this is empty, the items
+        /// are only deactivated.
+        new: Vec<String>,
+    },
+}
+
+impl Display for ConfigurationDocumentChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigurationDocumentChange::InsertType(configuration_type) => {
+                write!(f, "+ type {}", configuration_type.name)
+            }
+            ConfigurationDocumentChange::UpdateType(configuration_type) => {
+                write!(f, "~ type {}", configuration_type.name)
+            }
+            ConfigurationDocumentChange::DeactivateType(name) => write!(f, "- type {name}"),
+            ConfigurationDocumentChange::InsertKey(key) => write!(f, "+ key {}", key.name),
+            ConfigurationDocumentChange::UpdateKey(key) => write!(f, "~ key {}", key.name),
+            ConfigurationDocumentChange::DeactivateKey(name) => write!(f, "- key {name}"),
+            ConfigurationDocumentChange::ReplaceEntryItems {
+                key_name,
+                scope,
+                secret,
+                old,
+                new,
+            } => {
+                let sign = match (old.is_empty(), new.is_empty()) {
+                    (true, _) => '+',
+                    (_, true) => '-',
+                    _ => '~',
+                };
+
+                let format_texts = |texts: &[String]| {
+                    texts
+                        .iter()
+                        .map(|text| match secret {
+                            true => "<secret>".to_owned(),
+                            false => format!("{text:#?}"),
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                };
+
+                write!(
+                    f,
+                    "{sign} entry {key_name} ({scope}): [{}] -> [{}]",
+                    format_texts(old),
+                    format_texts(new)
+                )
+            }
+        }
+    }
+}
+
+/// The changes that applying a configuration document makes, in the order that
+/// they are applied
+pub type ConfigurationDocumentPlan = Vec<ConfigurationDocumentChange>;
+
+/// Export all active configuration types, keys and entries as a configuration
+/// document
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+///
+/// # Returns
+///
+/// The configuration document, with types and keys ordered by id and entries
+/// ordered by key. Secret values are exported encrypted.
+///
+/// # Errors
+///
+/// Returns any database errors. If a stored value does not parse as its key's
+/// type, an error is returned.
+#[instrument(level = "debug", skip_all)]
+pub async fn export_configuration_document<C: ConnectionTrait>(
+    connection: &C,
+) -> Result<ConfigurationDocument, Error> {
+    let type_set = get_all_configuration_types(connection).await?;
+    let key_set = get_all_configuration_keys(connection, &type_set).await?;
+
+    let types = type_set
+        .iter()
+        .map(|configuration_type| ConfigurationDocumentType {
+            name: configuration_type.name.clone(),
+            description: configuration_type.description.clone(),
+        })
+        .collect();
+
+    let keys = key_set
+        .iter()
+        .map(build_document_key)
+        .collect::<Result<Vec<ConfigurationDocumentKey>, Error>>()?;
+
+    let scopes = find_active_entry_scopes(connection).await?;
+
+    let mut entries = Vec::new();
+
+    for key in &key_set {
+        let mut entry = ConfigurationDocumentEntry {
+            key: key.name.clone(),
+            global: Vec::new(),
+            groups: BTreeMap::new(),
+            users: BTreeMap::new(),
+        };
+
+        for ((key_id, scope), texts) in &scopes {
+            if *key_id != key.id {
+                continue;
+            }
+
+            let values = texts
+                .iter()
+                .map(|text| build_document_value(text, &key.configuration_type))
+                .collect::<Result<Vec<ConfigurationDocumentValue>, Error>>()?;
+
+            match scope {
+                ConfigurationEntryScope::User(user_id) => {
+                    entry.users.insert(user_id.clone(), values);
+                }
+                ConfigurationEntryScope::Group(group_id) => {
+                    entry.groups.insert(group_id.clone(), values);
+                }
+                ConfigurationEntryScope::Global => entry.global = values,
+            }
+        }
+
+        if !entry.global.is_empty() || !entry.groups.is_empty() || !entry.users.is_empty() {
+            entries.push(entry);
+        }
+    }
+
+    Ok(ConfigurationDocument {
+        types,
+        keys,
+        entries,
+    })
+}
+
+/// Plan the changes that make the database match a configuration document
+///
+/// Nothing is written. The plan is only valid for the state of the database
+/// that it was made against, so `apply_configuration_document` plans again
+/// within its transaction.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `document` - The configuration document
+///
+/// # Returns
+///
+/// The changes in the order that they would be applied.
+///
+/// # Errors
+///
+/// Returns any database errors. If the document describes a type, key or
+/// entry more than once, an error is returned. If a key uses a type or an
+/// entry uses a key that is not in the document, an error is returned. If a
+/// value does not parse as its key's type, or if a key that is not optional has
+/// no global items, an error is returned. If a secret
+/// value in plaintext has to be compared to a stored one and it cannot be
+/// decrypted, an error is returned.
+#[instrument(level = "debug", skip_all)]
+pub async fn plan_configuration_document<C: ConnectionTrait>(
+    connection: &C,
+    document: &ConfigurationDocument,
+) -> Result<ConfigurationDocumentPlan, Error> {
+    check_document_names(document.types.iter().map(|t| t.name.as_str()))?;
+    check_document_names(document.keys.iter().map(|key| key.name.as_str()))?;
+    check_document_names(document.entries.iter().map(|entry| entry.key.as_str()))?;
+
+    let type_set = get_all_configuration_types(connection).await?;
+    let key_set = get_all_configuration_keys(connection, &type_set).await?;
+
+    let mut type_changes = Vec::new();
+    let mut key_insert_changes = Vec::new();
+    let mut entry_changes = Vec::new();
+    let mut key_update_changes = Vec::new();
+    let mut deactivate_changes = Vec::new();
+
+    // Types
+    for document_type in &document.types {
+        match type_set.iter().find(|t| t.name == document_type.name) {
+            None => type_changes.push(ConfigurationDocumentChange::InsertType(
+                document_type.clone(),
+            )),
+            Some(configuration_type)
+                if configuration_type.description != document_type.description =>
+            {
+                type_changes.push(ConfigurationDocumentChange::UpdateType(
+                    document_type.clone(),
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Keys
+    for document_key in &document.keys {
+        let configuration_type = find_document_type(&document_key.type_name, &type_set, document)?;
+
+        match key_set.iter().find(|key| key.name == document_key.name) {
+            None => key_insert_changes
+                .push(ConfigurationDocumentChange::InsertKey(document_key.clone())),
+            Some(key)
+                if key
+                    != &build_document_key_response(document_key, key.id, &configuration_type)? =>
+            {
+                key_update_changes
+                    .push(ConfigurationDocumentChange::UpdateKey(document_key.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Entries
+    let scopes = find_active_entry_scopes(connection).await?;
+
+    let mut cipher = None;
+
+    for document_key in &document.keys {
+        let configuration_type = find_document_type(&document_key.type_name, &type_set, document)?;
+
+        let secret =
+            ConfigurationTypeKind::of(&configuration_type)? == ConfigurationTypeKind::Secret;
+
+        // Collect the canonical text of every scope in the document
+        let mut new_scopes: BTreeMap<ConfigurationEntryScope, Vec<String>> = BTreeMap::new();
+
+        if let Some(entry) = document
+            .entries
+            .iter()
+            .find(|entry| entry.key == document_key.name)
+        {
+            new_scopes.insert(
+                ConfigurationEntryScope::Global,
+                format_document_values(&entry.global, &configuration_type)?,
+            );
+
+            for (group_id, values) in &entry.groups {
+                new_scopes.insert(
+                    ConfigurationEntryScope::Group(group_id.clone()),
+                    format_document_values(values, &configuration_type)?,
+                );
+            }
+
+            for (user_id, values) in &entry.users {
+                new_scopes.insert(
+                    ConfigurationEntryScope::User(user_id.clone()),
+                    format_document_values(values, &configuration_type)?,
+                );
+            }
+        }
+
+        // Required keys need global items even if nothing changes
+        check_configuration_entry_item_count(
+            &document_key.name,
+            document_key.optional,
+            document_key.allows_multiple,
+            &ConfigurationEntryScope::Global,
+            new_scopes
+                .get(&ConfigurationEntryScope::Global)
+                .map_or(0, Vec::len),
+        )?;
+
+        // Collect the stored text of every scope in the database
+        let old_scopes = key_set
+            .iter()
+            .find(|key| key.name == document_key.name)
+            .map_or(BTreeMap::new(), |key| {
+                scopes
+                    .iter()
+                    .filter(|((key_id, _), _)| *key_id == key.id)
+                    .map(|((_, scope), texts)| (scope.clone(), texts.clone()))
+                    .collect::<BTreeMap<ConfigurationEntryScope, Vec<String>>>()
+            });
+
+        let all_scopes = new_scopes
+            .keys()
+            .chain(old_scopes.keys())
+            .cloned()
+            .collect::<BTreeSet<ConfigurationEntryScope>>();
+
+        for scope in all_scopes {
+            let old = old_scopes.get(&scope).cloned().unwrap_or_default();
+            let new = new_scopes.get(&scope).cloned().unwrap_or_default();
+
+            if !are_same_entry_texts(&old, &new, secret, &mut cipher)? {
+                entry_changes.push(ConfigurationDocumentChange::ReplaceEntryItems {
+                    key_name: document_key.name.clone(),
+                    scope,
+                    secret,
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+
+    for entry in &document.entries {
+        if !document.keys.iter().any(|key| key.name == entry.key) {
+            return Err(Error::ConfigurationKeyNotFoundByName(entry.key.clone()));
+        }
+    }
+
+    // Deactivations
+    for key in &key_set {
+        if !document.keys.iter().any(|x| x.name == key.name) {
+            deactivate_changes.push(ConfigurationDocumentChange::DeactivateKey(key.name.clone()));
+        }
+    }
+
+    for configuration_type in &type_set {
+        if !document
+            .types
+            .iter()
+            .any(|x| x.name == configuration_type.name)
+        {
+            deactivate_changes.push(ConfigurationDocumentChange::DeactivateType(
+                configuration_type.name.clone(),
+            ));
+        }
+    }
+
+    Ok(type_changes
+        .into_iter()
+        .chain(key_insert_changes)
+        .chain(entry_changes)
+        .chain(key_update_changes)
+        .chain(deactivate_changes)
+        .collect())
+}
+
+/// Make the database match a configuration document
+///
+/// The changes are planned and written in a single transaction. Entries are
+/// written before existing keys are updated, and both are checked against the
+/// keys as they are described by the document, so that a key and its entries
+/// can be changed together.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `document` - The configuration document
+/// * `dry_run` - Whether to roll the transaction back instead of committing it,
+///               so that the plan is checked without writing anything
+///
+/// # Returns
+///
+/// The changes that were applied.
+///
+/// # Errors
+///
+/// Returns any errors from `plan_configuration_document`. Returns any errors
+/// from writing the changes, for example when a value violates its key's
+/// constraints or a required key would have no global items. If any change
+/// fails, nothing is written.
+#[instrument(level = "debug", skip_all, fields(dry_run = dry_run))]
+pub async fn apply_configuration_document(
+    connection: &DatabaseConnection,
+    document: &ConfigurationDocument,
+    dry_run: bool,
+) -> Result<ConfigurationDocumentPlan, Error> {
+    let transaction = begin_transaction(connection).await?;
+
+    let plan = plan_configuration_document(&transaction, document).await?;
+
+    // Types
+    let type_set = get_all_configuration_types(&transaction).await?;
+
+    for change in &plan {
+        match change {
+            ConfigurationDocumentChange::InsertType(document_type) => {
+                create_configuration_type(
+                    &transaction,
+                    &ConfigurationTypeCreateRequest {
+                        name: document_type.name.clone(),
+                        description: document_type.description.clone(),
+                    },
+                )
+                .await?;
+            }
+            ConfigurationDocumentChange::UpdateType(document_type) => {
+                update_configuration_type(
+                    &transaction,
+                    find_document_type(&document_type.name, &type_set, document)?.id,
+                    &ConfigurationTypeUpdateRequest {
+                        description: document_type.description.clone(),
+                    },
+                )
+                .await?;
+            }
+            _ => {}
+        }
+    }
+
+    let type_set = get_all_configuration_types(&transaction).await?;
+
+    // New keys
+    for change in &plan {
+        if let ConfigurationDocumentChange::InsertKey(document_key) = change {
+            let configuration_type =
+                find_document_type(&document_key.type_name, &type_set, document)?;
+
+            create_configuration_key(
+                &transaction,
+                &type_set,
+                &ConfigurationKeyCreateRequest {
+                    name: document_key.name.clone(),
+                    description: document_key.description.clone(),
+                    type_id: configuration_type.id,
+                    optional: document_key.optional,
+                    allows_multiple: document_key.allows_multiple,
+                    allows_user_override: document_key.allows_user_override,
+                    constraints: build_document_key_constraints(
+                        &document_key.constraints,
+                        &configuration_type,
+                    )?,
+                    rollout: document_key.rollout.clone(),
+                },
+            )
+            .await?;
+        }
+    }
+
+    // Every key as it is described by the document
+    let key_set = get_all_configuration_keys(&transaction, &type_set).await?;
+
+    let mut keys = HashMap::new();
+
+    for document_key in &document.keys {
+        if let Some(key) = key_set.iter().find(|key| key.name == document_key.name) {
+            keys.insert(
+                document_key.name.as_str(),
+                build_document_key_response(
+                    document_key,
+                    key.id,
+                    &find_document_type(&document_key.type_name, &type_set, document)?,
+                )?,
+            );
+        }
+    }
+
+    // Entries
+    for change in &plan {
+        if let ConfigurationDocumentChange::ReplaceEntryItems {
+            key_name,
+            scope,
+            new,
+            ..
+        } = change
+        {
+            let key = keys
+                .get(key_name.as_str())
+                .ok_or_else(|| Error::ConfigurationKeyNotFoundByName(key_name.clone()))?;
+
+            if !new.is_empty() {
+                check_user_override_allowed(key, scope)?;
+            }
+
+            check_configuration_entry_item_count(
+                &key.name,
+                key.optional,
+                key.allows_multiple,
+                scope,
+                new.len(),
+            )?;
+
+            let texts = new
+                .iter()
+                .map(|text| {
+                    let text = match is_encrypted(text) {
+                        true => text.clone(),
+                        false => encrypt_configuration_entry_text(key, text.clone())?,
+                    };
+
+                    check_configuration_entry_item_text(key, &text)?;
+
+                    Ok(text)
+                })
+                .collect::<Result<Vec<String>, Error>>()?;
+
+            lock_configuration_key_rows(&transaction, &[key.id]).await?;
+
+            deactivate_configuration_entry_rows(&transaction, key.id, scope).await?;
+
+            for (order_index, text) in (1..).zip(texts) {
+                insert_configuration_entry_row(&transaction, key.id, order_index, scope, text)
+                    .await?;
+            }
+        }
+    }
+
+    // Updated keys and deactivations
+    for change in &plan {
+        match change {
+            ConfigurationDocumentChange::UpdateKey(document_key) => {
+                let key = keys.get(document_key.name.as_str()).ok_or_else(|| {
+                    Error::ConfigurationKeyNotFoundByName(document_key.name.clone())
+                })?;
+
+                update_configuration_key(
+                    &transaction,
+                    &type_set,
+                    key.id,
+                    &ConfigurationKeyUpdateRequest {
+                        description: key.description.clone(),
+                        type_id: key.configuration_type.id,
+                        optional: key.optional,
+                        allows_multiple: key.allows_multiple,
+                        allows_user_override: key.allows_user_override,
+                        constraints: key.constraints.clone(),
+                        rollout: key.rollout.clone(),
+                    },
+                )
+                .await?;
+            }
+            ConfigurationDocumentChange::DeactivateKey(name) => {
+                let key = key_set
+                    .iter()
+                    .find(|key| &key.name == name)
+                    .ok_or_else(|| Error::ConfigurationKeyNotFoundByName(name.clone()))?;
+
+                deactivate_configuration_key(&transaction, key.id).await?;
+            }
+            ConfigurationDocumentChange::DeactivateType(name) => {
+                let configuration_type = type_set
+                    .iter()
+                    .find(|t| &t.name == name)
+                    .ok_or_else(|| Error::ConfigurationTypeNotFoundByName(name.clone()))?;
+
+                deactivate_configuration_type(&transaction, configuration_type.id).await?;
+            }
+            _ => {}
+        }
+    }
+
+    match dry_run {
+        true => transaction.rollback().await?,
+        false => transaction.commit().await?,
+    }
+
+    Ok(plan)
+}
+
+/// Make sure that no name is described more than once.
+fn check_document_names<'name>(names: impl Iterator<Item = &'name str>) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+
+    for name in names {
+        if !seen.insert(name) {
+            return Err(Error::ConfigurationDocumentDuplicate(name.to_owned()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Find a configuration type that is described by a document.
+///
+/// Types that have not been created yet are returned without an id.
+fn find_document_type(
+    name: &str,
+    type_set: &[ConfigurationTypeResponse],
+    document: &ConfigurationDocument,
+) -> Result<ConfigurationTypeResponse, Error> {
+    let document_type = document
+        .types
+        .iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| Error::ConfigurationTypeNotFoundByName(name.to_owned()))?;
+
+    Ok(type_set
+        .iter()
+        .find(|t| t.name == name)
+        .cloned()
+        .unwrap_or_else(|| ConfigurationTypeResponse {
+            id: 0,
+            name: document_type.name.clone(),
+            description: document_type.description.clone(),
+        }))
+}
+
+/// Load the stored text of all active entry items, grouped by key and scope.
+async fn find_active_entry_scopes<C: ConnectionTrait>(
+    connection: &C,
+) -> Result<BTreeMap<(i32, ConfigurationEntryScope), Vec<String>>, Error> {
+    let mut scopes: BTreeMap<(i32, ConfigurationEntryScope), Vec<String>> = BTreeMap::new();
+
+    for row in configuration_entries::Entity::find()
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+        .order_by_asc(configuration_entries::Column::KeyId)
+        .order_by_asc(configuration_entries::Column::OrderIndex)
+        .all(connection)
+        .await?
+    {
+        scopes
+            .entry((row.key_id, ConfigurationEntryScope::of_row(&row)))
+            .or_default()
+            .push(row.value);
+    }
+
+    Ok(scopes)
+}
+
+/// Build the document description of a configuration key.
+fn build_document_key(key: &ConfigurationKeyResponse) -> Result<ConfigurationDocumentKey, Error> {
+    Ok(ConfigurationDocumentKey {
+        name: key.name.clone(),
+        description: key.description.clone(),
+        type_name: key.configuration_type.name.clone(),
+        optional: key.optional,
+        allows_multiple: key.allows_multiple,
+        allows_user_override: key.allows_user_override,
+        constraints: ConfigurationDocumentKeyConstraints {
+            minimum: key.constraints.minimum,
+            maximum: key.constraints.maximum,
+            pattern: key.constraints.pattern.clone(),
+            allowed_values: key
+                .constraints
+                .allowed_values
+                .as_ref()
+                .map(|allowed_values| {
+                    allowed_values
+                        .iter()
+                        .map(|value| {
+                            build_document_value(
+                                &format_configuration_value(value, &key.configuration_type)?,
+                                &key.configuration_type,
+                            )
+                        })
+                        .collect::<Result<Vec<ConfigurationDocumentValue>, Error>>()
+                })
+                .transpose()?,
+        },
+        rollout: key.rollout.clone(),
+    })
+}
+
+/// Build a configuration key response from its document description.
+fn build_document_key_response(
+    document_key: &ConfigurationDocumentKey,
+    id: i32,
+    configuration_type: &ConfigurationTypeResponse,
+) -> Result<ConfigurationKeyResponse, Error> {
+    Ok(ConfigurationKeyResponse {
+        id,
+        name: document_key.name.clone(),
+        description: document_key.description.clone(),
+        configuration_type: configuration_type.clone(),
+        optional: document_key.optional,
+        allows_multiple: document_key.allows_multiple,
+        allows_user_override: document_key.allows_user_override,
+        constraints: build_document_key_constraints(&document_key.constraints, configuration_type)?,
+        rollout: document_key.rollout.clone(),
+    })
+}
+
+/// Build the canonical value constraints from their document description.
+fn build_document_key_constraints(
+    constraints: &ConfigurationDocumentKeyConstraints,
+    configuration_type: &ConfigurationTypeResponse,
+) -> Result<ConfigurationKeyConstraintsResponse, Error> {
+    Ok(ConfigurationKeyConstraintsResponse {
+        minimum: constraints.minimum,
+        maximum: constraints.maximum,
+        pattern: constraints.pattern.clone(),
+        allowed_values: constraints
+            .allowed_values
+            .as_ref()
+            .map(|allowed_values| {
+                format_document_values(allowed_values, configuration_type)?
+                    .iter()
+                    .map(|text| parse_configuration_value(text, configuration_type))
+                    .collect()
+            })
+            .transpose()?,
+    })
+}
+
+/// Build the document value for stored text, using native values where the
+/// type allows.
+fn build_document_value(
+    text: &str,
+    configuration_type: &ConfigurationTypeResponse,
+) -> Result<ConfigurationDocumentValue, Error> {
+    let value = parse_configuration_value(text, configuration_type)?;
+
+    Ok(if let Some(x) = value.as_boolean {
+        ConfigurationDocumentValue::Boolean(x)
+    } else if let Some(x) = value.as_integer {
+        ConfigurationDocumentValue::Integer(x)
+    } else if let Some(x) = value.as_float {
+        ConfigurationDocumentValue::Float(x)
+    } else {
+        ConfigurationDocumentValue::Text(text.to_owned())
+    })
+}
+
+/// Format document values as their canonical text.
+///
+/// Secret values are left as they are, whether they are encrypted or not.
+fn format_document_values(
+    values: &[ConfigurationDocumentValue],
+    configuration_type: &ConfigurationTypeResponse,
+) -> Result<Vec<String>, Error> {
+    let kind = ConfigurationTypeKind::of(configuration_type)?;
+
+    values
+        .iter()
+        .map(|value| {
+            let text = match value {
+                ConfigurationDocumentValue::Boolean(x) => x.to_string(),
+                ConfigurationDocumentValue::Integer(x) => x.to_string(),
+                ConfigurationDocumentValue::Float(x) => x.to_string(),
+                ConfigurationDocumentValue::Text(x) => x.clone(),
+            };
+
+            match kind {
+                ConfigurationTypeKind::Secret => Ok(text),
+                _ => format_configuration_value(
+                    &parse_configuration_value(&text, configuration_type)?,
+                    configuration_type,
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Check whether stored items have the same values as items from a document.
+///
+/// Secret values from a document that are in plaintext are compared to the
+/// decrypted stored values.
+fn are_same_entry_texts(
+    old: &[String],
+    new: &[String],
+    secret: bool,
+    cipher: &mut Option<SecretCipher>,
+) -> Result<bool, Error> {
+    if old.len() != new.len() {
+        return Ok(false);
+    }
+
+    for (old, new) in old.iter().zip(new) {
+        let same = if secret && !is_encrypted(new) {
+            if cipher.is_none() {
+                *cipher = Some(SecretCipher::from_configuration()?);
+            }
+
+            cipher.as_ref().map_or(Ok(false), |cipher| {
+                cipher.decrypt(old).map(|old| &old == new)
+            })?
+        } else {
+            old == new
+        };
+
+        if !same {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,232 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Streaming changes to the configuration as they are committed.
+//!
+//! The configuration tables have triggers that send a notification on the
+//! `configuration_changes` channel for every row that is written. The
+//! [`ConfigurationCacheWatcher`] receives them once the writing transaction
+//! commits and passes them on to every listener, which turns them into events
+//! that describe which key or entry changed along with its new state. All
+//! listeners share the watcher's connection, so they do not hold connections
+//! from the pool.
+//!
+//! [`ConfigurationCacheWatcher`]: super::cache::ConfigurationCacheWatcher
+
+use super::{
+    cache::ConfigurationCache, get_configuration_entry, get_configuration_key_by_name,
+    groups::get_configuration_group_ids, ConfigurationEntryScope,
+};
+use crate::{
+    entities::{configuration_entries, configuration_key_reference},
+    queries::configuration::{check_configuration_key_prefix, matches_configuration_key_prefix},
+    Error,
+};
+use domain_api::configuration::{ConfigurationEventKind, ConfigurationEventResponse};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::Deserialize;
+use sqlx::postgres::PgListener;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+/// The channel that changes to the configuration tables are sent on. This must
+/// match the channel used by the migrations.
+pub const CONFIGURATION_CHANGES_CHANNEL: &str = "configuration_changes";
+
+/// The payload of a notification sent by the configuration tables
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ConfigurationChangeNotification {
+    /// The name of the table that was written
+    table: String,
+    /// The id of the row that was written
+    id: i32,
+}
+
+/// A listener for changes to the configuration, as seen by a single caller
+pub struct ConfigurationEventListener {
+    changes: Receiver<ConfigurationChangeNotification>,
+    user_id: Option<String>,
+    prefix: Option<String>,
+}
+
+impl ConfigurationEventListener {
+    /// Start listening for changes to the configuration.
+    ///
+    /// Changes are only received once this returns, so any state that is read
+    /// afterwards is never older than the events that follow it.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The cache whose watcher receives the changes
+    /// * `user_id` - The user id of the caller. Changes to the overrides of
+    ///               other users and of groups that the caller is not a member
+    ///               of are skipped.
+    /// * `prefix` - The dotted key name prefix to receive changes for. If this
+    ///              value is null, changes to all keys are received.
+    ///
+    /// # Errors
+    ///
+    /// If the prefix is not a valid key name, an error is returned. If no
+    /// watcher is running for the cache, an error is returned.
+    pub fn subscribe(
+        cache: &ConfigurationCache,
+        user_id: Option<&str>,
+        prefix: Option<&str>,
+    ) -> Result<Self, Error> {
+        let prefix = prefix.map(check_configuration_key_prefix).transpose()?;
+
+        Ok(Self {
+            changes: cache.subscribe()?,
+            user_id: user_id.map(|x| x.to_owned()),
+            prefix: prefix.map(|x| x.to_owned()),
+        })
+    }
+
+    /// Wait for the next change that the caller can see.
+    ///
+    /// Changes to configuration types, to group memberships, to keys outside
+    /// of the prefix, and to the overrides of other users and groups are
+    /// skipped. Every item that is written causes its own event, so replacing
+    /// the items of an entry causes more than one.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The database connection to read the new state with
+    ///
+    /// # Returns
+    ///
+    /// The event, including the entry as the caller sees it after the change.
+    ///
+    /// # Errors
+    ///
+    /// Returns any database errors. If the listener falls so far behind that
+    /// changes are dropped, an error is returned. If the watcher stops, an
+    /// error is returned.
+    pub async fn next_event(
+        &mut self,
+        connection: &DatabaseConnection,
+    ) -> Result<ConfigurationEventResponse, Error> {
+        loop {
+            let notification = match self.changes.recv().await {
+                Ok(notification) => notification,
+                Err(RecvError::Lagged(count)) => {
+                    return Err(Error::ConfigurationChangesLagged(count))
+                }
+                Err(RecvError::Closed) => return Err(Error::ConfigurationChangesUnavailable),
+            };
+
+            if let Some(event) = self.build_event(connection, &notification).await? {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Build the event for a notification, or return `None` if the caller
+    /// cannot see it.
+    async fn build_event(
+        &self,
+        connection: &DatabaseConnection,
+        notification: &ConfigurationChangeNotification,
+    ) -> Result<Option<ConfigurationEventResponse>, Error> {
+        let (kind, key_id, scope) = match notification.table.as_str() {
+            "configuration_key_reference" => (
+                ConfigurationEventKind::Key,
+                notification.id,
+                ConfigurationEntryScope::Global,
+            ),
+            "configuration_entries" => {
+                match configuration_entries::Entity::find_by_id(notification.id)
+                    .one(connection)
+                    .await?
+                {
+                    Some(row) => (
+                        ConfigurationEventKind::Entry,
+                        row.key_id,
+                        ConfigurationEntryScope::of_row(&row),
+                    ),
+                    None => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        match (&scope, &self.user_id) {
+            (ConfigurationEntryScope::Global, _) => {}
+            (ConfigurationEntryScope::User(user_id), Some(caller)) if user_id == caller => {}
+            (ConfigurationEntryScope::Group(group_id), Some(caller))
+                if get_configuration_group_ids(connection, caller)
+                    .await?
+                    .contains(group_id) => {}
+            _ => return Ok(None),
+        }
+
+        let Some(key_row) = configuration_key_reference::Entity::find_by_id(key_id)
+            .one(connection)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        if let Some(prefix) = &self.prefix {
+            if !matches_configuration_key_prefix(&key_row.name, prefix) {
+                return Ok(None);
+            }
+        }
+
+        let entry = match key_row.deactivate_timestamp {
+            Some(_) => None,
+            None => {
+                let key = get_configuration_key_by_name(connection, &key_row.name).await?;
+
+                Some(get_configuration_entry(connection, &key, self.user_id.as_deref()).await?)
+            }
+        };
+
+        Ok(Some(ConfigurationEventResponse {
+            kind,
+            key_name: key_row.name,
+            user_id: scope.user_id().map(|x| x.to_owned()),
+            group_id: scope.group_id().map(|x| x.to_owned()),
+            entry,
+        }))
+    }
+}
+
+/// Start listening for the notifications sent by the configuration tables on a
+/// dedicated connection.
+///
+/// # Errors
+///
+/// Returns any database errors. If the connection is not a PostgreSQL
+/// connection pool, an error is returned.
+pub(crate) async fn listen_for_configuration_changes(
+    connection: &DatabaseConnection,
+) -> Result<PgListener, Error> {
+    let DatabaseConnection::SqlxPostgresPoolConnection(_) = connection else {
+        return Err(Error::ConfigurationChangesUnavailable);
+    };
+
+    let mut listener = PgListener::connect_with(connection.get_postgres_connection_pool()).await?;
+
+    listener.listen(CONFIGURATION_CHANGES_CHANNEL).await?;
+
+    Ok(listener)
+}
@@ -0,0 +1,381 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Groups of users that share configuration overrides.
+//!
+//! Group items override the global items of a configuration entry for every
+//! member of the group, and are themselves overridden by the items of each
+//! member. Membership is kept in the `configuration_group_members` table, so
+//! groups from an identity provider can be mirrored into it.
+//!
+//! When a user is a member of several groups that override the same key, the
+//! group with the highest priority wins. Priorities are kept in the
+//! `configuration_groups` table, and groups without a row there have a
+//! priority of [`DEFAULT_CONFIGURATION_GROUP_PRIORITY`]. Groups with the same
+//! priority take precedence in the order of their ids.
+
+use crate::{
+    entities::{configuration_group_members, configuration_groups},
+    request_id::begin_transaction,
+    Error,
+};
+use chrono::Utc;
+use domain_api::configuration::{
+    ConfigurationGroupResponse, ConfigurationGroupSetResponse, ConfigurationGroupUpdateRequest,
+};
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Select,
+    Set,
+};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+};
+use tracing::instrument;
+
+/// The priority of groups that have never had one set
+pub const DEFAULT_CONFIGURATION_GROUP_PRIORITY: i32 = 0;
+
+/// Get all groups that have members or a priority from the database
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+///
+/// # Returns
+///
+/// The set of groups ordered by id, with their members ordered by user id.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_all_configuration_groups<C: ConnectionTrait>(
+    connection: &C,
+) -> Result<ConfigurationGroupSetResponse, Error> {
+    let priorities = get_configuration_group_priorities(connection).await?;
+
+    let mut groups: BTreeMap<String, Vec<String>> = priorities
+        .keys()
+        .map(|group_id| (group_id.clone(), Vec::new()))
+        .collect();
+
+    for row in find_active_configuration_group_member_rows()
+        .all(connection)
+        .await?
+    {
+        groups.entry(row.group_id).or_default().push(row.user_id);
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(group_id, user_ids)| ConfigurationGroupResponse {
+            priority: get_configuration_group_priority(&priorities, &group_id),
+            group_id,
+            user_ids,
+        })
+        .collect())
+}
+
+/// Get a single group from the database
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `group_id` - The id of the group
+///
+/// # Returns
+///
+/// The group with its priority and its members ordered by user id. A group
+/// without members is returned with no user ids.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all, fields(group_id = group_id))]
+pub async fn get_configuration_group<C: ConnectionTrait>(
+    connection: &C,
+    group_id: &str,
+) -> Result<ConfigurationGroupResponse, Error> {
+    Ok(ConfigurationGroupResponse {
+        group_id: group_id.to_owned(),
+        priority: configuration_groups::Entity::find()
+            .filter(configuration_groups::Column::GroupId.eq(group_id))
+            .one(connection)
+            .await?
+            .map_or(DEFAULT_CONFIGURATION_GROUP_PRIORITY, |row| row.priority),
+        user_ids: find_active_configuration_group_member_rows()
+            .filter(configuration_group_members::Column::GroupId.eq(group_id))
+            .all(connection)
+            .await?
+            .into_iter()
+            .map(|row| row.user_id)
+            .collect(),
+    })
+}
+
+/// Get the ids of the groups that a user is a member of from the database
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `user_id` - The id of the user
+///
+/// # Returns
+///
+/// The group ids in the order that their items take precedence, as described
+/// by [`compare_configuration_group_precedence`].
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all, fields(user_id = user_id))]
+pub async fn get_configuration_group_ids<C: ConnectionTrait>(
+    connection: &C,
+    user_id: &str,
+) -> Result<Vec<String>, Error> {
+    let priorities = get_configuration_group_priorities(connection).await?;
+
+    let mut group_ids = find_active_configuration_group_member_rows()
+        .filter(configuration_group_members::Column::UserId.eq(user_id))
+        .all(connection)
+        .await?
+        .into_iter()
+        .map(|row| row.group_id)
+        .collect::<Vec<String>>();
+
+    group_ids.sort_by(|a, b| compare_configuration_group_precedence(a, b, &priorities));
+
+    Ok(group_ids)
+}
+
+/// Get the priority of every group that has one set from the database
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+///
+/// # Returns
+///
+/// A map from group id to priority. Groups that are not in the map have the
+/// default priority.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_configuration_group_priorities<C: ConnectionTrait>(
+    connection: &C,
+) -> Result<HashMap<String, i32>, Error> {
+    Ok(configuration_groups::Entity::find()
+        .all(connection)
+        .await?
+        .into_iter()
+        .map(|row| (row.group_id, row.priority))
+        .collect())
+}
+
+/// Set the priority of a group
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `group_id` - The id of the group
+/// * `group` - The request with the new priority
+///
+/// # Returns
+///
+/// The group after its priority was set.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all, fields(group_id = group_id))]
+pub async fn update_configuration_group(
+    connection: &DatabaseConnection,
+    group_id: &str,
+    group: &ConfigurationGroupUpdateRequest,
+) -> Result<ConfigurationGroupResponse, Error> {
+    let transaction = begin_transaction(connection).await?;
+
+    configuration_groups::Entity::insert(configuration_groups::ActiveModel {
+        group_id: Set(group_id.to_owned()),
+        priority: Set(group.priority),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(configuration_groups::Column::GroupId)
+            .update_column(configuration_groups::Column::Priority)
+            .to_owned(),
+    )
+    .exec(&transaction)
+    .await?;
+
+    let group = get_configuration_group(&transaction, group_id).await?;
+
+    transaction.commit().await?;
+
+    Ok(group)
+}
+
+/// Add a user to a group
+///
+/// Adding a user that is already a member changes nothing.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `group_id` - The id of the group
+/// * `user_id` - The id of the user
+///
+/// # Returns
+///
+/// The group after the user was added.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all, fields(group_id = group_id, user_id = user_id))]
+pub async fn add_configuration_group_member(
+    connection: &DatabaseConnection,
+    group_id: &str,
+    user_id: &str,
+) -> Result<ConfigurationGroupResponse, Error> {
+    let transaction = begin_transaction(connection).await?;
+
+    // The unique index on active memberships makes concurrent adds of the same
+    // user insert a single row
+    configuration_group_members::Entity::insert(configuration_group_members::ActiveModel {
+        group_id: Set(group_id.to_owned()),
+        user_id: Set(user_id.to_owned()),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            configuration_group_members::Column::GroupId,
+            configuration_group_members::Column::UserId,
+        ])
+        .target_and_where(
+            Expr::col(configuration_group_members::Column::DeactivateTimestamp).is_null(),
+        )
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec_without_returning(&transaction)
+    .await?;
+
+    let group = get_configuration_group(&transaction, group_id).await?;
+
+    transaction.commit().await?;
+
+    Ok(group)
+}
+
+/// Remove a user from a group
+///
+/// The user's membership is deactivated. Removing a user that is not a member
+/// changes nothing.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `group_id` - The id of the group
+/// * `user_id` - The id of the user
+///
+/// # Returns
+///
+/// The group after the user was removed.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all, fields(group_id = group_id, user_id = user_id))]
+pub async fn remove_configuration_group_member(
+    connection: &DatabaseConnection,
+    group_id: &str,
+    user_id: &str,
+) -> Result<ConfigurationGroupResponse, Error> {
+    let transaction = begin_transaction(connection).await?;
+
+    configuration_group_members::Entity::update_many()
+        .col_expr(
+            configuration_group_members::Column::DeactivateTimestamp,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(configuration_group_members::Column::GroupId.eq(group_id))
+        .filter(configuration_group_members::Column::UserId.eq(user_id))
+        .filter(configuration_group_members::Column::DeactivateTimestamp.is_null())
+        .exec(&transaction)
+        .await?;
+
+    let group = get_configuration_group(&transaction, group_id).await?;
+
+    transaction.commit().await?;
+
+    Ok(group)
+}
+
+/// Build a query that selects all active group memberships, ordered by group
+/// id and then by user id.
+fn find_active_configuration_group_member_rows() -> Select<configuration_group_members::Entity> {
+    configuration_group_members::Entity::find()
+        .filter(configuration_group_members::Column::DeactivateTimestamp.is_null())
+        .order_by_asc(configuration_group_members::Column::GroupId)
+        .order_by_asc(configuration_group_members::Column::UserId)
+}
+
+/// Get the priority of a group from a map of priorities, falling back to the
+/// default priority.
+pub(crate) fn get_configuration_group_priority(
+    priorities: &HashMap<String, i32>,
+    group_id: &str,
+) -> i32 {
+    priorities
+        .get(group_id)
+        .copied()
+        .unwrap_or(DEFAULT_CONFIGURATION_GROUP_PRIORITY)
+}
+
+/// Compare two groups by the precedence of their items
+///
+/// Groups with a higher priority come first. Groups with the same priority
+/// come in the order of their ids, so the order never depends on the database.
+///
+/// # Arguments
+///
+/// * `a` - The id of the first group
+/// * `b` - The id of the second group
+/// * `priorities` - The priorities of the groups that have one set
+///
+/// # Returns
+///
+/// `Ordering::Less` if the items of the first group take precedence.
+pub(crate) fn compare_configuration_group_precedence(
+    a: &str,
+    b: &str,
+    priorities: &HashMap<String, i32>,
+) -> Ordering {
+    get_configuration_group_priority(priorities, b)
+        .cmp(&get_configuration_group_priority(priorities, a))
+        .then_with(|| a.cmp(b))
+}
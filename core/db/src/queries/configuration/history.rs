@@ -0,0 +1,1082 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Reading configuration as it was at a point in time.
+//!
+//! The audit triggers record the new row for every insert and the old row for
+//! every update or delete, stamped with the start of the writing transaction.
+//! A row as it was at a point in time is therefore the row recorded by its
+//! first audit record after that point, unless that record is an insert. Rows
+//! without any audit records after that point have not changed since, so they
+//! are read from the source table.
+//!
+//! Audit timestamps are compared in UTC.
+//!
+//! Comparing the configuration at two points in time and rolling it back are
+//! built on the same reconstruction.
+
+use super::{
+    build_configuration_key_response, build_configuration_type_response,
+    check_configuration_entry_item_count, check_configuration_entry_item_text,
+    check_configuration_key_prefix, check_user_override_allowed, collect_configuration_entries,
+    deactivate_configuration_entry_rows, get_all_configuration_keys, get_all_configuration_types,
+    insert_configuration_entry_row, lock_configuration_key_rows, matches_configuration_key_prefix,
+    value::parse_configuration_value, ConfigurationEntryScope,
+};
+use crate::{
+    entities::{
+        configuration_entries, configuration_entries_audit, configuration_group_members,
+        configuration_group_members_audit, configuration_groups, configuration_groups_audit,
+        configuration_key_reference, configuration_key_reference_audit,
+        configuration_type_reference, configuration_type_reference_audit,
+    },
+    request_id::begin_transaction,
+    secrets::SecretCipher,
+    Error,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use domain_api::configuration::{
+    ConfigurationChangeAuditResponse, ConfigurationChangeKind, ConfigurationDiffResponse,
+    ConfigurationEntryItemChangeResponse, ConfigurationEntrySetResponse,
+    ConfigurationKeyChangeResponse, ConfigurationKeyResponse, ConfigurationKeySetResponse,
+    ConfigurationRollbackResponse, ConfigurationTypeSetResponse, ConfigurationValueResponse,
+};
+use sea_orm::{
+    sea_query::{Alias, Expr, SimpleExpr},
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait, Order, QueryFilter,
+    QueryOrder,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tracing::instrument;
+
+/// A point in the history of the configuration
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ConfigurationAuditPoint {
+    /// The moment in time, compared to the start of the writing transactions
+    Timestamp(DateTime<Utc>),
+    /// The change recorded by an audit record of `configuration_entries`,
+    /// including that change
+    AuditId(i32),
+}
+
+/// Get all configuration types from the database as they were at a point in
+/// time
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `as_of` - The point in time
+///
+/// # Returns
+///
+/// The set of configuration types that were active at that time.
+///
+/// # Errors
+///
+/// Returns any database errors. If an audit record is incomplete, an error is
+/// returned.
+#[instrument(level = "debug", skip_all, fields(as_of = %as_of))]
+pub async fn get_all_configuration_types_as_of(
+    connection: &DatabaseConnection,
+    as_of: DateTime<Utc>,
+) -> Result<ConfigurationTypeSetResponse, Error> {
+    find_rows_as_of::<
+        _,
+        configuration_type_reference::Entity,
+        configuration_type_reference_audit::Entity,
+    >(connection, ConfigurationAuditPoint::Timestamp(as_of))
+    .await?
+    .into_iter()
+    .filter(|row| row.deactivate_timestamp.is_none())
+    .map(build_configuration_type_response)
+    .collect::<Result<ConfigurationTypeSetResponse, Error>>()
+}
+
+/// Get all configuration keys from the database as they were at a point in
+/// time
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `type_set` - The set of configuration types loaded for the same point in
+///                time
+/// * `as_of` - The point in time
+///
+/// # Returns
+///
+/// The set of configuration keys that were active at that time.
+///
+/// # Errors
+///
+/// Returns any database errors. If an audit record is incomplete, an error is
+/// returned. If there is a configuration type id referenced that is not in the
+/// set of configuration types, an error is returned.
+#[instrument(level = "debug", skip_all, fields(as_of = %as_of))]
+pub async fn get_all_configuration_keys_as_of(
+    connection: &DatabaseConnection,
+    type_set: &ConfigurationTypeSetResponse,
+    as_of: DateTime<Utc>,
+) -> Result<ConfigurationKeySetResponse, Error> {
+    find_rows_as_of::<
+        _,
+        configuration_key_reference::Entity,
+        configuration_key_reference_audit::Entity,
+    >(connection, ConfigurationAuditPoint::Timestamp(as_of))
+    .await?
+    .into_iter()
+    .filter(|row| row.deactivate_timestamp.is_none())
+    .map(|row| build_configuration_key_response(row, type_set))
+    .collect::<Result<ConfigurationKeySetResponse, Error>>()
+}
+
+/// Get all configuration entries from the database as they were at a point in
+/// time
+///
+/// Secret values are redacted.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key_set` - The set of configuration keys loaded for the same point in
+///               time
+/// * `user_id` - The user id to select the configuration entries for. If this
+///               value is null, return only global configuration entries.
+///               Otherwise, the items of the groups that the user was a
+///               member of at that time are returned too.
+/// * `prefix` - The dotted key name prefix to select the configuration entries
+///              for. If this value is null, return entries for all keys.
+/// * `as_of` - The point in time
+///
+/// # Returns
+///
+/// The set of configuration entries that were active at that time.
+///
+/// # Errors
+///
+/// Returns any database errors. If an audit record is incomplete, an error is
+/// returned. If the prefix is not a valid key name, an error is returned.
+#[instrument(level = "debug", skip_all, fields(user_id = user_id, prefix = prefix, as_of = %as_of))]
+pub async fn get_all_configuration_entries_as_of(
+    connection: &DatabaseConnection,
+    key_set: &ConfigurationKeySetResponse,
+    user_id: Option<&str>,
+    prefix: Option<&str>,
+    as_of: DateTime<Utc>,
+) -> Result<ConfigurationEntrySetResponse, Error> {
+    get_configuration_entries_as_of(connection, key_set, user_id, prefix, as_of, None).await
+}
+
+/// Get all configuration entries from the database as they were at a point in
+/// time with secret values decrypted
+///
+/// Only use this when the caller is allowed to see secret values.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `key_set` - The set of configuration keys loaded for the same point in
+///               time
+/// * `user_id` - The user id to select the configuration entries for. If this
+///               value is null, return only global configuration entries.
+///               Otherwise, the items of the groups that the user was a
+///               member of at that time are returned too.
+/// * `prefix` - The dotted key name prefix to select the configuration entries
+///              for. If this value is null, return entries for all keys.
+/// * `as_of` - The point in time
+///
+/// # Returns
+///
+/// The set of configuration entries that were active at that time.
+///
+/// # Errors
+///
+/// Returns any database errors. If an audit record is incomplete, an error is
+/// returned. If the prefix is not a valid key name, an error is returned. If
+/// there is no valid key configured for secret values or if any secret value
+/// cannot be decrypted, an error is returned.
+#[instrument(level = "debug", skip_all, fields(user_id = user_id, prefix = prefix, as_of = %as_of))]
+pub async fn get_all_configuration_entries_with_secrets_as_of(
+    connection: &DatabaseConnection,
+    key_set: &ConfigurationKeySetResponse,
+    user_id: Option<&str>,
+    prefix: Option<&str>,
+    as_of: DateTime<Utc>,
+) -> Result<ConfigurationEntrySetResponse, Error> {
+    let secret_cipher = SecretCipher::from_configuration()?;
+
+    get_configuration_entries_as_of(
+        connection,
+        key_set,
+        user_id,
+        prefix,
+        as_of,
+        Some(&secret_cipher),
+    )
+    .await
+}
+
+/// Compare the configuration at two points in time
+///
+/// Keys and entry items are compared by id. Values are parsed with the type of
+/// their key at the respective point in time, and secret values are redacted.
+/// Every change is attributed to the last audit record of its row between the
+/// two points in time.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `from` - The earlier point in time
+/// * `to` - The later point in time
+///
+/// # Returns
+///
+/// The keys and entry items that were added, changed or deactivated, ordered
+/// by id.
+///
+/// # Errors
+///
+/// Returns any database errors. If `from` is after `to`, an error is returned.
+/// If an audit record is incomplete, an error is returned. If any value cannot
+/// be parsed as its key's type, an error is returned.
+#[instrument(level = "debug", skip_all, fields(from = %from, to = %to))]
+pub async fn get_configuration_diff(
+    connection: &DatabaseConnection,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<ConfigurationDiffResponse, Error> {
+    if from > to {
+        return Err(Error::AuditRangeInvalid(
+            format_audit_timestamp(from),
+            format_audit_timestamp(to),
+        ));
+    }
+
+    // Diff keys
+    let types_from = get_all_configuration_types_as_of(connection, from).await?;
+    let types_to = get_all_configuration_types_as_of(connection, to).await?;
+
+    let keys_from = get_all_configuration_keys_as_of(connection, &types_from, from).await?;
+    let keys_to = get_all_configuration_keys_as_of(connection, &types_to, to).await?;
+
+    let key_changes = find_last_changes::<_, configuration_key_reference_audit::Entity>(
+        connection,
+        build_between_timestamps_condition(from, to),
+    )
+    .await?;
+
+    let key_ids = keys_from
+        .iter()
+        .chain(keys_to.iter())
+        .map(|key| key.id)
+        .collect::<BTreeSet<i32>>();
+
+    let mut keys = Vec::new();
+
+    for id in key_ids {
+        let old = keys_from.iter().find(|key| key.id == id);
+        let new = keys_to.iter().find(|key| key.id == id);
+
+        if let Some(kind) = compare_versions(old, new, |old, new| old != new) {
+            keys.push(ConfigurationKeyChangeResponse {
+                kind,
+                old: old.cloned(),
+                new: new.cloned(),
+                audit: key_changes.get(&id).map(AuditRecord::audit),
+            });
+        }
+    }
+
+    // Diff entry items
+    let entries_from = find_active_entry_rows_as_of(connection, from).await?;
+    let entries_to = find_active_entry_rows_as_of(connection, to).await?;
+
+    let entry_changes = find_last_changes::<_, configuration_entries_audit::Entity>(
+        connection,
+        build_between_timestamps_condition(from, to),
+    )
+    .await?;
+
+    let entry_ids = entries_from
+        .keys()
+        .chain(entries_to.keys())
+        .copied()
+        .collect::<BTreeSet<i32>>();
+
+    let mut entries = Vec::new();
+
+    for id in entry_ids {
+        let old = entries_from.get(&id);
+        let new = entries_to.get(&id);
+
+        let Some(kind) = compare_versions(old, new, |old, new| {
+            old.value != new.value || old.order_index != new.order_index
+        }) else {
+            continue;
+        };
+
+        let old_value = old
+            .map(|row| parse_configuration_entry_row_value(row, &keys_from))
+            .transpose()?;
+
+        let new_value = new
+            .map(|row| parse_configuration_entry_row_value(row, &keys_to))
+            .transpose()?;
+
+        let row = new
+            .or(old)
+            .expect("entry ids come from one of the two versions");
+
+        entries.push(ConfigurationEntryItemChangeResponse {
+            kind,
+            id,
+            key_name: find_configuration_key_by_id(row.key_id, &keys_to, &keys_from)?
+                .name
+                .clone(),
+            user_id: row.user_id.clone(),
+            group_id: row.group_id.clone(),
+            old: old_value,
+            new: new_value,
+            audit: entry_changes.get(&id).map(AuditRecord::audit),
+        });
+    }
+
+    Ok(ConfigurationDiffResponse {
+        from: format_audit_timestamp(from),
+        to: format_audit_timestamp(to),
+        keys,
+        entries,
+    })
+}
+
+/// Restore configuration entries to their state at an audit point
+///
+/// Every scope of an entry whose active items differ from the ones at the
+/// audit point has its items replaced by copies of those items, the same way
+/// that `replace_configuration_entry_items` does. The rollback is therefore
+/// recorded in the audit tables like any other write. Only entries of keys that
+/// are currently active are restored.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection
+/// * `point` - The audit point to restore
+/// * `prefix` - The dotted key name prefix to restore the configuration entries
+///              for. If this value is null, all entries are restored.
+///
+/// # Returns
+///
+/// The entry items that were deactivated and added, attributed to the audit
+/// records of the rollback.
+///
+/// # Errors
+///
+/// Returns any database errors. If the prefix is not a valid key name, an error
+/// is returned. If an audit record is incomplete, an error is returned. If any
+/// of the restored items would not be allowed by the current version of its
+/// key, an error is returned and nothing is written.
+#[instrument(level = "debug", skip_all, fields(point = ?point, prefix = prefix))]
+pub async fn rollback_configuration_entries(
+    connection: &DatabaseConnection,
+    point: ConfigurationAuditPoint,
+    prefix: Option<&str>,
+) -> Result<ConfigurationRollbackResponse, Error> {
+    let prefix = prefix.map(check_configuration_key_prefix).transpose()?;
+
+    let transaction = begin_transaction(connection).await?;
+
+    let type_set = get_all_configuration_types(&transaction).await?;
+
+    let key_set = get_all_configuration_keys(&transaction, &type_set)
+        .await?
+        .into_iter()
+        .filter(|key| {
+            prefix.map_or(true, |prefix| {
+                matches_configuration_key_prefix(&key.name, prefix)
+            })
+        })
+        .collect::<ConfigurationKeySetResponse>();
+
+    lock_configuration_key_rows(
+        &transaction,
+        &key_set.iter().map(|key| key.id).collect::<Vec<i32>>(),
+    )
+    .await?;
+
+    // Group the active rows of both versions by scope
+    let group_by_scope = |rows: Vec<configuration_entries::Model>| {
+        let mut scopes: BTreeMap<
+            (i32, ConfigurationEntryScope),
+            Vec<configuration_entries::Model>,
+        > = BTreeMap::new();
+
+        for row in rows {
+            if row.deactivate_timestamp.is_none() && key_set.iter().any(|key| key.id == row.key_id)
+            {
+                scopes
+                    .entry((row.key_id, ConfigurationEntryScope::of_row(&row)))
+                    .or_default()
+                    .push(row);
+            }
+        }
+
+        for rows in scopes.values_mut() {
+            rows.sort_by_key(|row| row.order_index);
+        }
+
+        scopes
+    };
+
+    let target_scopes = group_by_scope(
+        find_rows_as_of::<_, configuration_entries::Entity, configuration_entries_audit::Entity>(
+            &transaction,
+            point,
+        )
+        .await?,
+    );
+
+    let current_scopes = group_by_scope(
+        configuration_entries::Entity::find()
+            .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+            .all(&transaction)
+            .await?,
+    );
+
+    let scopes = target_scopes
+        .keys()
+        .chain(current_scopes.keys())
+        .cloned()
+        .collect::<BTreeSet<(i32, ConfigurationEntryScope)>>();
+
+    // Replace the items of every scope that differs
+    let mut entries = Vec::new();
+
+    for (key_id, scope) in scopes {
+        let target_rows = target_scopes
+            .get(&(key_id, scope.clone()))
+            .map_or(&[][..], Vec::as_slice);
+
+        let current_rows = current_scopes
+            .get(&(key_id, scope.clone()))
+            .map_or(&[][..], Vec::as_slice);
+
+        if target_rows
+            .iter()
+            .map(|row| &row.value)
+            .eq(current_rows.iter().map(|row| &row.value))
+        {
+            continue;
+        }
+
+        let key = find_configuration_key_by_id(key_id, &key_set, &key_set)?;
+
+        if !target_rows.is_empty() {
+            check_user_override_allowed(key, &scope)?;
+        }
+
+        check_configuration_entry_item_count(
+            &key.name,
+            key.optional,
+            key.allows_multiple,
+            &scope,
+            target_rows.len(),
+        )?;
+
+        deactivate_configuration_entry_rows(&transaction, key.id, &scope).await?;
+
+        for row in current_rows {
+            entries.push(ConfigurationEntryItemChangeResponse {
+                kind: ConfigurationChangeKind::Deactivated,
+                id: row.id,
+                key_name: key.name.clone(),
+                user_id: row.user_id.clone(),
+                group_id: row.group_id.clone(),
+                old: Some(parse_configuration_value(
+                    &row.value,
+                    &key.configuration_type,
+                )?),
+                new: None,
+                audit: None,
+            });
+        }
+
+        for (order_index, row) in (1..).zip(target_rows) {
+            check_configuration_entry_item_text(key, &row.value)?;
+
+            let id = insert_configuration_entry_row(
+                &transaction,
+                key.id,
+                order_index,
+                &scope,
+                row.value.clone(),
+            )
+            .await?;
+
+            entries.push(ConfigurationEntryItemChangeResponse {
+                kind: ConfigurationChangeKind::Added,
+                id,
+                key_name: key.name.clone(),
+                user_id: row.user_id.clone(),
+                group_id: row.group_id.clone(),
+                old: None,
+                new: Some(parse_configuration_value(
+                    &row.value,
+                    &key.configuration_type,
+                )?),
+                audit: None,
+            });
+        }
+    }
+
+    // Attribute the changes to the audit records of the rollback
+    let entry_changes = find_last_changes::<_, configuration_entries_audit::Entity>(
+        &transaction,
+        Condition::all()
+            .add(Expr::col(Alias::new("id")).is_in(entries.iter().map(|entry| entry.id))),
+    )
+    .await?;
+
+    for entry in &mut entries {
+        entry.audit = entry_changes.get(&entry.id).map(AuditRecord::audit);
+    }
+
+    transaction.commit().await?;
+
+    Ok(ConfigurationRollbackResponse { entries })
+}
+
+/// Classify the change between two versions of a row, or return `None` if
+/// there is no change.
+fn compare_versions<T: Copy>(
+    old: Option<T>,
+    new: Option<T>,
+    differ: impl FnOnce(T, T) -> bool,
+) -> Option<ConfigurationChangeKind> {
+    match (old, new) {
+        (None, Some(_)) => Some(ConfigurationChangeKind::Added),
+        (Some(_), None) => Some(ConfigurationChangeKind::Deactivated),
+        (Some(old), Some(new)) if differ(old, new) => Some(ConfigurationChangeKind::Changed),
+        _ => None,
+    }
+}
+
+/// Find the configuration entry rows that were active at a point in time by
+/// id.
+async fn find_active_entry_rows_as_of(
+    connection: &DatabaseConnection,
+    as_of: DateTime<Utc>,
+) -> Result<BTreeMap<i32, configuration_entries::Model>, Error> {
+    Ok(
+        find_rows_as_of::<_, configuration_entries::Entity, configuration_entries_audit::Entity>(
+            connection,
+            ConfigurationAuditPoint::Timestamp(as_of),
+        )
+        .await?
+        .into_iter()
+        .filter(|row| row.deactivate_timestamp.is_none())
+        .map(|row| (row.id, row))
+        .collect(),
+    )
+}
+
+/// Find a configuration key by id in the first key set that has it.
+fn find_configuration_key_by_id<'key_set>(
+    id: i32,
+    key_set: &'key_set ConfigurationKeySetResponse,
+    key_set_fallback: &'key_set ConfigurationKeySetResponse,
+) -> Result<&'key_set ConfigurationKeyResponse, Error> {
+    key_set
+        .iter()
+        .chain(key_set_fallback.iter())
+        .find(|key| key.id == id)
+        .ok_or(Error::ConfigurationKeyNotFound(id))
+}
+
+/// Parse the value of a configuration entry row with the type of its key.
+fn parse_configuration_entry_row_value(
+    row: &configuration_entries::Model,
+    key_set: &ConfigurationKeySetResponse,
+) -> Result<ConfigurationValueResponse, Error> {
+    let key = find_configuration_key_by_id(row.key_id, key_set, key_set)?;
+
+    parse_configuration_value(&row.value, &key.configuration_type)
+}
+
+/// Format a timestamp the way audit timestamps are reported.
+fn format_audit_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Select and group the configuration entry rows that were active at a point
+/// in time.
+async fn get_configuration_entries_as_of(
+    connection: &DatabaseConnection,
+    key_set: &ConfigurationKeySetResponse,
+    user_id: Option<&str>,
+    prefix: Option<&str>,
+    as_of: DateTime<Utc>,
+    secret_cipher: Option<&SecretCipher>,
+) -> Result<ConfigurationEntrySetResponse, Error> {
+    let prefix = prefix.map(check_configuration_key_prefix).transpose()?;
+
+    // Only keep the keys that match the prefix
+    let key_set = key_set
+        .iter()
+        .filter(|key| {
+            prefix.map_or(true, |prefix| {
+                matches_configuration_key_prefix(&key.name, prefix)
+            })
+        })
+        .cloned()
+        .collect::<ConfigurationKeySetResponse>();
+
+    let mut rows = find_rows_as_of::<
+        _,
+        configuration_entries::Entity,
+        configuration_entries_audit::Entity,
+    >(connection, ConfigurationAuditPoint::Timestamp(as_of))
+    .await?
+    .into_iter()
+    .filter(|row| row.deactivate_timestamp.is_none())
+    .filter(|row| key_set.iter().any(|key| key.id == row.key_id))
+    .collect::<Vec<configuration_entries::Model>>();
+
+    // Only keep the scopes that the user could see at that time
+    let group_ids = match user_id {
+        Some(user_id) => find_rows_as_of::<
+            _,
+            configuration_group_members::Entity,
+            configuration_group_members_audit::Entity,
+        >(connection, ConfigurationAuditPoint::Timestamp(as_of))
+        .await?
+        .into_iter()
+        .filter(|row| row.deactivate_timestamp.is_none() && row.user_id == user_id)
+        .map(|row| row.group_id)
+        .collect::<BTreeSet<String>>(),
+        None => BTreeSet::new(),
+    };
+
+    rows.retain(|row| match ConfigurationEntryScope::of_row(row) {
+        ConfigurationEntryScope::Global => true,
+        ConfigurationEntryScope::Group(group_id) => group_ids.contains(&group_id),
+        ConfigurationEntryScope::User(row_user_id) => Some(row_user_id.as_str()) == user_id,
+    });
+
+    // Match the order of the live query
+    rows.sort_by_key(|row| (row.key_id, row.order_index));
+
+    // Order the groups by the priorities they had at that time
+    let group_priorities = find_rows_as_of::<
+        _,
+        configuration_groups::Entity,
+        configuration_groups_audit::Entity,
+    >(connection, ConfigurationAuditPoint::Timestamp(as_of))
+    .await?
+    .into_iter()
+    .map(|row| (row.group_id, row.priority))
+    .collect::<HashMap<String, i32>>();
+
+    Ok(
+        collect_configuration_entries(rows, &key_set, &group_priorities, secret_cipher)?
+            .into_values()
+            .collect::<ConfigurationEntrySetResponse>(),
+    )
+}
+
+/// Build a condition that matches the audit records after an audit point.
+fn build_after_audit_point_condition(point: ConfigurationAuditPoint) -> SimpleExpr {
+    match point {
+        ConfigurationAuditPoint::Timestamp(timestamp) => {
+            Expr::col(Alias::new("audit_timestamp_transaction_start")).gt(timestamp.naive_utc())
+        }
+        ConfigurationAuditPoint::AuditId(audit_id) => {
+            Expr::col(Alias::new("audit_id")).gt(audit_id)
+        }
+    }
+}
+
+/// Build a condition that matches the audit records between two points in
+/// time, excluding the start and including the end.
+fn build_between_timestamps_condition(from: DateTime<Utc>, to: DateTime<Utc>) -> Condition {
+    Condition::all()
+        .add(Expr::col(Alias::new("audit_timestamp_transaction_start")).gt(from.naive_utc()))
+        .add(Expr::col(Alias::new("audit_timestamp_transaction_start")).lte(to.naive_utc()))
+}
+
+/// An audit table row that records a version of a row in its source table
+trait AuditRecord {
+    /// The model of the source table
+    type Source;
+
+    /// The name of the source table, for error messages
+    const SOURCE_TABLE_NAME: &'static str;
+
+    /// The id of the source row
+    fn source_id(&self) -> Option<i32>;
+
+    /// The id of the audit row
+    fn audit_id(&self) -> i32;
+
+    /// Whether the audit row records an insert
+    fn is_insert(&self) -> bool;
+
+    /// Describe the change that the audit row records
+    fn audit(&self) -> ConfigurationChangeAuditResponse;
+
+    /// The id of a source row
+    fn id_of(source: &Self::Source) -> i32;
+
+    /// Convert the recorded version into a source row, or return `None` if any
+    /// of the recorded source columns are missing
+    fn into_source(self) -> Option<Self::Source>;
+}
+
+/// Find all rows of a source table as they were at an audit point.
+///
+/// # Errors
+///
+/// Returns any database errors. If an audit record is incomplete, an error is
+/// returned.
+async fn find_rows_as_of<C, SourceEntity, AuditEntity>(
+    connection: &C,
+    point: ConfigurationAuditPoint,
+) -> Result<Vec<SourceEntity::Model>, Error>
+where
+    C: ConnectionTrait,
+    SourceEntity: EntityTrait,
+    AuditEntity: EntityTrait,
+    AuditEntity::Model: AuditRecord<Source = SourceEntity::Model>,
+{
+    // Find the first change to every row after the point
+    let mut first_changes: HashMap<i32, AuditEntity::Model> = HashMap::new();
+
+    for audit_row in AuditEntity::find()
+        .filter(build_after_audit_point_condition(point))
+        .order_by(Expr::col(Alias::new("audit_id")), Order::Asc)
+        .all(connection)
+        .await?
+    {
+        let source_id = audit_row.source_id().ok_or_else(|| {
+            Error::AuditRecordIncomplete(
+                AuditEntity::Model::SOURCE_TABLE_NAME.to_owned(),
+                audit_row.audit_id(),
+            )
+        })?;
+
+        first_changes.entry(source_id).or_insert(audit_row);
+    }
+
+    // Rows without changes since then are unchanged in the source table
+    let mut rows = SourceEntity::find()
+        .all(connection)
+        .await?
+        .into_iter()
+        .filter(|row| !first_changes.contains_key(&AuditEntity::Model::id_of(row)))
+        .collect::<Vec<SourceEntity::Model>>();
+
+    // Other rows are recorded by their first change, unless they were inserted
+    for audit_row in first_changes.into_values() {
+        if audit_row.is_insert() {
+            continue;
+        }
+
+        let audit_id = audit_row.audit_id();
+
+        rows.push(audit_row.into_source().ok_or_else(|| {
+            Error::AuditRecordIncomplete(AuditEntity::Model::SOURCE_TABLE_NAME.to_owned(), audit_id)
+        })?);
+    }
+
+    rows.sort_by_key(AuditEntity::Model::id_of);
+
+    Ok(rows)
+}
+
+/// Find the last audit record of every row of a source table among the audit
+/// records that match a condition.
+///
+/// # Errors
+///
+/// Returns any database errors. If an audit record is incomplete, an error is
+/// returned.
+async fn find_last_changes<C, AuditEntity>(
+    connection: &C,
+    condition: Condition,
+) -> Result<HashMap<i32, AuditEntity::Model>, Error>
+where
+    C: ConnectionTrait,
+    AuditEntity: EntityTrait,
+    AuditEntity::Model: AuditRecord,
+{
+    let mut last_changes = HashMap::new();
+
+    for audit_row in AuditEntity::find()
+        .filter(condition)
+        .order_by(Expr::col(Alias::new("audit_id")), Order::Asc)
+        .all(connection)
+        .await?
+    {
+        let source_id = audit_row.source_id().ok_or_else(|| {
+            Error::AuditRecordIncomplete(
+                AuditEntity::Model::SOURCE_TABLE_NAME.to_owned(),
+                audit_row.audit_id(),
+            )
+        })?;
+
+        last_changes.insert(source_id, audit_row);
+    }
+
+    Ok(last_changes)
+}
+
+impl AuditRecord for configuration_type_reference_audit::Model {
+    type Source = configuration_type_reference::Model;
+
+    const SOURCE_TABLE_NAME: &'static str = "configuration_type_reference";
+
+    fn source_id(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn audit_id(&self) -> i32 {
+        self.audit_id
+    }
+
+    fn is_insert(&self) -> bool {
+        self.audit_action == "I"
+    }
+
+    fn audit(&self) -> ConfigurationChangeAuditResponse {
+        ConfigurationChangeAuditResponse {
+            audit_id: self.audit_id,
+            timestamp: format_audit_timestamp(DateTime::from_utc(
+                self.audit_timestamp_transaction_start,
+                Utc,
+            )),
+            client_host: self.audit_client_host.clone(),
+            client_query: self.audit_client_query.clone(),
+            request_id: self.audit_request_id.clone(),
+        }
+    }
+
+    fn id_of(source: &Self::Source) -> i32 {
+        source.id
+    }
+
+    fn into_source(self) -> Option<Self::Source> {
+        Some(configuration_type_reference::Model {
+            id: self.id?,
+            name: self.name?,
+            description: self.description?,
+            deactivate_timestamp: self.deactivate_timestamp,
+        })
+    }
+}
+
+impl AuditRecord for configuration_key_reference_audit::Model {
+    type Source = configuration_key_reference::Model;
+
+    const SOURCE_TABLE_NAME: &'static str = "configuration_key_reference";
+
+    fn source_id(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn audit_id(&self) -> i32 {
+        self.audit_id
+    }
+
+    fn is_insert(&self) -> bool {
+        self.audit_action == "I"
+    }
+
+    fn audit(&self) -> ConfigurationChangeAuditResponse {
+        ConfigurationChangeAuditResponse {
+            audit_id: self.audit_id,
+            timestamp: format_audit_timestamp(DateTime::from_utc(
+                self.audit_timestamp_transaction_start,
+                Utc,
+            )),
+            client_host: self.audit_client_host.clone(),
+            client_query: self.audit_client_query.clone(),
+            request_id: self.audit_request_id.clone(),
+        }
+    }
+
+    fn id_of(source: &Self::Source) -> i32 {
+        source.id
+    }
+
+    fn into_source(self) -> Option<Self::Source> {
+        Some(configuration_key_reference::Model {
+            id: self.id?,
+            name: self.name?,
+            description: self.description?,
+            type_id: self.type_id?,
+            optional: self.optional?,
+            allows_multiple: self.allows_multiple?,
+            allows_user_override: self.allows_user_override?,
+            deactivate_timestamp: self.deactivate_timestamp,
+            minimum: self.minimum,
+            maximum: self.maximum,
+            pattern: self.pattern,
+            allowed_values: self.allowed_values,
+            rollout_percentage: self.rollout_percentage,
+            rollout_allow_user_ids: self.rollout_allow_user_ids,
+            rollout_deny_user_ids: self.rollout_deny_user_ids,
+            rollout_group_ids: self.rollout_group_ids,
+        })
+    }
+}
+
+impl AuditRecord for configuration_entries_audit::Model {
+    type Source = configuration_entries::Model;
+
+    const SOURCE_TABLE_NAME: &'static str = "configuration_entries";
+
+    fn source_id(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn audit_id(&self) -> i32 {
+        self.audit_id
+    }
+
+    fn is_insert(&self) -> bool {
+        self.audit_action == "I"
+    }
+
+    fn audit(&self) -> ConfigurationChangeAuditResponse {
+        ConfigurationChangeAuditResponse {
+            audit_id: self.audit_id,
+            timestamp: format_audit_timestamp(DateTime::from_utc(
+                self.audit_timestamp_transaction_start,
+                Utc,
+            )),
+            client_host: self.audit_client_host.clone(),
+            client_query: self.audit_client_query.clone(),
+            request_id: self.audit_request_id.clone(),
+        }
+    }
+
+    fn id_of(source: &Self::Source) -> i32 {
+        source.id
+    }
+
+    fn into_source(self) -> Option<Self::Source> {
+        Some(configuration_entries::Model {
+            id: self.id?,
+            key_id: self.key_id?,
+            user_id: self.user_id,
+            order_index: self.order_index?,
+            value: self.value?,
+            deactivate_timestamp: self.deactivate_timestamp,
+            group_id: self.group_id,
+        })
+    }
+}
+
+impl AuditRecord for configuration_group_members_audit::Model {
+    type Source = configuration_group_members::Model;
+
+    const SOURCE_TABLE_NAME: &'static str = "configuration_group_members";
+
+    fn source_id(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn audit_id(&self) -> i32 {
+        self.audit_id
+    }
+
+    fn is_insert(&self) -> bool {
+        self.audit_action == "I"
+    }
+
+    fn audit(&self) -> ConfigurationChangeAuditResponse {
+        ConfigurationChangeAuditResponse {
+            audit_id: self.audit_id,
+            timestamp: format_audit_timestamp(DateTime::from_utc(
+                self.audit_timestamp_transaction_start,
+                Utc,
+            )),
+            client_host: self.audit_client_host.clone(),
+            client_query: self.audit_client_query.clone(),
+            request_id: self.audit_request_id.clone(),
+        }
+    }
+
+    fn id_of(source: &Self::Source) -> i32 {
+        source.id
+    }
+
+    fn into_source(self) -> Option<Self::Source> {
+        Some(configuration_group_members::Model {
+            id: self.id?,
+            group_id: self.group_id?,
+            user_id: self.user_id?,
+            deactivate_timestamp: self.deactivate_timestamp,
+        })
+    }
+}
+
+impl AuditRecord for configuration_groups_audit::Model {
+    type Source = configuration_groups::Model;
+
+    const SOURCE_TABLE_NAME: &'static str = "configuration_groups";
+
+    fn source_id(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn audit_id(&self) -> i32 {
+        self.audit_id
+    }
+
+    fn is_insert(&self) -> bool {
+        self.audit_action == "I"
+    }
+
+    fn audit(&self) -> ConfigurationChangeAuditResponse {
+        ConfigurationChangeAuditResponse {
+            audit_id: self.audit_id,
+            timestamp: format_audit_timestamp(DateTime::from_utc(
+                self.audit_timestamp_transaction_start,
+                Utc,
+            )),
+            client_host: self.audit_client_host.clone(),
+            client_query: self.audit_client_query.clone(),
+            request_id: self.audit_request_id.clone(),
+        }
+    }
+
+    fn id_of(source: &Self::Source) -> i32 {
+        source.id
+    }
+
+    fn into_source(self) -> Option<Self::Source> {
+        Some(configuration_groups::Model {
+            id: self.id?,
+            group_id: self.group_id?,
+            priority: self.priority?,
+        })
+    }
+}
@@ -0,0 +1,535 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Parsing and formatting of configuration values.
+//!
+//! Values are stored in the database as text. Every configuration type has a
+//! canonical text representation so that equal values are always stored the
+//! same way.
+
+use crate::{secrets::is_encrypted, Error};
+use chrono::{DateTime, SecondsFormat, Utc};
+use domain_api::configuration::{ConfigurationTypeResponse, ConfigurationValueResponse};
+use std::time::Duration;
+use url::Url;
+
+/// The kinds of values that configuration types can hold
+///
+/// Configuration types are identified by name in the database, and each name
+/// must match one of these kinds.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ConfigurationTypeKind {
+    /// A true/false value
+    Boolean,
+    /// A signed 64-bit integer
+    Integer,
+    /// A 64-bit floating-point number
+    Float,
+    /// Any string
+    String,
+    /// A duration like `1h 30m`
+    Duration,
+    /// An absolute URL
+    Url,
+    /// An RFC 3339 timestamp, stored in UTC
+    Datetime,
+    /// Any JSON value
+    Json,
+    /// One of the allowed values of the configuration key
+    Enum,
+    /// A string that is encrypted at rest and redacted when read
+    Secret,
+}
+
+impl ConfigurationTypeKind {
+    /// All of the supported kinds
+    pub const ALL: [ConfigurationTypeKind; 10] = [
+        ConfigurationTypeKind::Boolean,
+        ConfigurationTypeKind::Integer,
+        ConfigurationTypeKind::Float,
+        ConfigurationTypeKind::String,
+        ConfigurationTypeKind::Duration,
+        ConfigurationTypeKind::Url,
+        ConfigurationTypeKind::Datetime,
+        ConfigurationTypeKind::Json,
+        ConfigurationTypeKind::Enum,
+        ConfigurationTypeKind::Secret,
+    ];
+
+    /// Gets the kind for a configuration type name, if it is supported.
+    pub fn from_name(name: &str) -> Option<ConfigurationTypeKind> {
+        ConfigurationTypeKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_name() == name)
+    }
+
+    /// Gets the name of the kind as it is known within the database.
+    pub fn as_name(self) -> &'static str {
+        match self {
+            ConfigurationTypeKind::Boolean => "boolean",
+            ConfigurationTypeKind::Integer => "integer",
+            ConfigurationTypeKind::Float => "float",
+            ConfigurationTypeKind::String => "string",
+            ConfigurationTypeKind::Duration => "duration",
+            ConfigurationTypeKind::Url => "url",
+            ConfigurationTypeKind::Datetime => "datetime",
+            ConfigurationTypeKind::Json => "json",
+            ConfigurationTypeKind::Enum => "enum",
+            ConfigurationTypeKind::Secret => "secret",
+        }
+    }
+
+    /// Gets the kind of a configuration type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration type's name is not supported.
+    pub fn of(
+        configuration_type: &ConfigurationTypeResponse,
+    ) -> Result<ConfigurationTypeKind, Error> {
+        ConfigurationTypeKind::from_name(&configuration_type.name)
+            .ok_or_else(|| Error::ConfigurationTypeUnsupported(configuration_type.name.clone()))
+    }
+}
+
+/// Parse a configuration value from a string.
+///
+/// Secret values are stored encrypted and are never decrypted here. Instead, a
+/// redacted value is returned.
+///
+/// # Arguments
+///
+/// * `text` - The text to parse
+/// * `configuration_type` - The configuration type to parse the text as
+///
+/// # Returns
+///
+/// A configuration value response.
+///
+/// # Errors
+///
+/// Returns an error if the text cannot be parsed as the given configuration.
+pub fn parse_configuration_value(
+    text: &str,
+    configuration_type: &ConfigurationTypeResponse,
+) -> Result<ConfigurationValueResponse, Error> {
+    Ok(match ConfigurationTypeKind::of(configuration_type)? {
+        ConfigurationTypeKind::Boolean => ConfigurationValueResponse {
+            as_boolean: match text {
+                "true" => Ok(Some(true)),
+                "false" => Ok(Some(false)),
+                _ => Err(Error::ConfigurationValueParseErrorBoolean(text.to_owned())),
+            }?,
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Integer => ConfigurationValueResponse {
+            as_integer: Some(text.parse::<i64>()?),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Float => ConfigurationValueResponse {
+            as_float: Some(text.parse::<f64>()?),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::String => ConfigurationValueResponse {
+            as_string: Some(text.to_owned()),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Duration => ConfigurationValueResponse {
+            as_duration: Some(format_duration(humantime::parse_duration(text)?)),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Url => ConfigurationValueResponse {
+            as_url: Some(Url::parse(text)?.to_string()),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Datetime => ConfigurationValueResponse {
+            as_datetime: Some(format_datetime(DateTime::parse_from_rfc3339(text)?)),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Json => ConfigurationValueResponse {
+            as_json: Some(serde_json::from_str(text)?),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Enum => ConfigurationValueResponse {
+            as_enum: Some(text.to_owned()),
+            ..Default::default()
+        },
+        ConfigurationTypeKind::Secret => {
+            if !is_encrypted(text) {
+                return Err(Error::ConfigurationSecretDecryptionFailed);
+            }
+
+            redacted_configuration_value()
+        }
+    })
+}
+
+/// Format a configuration value as a string.
+///
+/// This is the inverse of `parse_configuration_value`. Values are formatted in
+/// their canonical form, so for example durations of `90m` and `1h 30m` are
+/// stored the same way. Secret values are formatted in plaintext and must be
+/// encrypted before they are stored.
+///
+/// # Arguments
+///
+/// * `value` - The value to format
+/// * `configuration_type` - The configuration type to format the value as
+///
+/// # Returns
+///
+/// The text representation of the value as it is stored in the database.
+///
+/// # Errors
+///
+/// Returns an error if the value does not have exactly the field set that
+/// matches the given configuration type, or if that field is not valid for the
+/// type. Redacted values cannot be formatted.
+pub fn format_configuration_value(
+    value: &ConfigurationValueResponse,
+    configuration_type: &ConfigurationTypeResponse,
+) -> Result<String, Error> {
+    let mismatch = || Error::ConfigurationValueTypeMismatch(configuration_type.name.clone());
+
+    let text = match ConfigurationTypeKind::of(configuration_type)? {
+        ConfigurationTypeKind::Boolean => value.as_boolean.map(|x| x.to_string()),
+        ConfigurationTypeKind::Integer => value.as_integer.map(|x| x.to_string()),
+        ConfigurationTypeKind::Float => value
+            .as_float
+            .filter(|x| x.is_finite())
+            .map(|x| x.to_string()),
+        ConfigurationTypeKind::String => value.as_string.clone(),
+        ConfigurationTypeKind::Duration => value
+            .as_duration
+            .as_deref()
+            .and_then(|x| humantime::parse_duration(x).ok())
+            .map(format_duration),
+        ConfigurationTypeKind::Url => value
+            .as_url
+            .as_deref()
+            .and_then(|x| Url::parse(x).ok())
+            .map(|x| x.to_string()),
+        ConfigurationTypeKind::Datetime => value
+            .as_datetime
+            .as_deref()
+            .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+            .map(format_datetime),
+        ConfigurationTypeKind::Json => value.as_json.as_ref().map(|x| x.to_string()),
+        ConfigurationTypeKind::Enum => value.as_enum.clone().filter(|x| !x.is_empty()),
+        ConfigurationTypeKind::Secret => value.as_secret.clone(),
+    }
+    .filter(|_| !value.redacted)
+    .ok_or_else(mismatch)?;
+
+    // Make sure that no fields for other types are set
+    let field_count = [
+        value.as_boolean.is_some(),
+        value.as_integer.is_some(),
+        value.as_float.is_some(),
+        value.as_string.is_some(),
+        value.as_duration.is_some(),
+        value.as_url.is_some(),
+        value.as_datetime.is_some(),
+        value.as_json.is_some(),
+        value.as_enum.is_some(),
+        value.as_secret.is_some(),
+    ]
+    .into_iter()
+    .filter(|x| *x)
+    .count();
+
+    if field_count != 1 {
+        return Err(mismatch());
+    }
+
+    Ok(text)
+}
+
+/// Build a configuration value that stands in for a secret value.
+pub fn redacted_configuration_value() -> ConfigurationValueResponse {
+    ConfigurationValueResponse {
+        redacted: true,
+        ..Default::default()
+    }
+}
+
+/// Format a duration in its canonical form, like `1h 30m`.
+fn format_duration(duration: Duration) -> String {
+    humantime::format_duration(duration).to_string()
+}
+
+/// Format a timestamp in its canonical form, which is RFC 3339 in UTC.
+fn format_datetime<Tz: chrono::TimeZone>(datetime: DateTime<Tz>) -> String {
+    datetime
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_configuration_value, parse_configuration_value, ConfigurationTypeKind};
+    use domain_api::configuration::{ConfigurationTypeResponse, ConfigurationValueResponse};
+    use serde_json::json;
+
+    fn configuration_type(name: &str) -> ConfigurationTypeResponse {
+        ConfigurationTypeResponse {
+            id: 1,
+            name: name.to_owned(),
+            description: "A type".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_configuration_type_kind_names() {
+        for kind in ConfigurationTypeKind::ALL {
+            assert_eq!(ConfigurationTypeKind::from_name(kind.as_name()), Some(kind));
+        }
+
+        assert_eq!(ConfigurationTypeKind::from_name("color"), None);
+    }
+
+    #[test]
+    fn test_format_configuration_value_round_trip() {
+        let values = [
+            (
+                "boolean",
+                ConfigurationValueResponse {
+                    as_boolean: Some(false),
+                    ..Default::default()
+                },
+            ),
+            (
+                "integer",
+                ConfigurationValueResponse {
+                    as_integer: Some(-42),
+                    ..Default::default()
+                },
+            ),
+            (
+                "float",
+                ConfigurationValueResponse {
+                    as_float: Some(1.5),
+                    ..Default::default()
+                },
+            ),
+            (
+                "string",
+                ConfigurationValueResponse {
+                    as_string: Some("hello".to_owned()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "duration",
+                ConfigurationValueResponse {
+                    as_duration: Some("1h 30m".to_owned()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "url",
+                ConfigurationValueResponse {
+                    as_url: Some("https://example.com/hooks?id=1".to_owned()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "datetime",
+                ConfigurationValueResponse {
+                    as_datetime: Some("2023-03-05T12:00:00Z".to_owned()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "json",
+                ConfigurationValueResponse {
+                    as_json: Some(json!({ "retries": 3, "codes": [500, 503] })),
+                    ..Default::default()
+                },
+            ),
+            (
+                "enum",
+                ConfigurationValueResponse {
+                    as_enum: Some("debug".to_owned()),
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for (type_name, value) in values {
+            let configuration_type = configuration_type(type_name);
+
+            let text = format_configuration_value(&value, &configuration_type).unwrap();
+
+            assert_eq!(
+                parse_configuration_value(&text, &configuration_type).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_configuration_value_canonical() {
+        assert_eq!(
+            format_configuration_value(
+                &ConfigurationValueResponse {
+                    as_duration: Some("90m".to_owned()),
+                    ..Default::default()
+                },
+                &configuration_type("duration"),
+            )
+            .unwrap(),
+            "1h 30m"
+        );
+
+        assert_eq!(
+            format_configuration_value(
+                &ConfigurationValueResponse {
+                    as_url: Some("HTTPS://Example.com".to_owned()),
+                    ..Default::default()
+                },
+                &configuration_type("url"),
+            )
+            .unwrap(),
+            "https://example.com/"
+        );
+
+        assert_eq!(
+            format_configuration_value(
+                &ConfigurationValueResponse {
+                    as_datetime: Some("2023-03-05T13:00:00.500+01:00".to_owned()),
+                    ..Default::default()
+                },
+                &configuration_type("datetime"),
+            )
+            .unwrap(),
+            "2023-03-05T12:00:00.500Z"
+        );
+
+        assert_eq!(
+            format_configuration_value(
+                &ConfigurationValueResponse {
+                    as_json: Some(json!({ "b": 1, "a": [true, null] })),
+                    ..Default::default()
+                },
+                &configuration_type("json"),
+            )
+            .unwrap(),
+            r#"{"a":[true,null],"b":1}"#
+        );
+    }
+
+    #[test]
+    fn test_format_configuration_value_mismatch() {
+        assert!(format_configuration_value(
+            &ConfigurationValueResponse {
+                as_integer: Some(1),
+                ..Default::default()
+            },
+            &configuration_type("boolean"),
+        )
+        .is_err());
+
+        assert!(format_configuration_value(
+            &ConfigurationValueResponse {
+                as_boolean: Some(true),
+                as_string: Some("true".to_owned()),
+                ..Default::default()
+            },
+            &configuration_type("boolean"),
+        )
+        .is_err());
+
+        assert!(format_configuration_value(
+            &ConfigurationValueResponse::default(),
+            &configuration_type("string"),
+        )
+        .is_err());
+
+        for (type_name, value) in [
+            (
+                "duration",
+                ConfigurationValueResponse {
+                    as_duration: Some("soon".to_owned()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "url",
+                ConfigurationValueResponse {
+                    as_url: Some("/relative/path".to_owned()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "datetime",
+                ConfigurationValueResponse {
+                    as_datetime: Some("2023-03-05".to_owned()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "enum",
+                ConfigurationValueResponse {
+                    as_enum: Some(String::new()),
+                    ..Default::default()
+                },
+            ),
+        ] {
+            assert!(format_configuration_value(&value, &configuration_type(type_name)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_secret_configuration_value() {
+        let secret = ConfigurationValueResponse {
+            as_secret: Some("hunter2".to_owned()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_configuration_value(&secret, &configuration_type("secret")).unwrap(),
+            "hunter2"
+        );
+
+        // Redacted values cannot be written back
+        assert!(format_configuration_value(
+            &ConfigurationValueResponse {
+                redacted: true,
+                ..secret
+            },
+            &configuration_type("secret"),
+        )
+        .is_err());
+
+        // Secrets are never parsed from plaintext
+        assert!(parse_configuration_value("hunter2", &configuration_type("secret")).is_err());
+    }
+
+    #[test]
+    fn test_parse_configuration_value_unsupported_type() {
+        assert!(matches!(
+            parse_configuration_value("red", &configuration_type("color")),
+            Err(crate::Error::ConfigurationTypeUnsupported(_))
+        ));
+    }
+}
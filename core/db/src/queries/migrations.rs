@@ -0,0 +1,127 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Queries for the state of the database and its schema.
+
+use crate::Error;
+use domain_api::health::HealthMigrationsResponse;
+use sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use std::collections::HashSet;
+use tracing::instrument;
+
+/// Check that the database can be queried.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be reached.
+#[instrument(level = "debug", skip_all)]
+pub async fn ping_database(connection: &DatabaseConnection) -> Result<(), Error> {
+    connection
+        .execute(Statement::from_string(
+            connection.get_database_backend(),
+            "SELECT 1".to_owned(),
+        ))
+        .await?;
+
+    Ok(())
+}
+
+/// Get the names of the migrations that have been applied to the database.
+///
+/// This only reads the `seaql_migrations` table, unlike the migrator itself
+/// which creates the table if it is missing.
+///
+/// # Returns
+///
+/// The names in the order they were applied, or an empty list if no
+/// migrations have been run yet.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_applied_migrations(connection: &DatabaseConnection) -> Result<Vec<String>, Error> {
+    let backend = connection.get_database_backend();
+
+    let table_exists = connection
+        .query_one(Statement::from_string(
+            backend,
+            "SELECT to_regclass('seaql_migrations') IS NOT NULL AS exists".to_owned(),
+        ))
+        .await?
+        .map(|row| row.try_get::<bool>("", "exists"))
+        .transpose()?
+        .unwrap_or(false);
+
+    if !table_exists {
+        return Ok(Vec::new());
+    }
+
+    connection
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT version FROM seaql_migrations ORDER BY applied_at, version".to_owned(),
+        ))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get::<String>("", "version").map_err(Error::from))
+        .collect()
+}
+
+/// Compare the migrations known to the server with those that have been
+/// applied to the database.
+///
+/// # Arguments
+///
+/// * `migrations` - The names of all migrations in order, as returned by
+///                  `Migrator::migrations()`
+///
+/// # Returns
+///
+/// The applied and pending migrations. The schema is up to date when there
+/// are no pending migrations. Applied migrations that the server does not
+/// know about are listed but do not make the schema out of date.
+///
+/// # Errors
+///
+/// Returns any database errors.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_migration_status(
+    connection: &DatabaseConnection,
+    migrations: &[String],
+) -> Result<HealthMigrationsResponse, Error> {
+    let applied = get_applied_migrations(connection).await?;
+
+    let applied_set = applied.iter().collect::<HashSet<_>>();
+
+    let pending = migrations
+        .iter()
+        .filter(|name| !applied_set.contains(name))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Ok(HealthMigrationsResponse {
+        up_to_date: pending.is_empty(),
+        applied,
+        pending,
+    })
+}
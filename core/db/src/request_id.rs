@@ -0,0 +1,86 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Correlation of database writes with the request that made them.
+//!
+//! Code that handles a request runs inside [`scope_request_id`]. Every write
+//! transaction begun by this crate then sets the request id as a PostgreSQL
+//! session variable, which the audit triggers copy into the `audit_request_id`
+//! column of the audit tables.
+
+use crate::Error;
+use sea_orm::{ConnectionTrait, DatabaseTransaction, Statement, TransactionTrait};
+use std::future::Future;
+
+/// The PostgreSQL setting read by the audit triggers.
+///
+/// This must match `AUDIT_REQUEST_ID_SETTING` in `migration-common`.
+const REQUEST_ID_SETTING: &str = "prelude.request_id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Run a future with a request id in scope.
+///
+/// # Arguments
+///
+/// * `request_id` - The id of the request that the future is handling
+/// * `future` - The future to run
+///
+/// # Returns
+///
+/// The output of the future.
+pub async fn scope_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// Get the request id in scope, if there is one.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+/// Begin a transaction for writing.
+///
+/// If a request id is in scope, it is set as a session variable local to the
+/// transaction so that audit rows written by the transaction record it.
+///
+/// # Errors
+///
+/// Returns any database errors.
+pub(crate) async fn begin_transaction<C: TransactionTrait>(
+    connection: &C,
+) -> Result<DatabaseTransaction, Error> {
+    let transaction = connection.begin().await?;
+
+    if let Some(request_id) = current_request_id() {
+        transaction
+            .execute(Statement::from_sql_and_values(
+                transaction.get_database_backend(),
+                "SELECT set_config($1, $2, true)",
+                [REQUEST_ID_SETTING.into(), request_id.into()],
+            ))
+            .await?;
+    }
+
+    Ok(transaction)
+}
@@ -0,0 +1,176 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Encryption of secret configuration values.
+//!
+//! Secret values are encrypted before they are written to the database, so
+//! that neither the source nor the audit tables ever contain them in
+//! plaintext. They are encrypted with AES-256-GCM using the key from
+//! `CONFIGURATION_SECRET_KEY`.
+
+use crate::Error;
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use config_env::Configuration;
+
+/// The prefix of every encrypted value, which also identifies the format
+const ENCRYPTED_PREFIX: &str = "encrypted:v1:";
+
+/// The length of an AES-GCM nonce in bytes
+const NONCE_LENGTH: usize = 12;
+
+/// A cipher to encrypt and decrypt secret configuration values.
+pub struct SecretCipher(Aes256Gcm);
+
+impl SecretCipher {
+    /// Create a cipher from a base64-encoded 256-bit key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is not valid base64 or is not 256 bits long.
+    pub fn new(key: &str) -> Result<Self, Error> {
+        let key = STANDARD
+            .decode(key)
+            .map_err(|_| Error::ConfigurationSecretKeyInvalid)?;
+
+        Aes256Gcm::new_from_slice(&key)
+            .map(Self)
+            .map_err(|_| Error::ConfigurationSecretKeyInvalid)
+    }
+
+    /// Create a cipher from the key in the environment configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be loaded, if there is no
+    /// key configured, or if the key is invalid.
+    pub fn from_configuration() -> Result<Self, Error> {
+        Self::new(
+            &Configuration::new()?
+                .configuration_secret_key
+                .ok_or(Error::ConfigurationSecretKeyMissing)?,
+        )
+    }
+
+    /// Encrypt a secret value.
+    ///
+    /// A new random nonce is used for every value, so encrypting the same
+    /// value twice gives different results.
+    ///
+    /// # Returns
+    ///
+    /// The encrypted value as it is stored in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encryption fails.
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, Error> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let mut payload = nonce.to_vec();
+
+        payload.extend(
+            self.0
+                .encrypt(&nonce, plaintext.as_bytes())
+                .map_err(|_| Error::ConfigurationSecretEncryptionFailed)?,
+        );
+
+        Ok(format!("{ENCRYPTED_PREFIX}{}", STANDARD.encode(payload)))
+    }
+
+    /// Decrypt a secret value as it is stored in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not an encrypted value, or if it was not
+    /// encrypted with this cipher's key.
+    pub fn decrypt(&self, text: &str) -> Result<String, Error> {
+        let payload = text
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .and_then(|payload| STANDARD.decode(payload).ok())
+            .filter(|payload| payload.len() > NONCE_LENGTH)
+            .ok_or(Error::ConfigurationSecretDecryptionFailed)?;
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+
+        let plaintext = self
+            .0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::ConfigurationSecretDecryptionFailed)?;
+
+        String::from_utf8(plaintext).map_err(|_| Error::ConfigurationSecretDecryptionFailed)
+    }
+}
+
+/// Check whether stored text looks like an encrypted value.
+///
+/// This does not need the key, so it can be used to validate secret values
+/// without decrypting them.
+pub fn is_encrypted(text: &str) -> bool {
+    text.strip_prefix(ENCRYPTED_PREFIX)
+        .and_then(|payload| STANDARD.decode(payload).ok())
+        .map_or(false, |payload| payload.len() > NONCE_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_encrypted, SecretCipher};
+
+    const KEY: &str = "6Wd4VsJ9ZfXMDg0Xx7mPv7cE4lTq8oRSa8R2ZjHfLkI=";
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let cipher = SecretCipher::new(KEY).unwrap();
+
+        let first = cipher.encrypt("hunter2").unwrap();
+        let second = cipher.encrypt("hunter2").unwrap();
+
+        assert!(is_encrypted(&first));
+        assert!(!first.contains("hunter2"));
+        assert_ne!(first, second);
+        assert_eq!(cipher.decrypt(&first).unwrap(), "hunter2");
+        assert_eq!(cipher.decrypt(&second).unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn test_decrypt_invalid() {
+        let cipher = SecretCipher::new(KEY).unwrap();
+
+        let other_cipher =
+            SecretCipher::new("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").unwrap();
+
+        assert!(!is_encrypted("hunter2"));
+        assert!(cipher.decrypt("hunter2").is_err());
+        assert!(other_cipher
+            .decrypt(&cipher.encrypt("hunter2").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_new_invalid_key() {
+        assert!(SecretCipher::new("not base64!").is_err());
+        assert!(SecretCipher::new("c2hvcnQ=").is_err());
+    }
+}
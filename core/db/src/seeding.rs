@@ -22,10 +22,16 @@
 
 //! Utility functions to help with seeding databases.
 
-use crate::entities::{
-    configuration_entries, configuration_key_reference, configuration_type_reference,
+use crate::{
+    entities::{configuration_entries, configuration_key_reference, configuration_type_reference},
+    queries::configuration::{
+        build_configuration_key_response, build_configuration_type_response,
+        check_configuration_entry_item_count, check_configuration_entry_item_text,
+        encrypt_configuration_entry_text, ConfigurationEntryScope,
+    },
+    request_id::begin_transaction,
 };
-use sea_orm::{DatabaseConnection, EntityTrait, Set};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, Set};
 
 /// Inserts a configuration type reference into the database.
 ///
@@ -47,16 +53,52 @@ pub async fn insert_configuration_type_reference(
     name: &str,
     description: &str,
 ) -> Result<i32, crate::Error> {
-    Ok(
+    let transaction = begin_transaction(connection).await?;
+
+    let id =
         configuration_type_reference::Entity::insert(configuration_type_reference::ActiveModel {
             name: Set(name.to_owned()),
             description: Set(description.to_owned()),
             ..Default::default()
         })
-        .exec(connection)
+        .exec(&transaction)
         .await?
-        .last_insert_id,
-    )
+        .last_insert_id;
+
+    transaction.commit().await?;
+
+    Ok(id)
+}
+
+/// Finds the id of an active configuration type reference by name.
+///
+/// The supported configuration types are inserted by migrations, so seeding
+/// code should look them up instead of inserting them.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection to use.
+/// * `name` - The name of the configuration type.
+///
+/// # Returns
+///
+/// The id of the configuration type reference.
+///
+/// # Errors
+///
+/// Returns any database errors. If there is no active configuration type with
+/// the given name, an error is returned.
+pub async fn find_configuration_type_reference(
+    connection: &DatabaseConnection,
+    name: &str,
+) -> Result<i32, crate::Error> {
+    Ok(configuration_type_reference::Entity::find()
+        .filter(configuration_type_reference::Column::Name.eq(name))
+        .filter(configuration_type_reference::Column::DeactivateTimestamp.is_null())
+        .one(connection)
+        .await?
+        .ok_or_else(|| crate::Error::ConfigurationTypeNotFoundByName(name.to_owned()))?
+        .id)
 }
 
 /// Inserts a configuration key reference into the database.
@@ -88,7 +130,9 @@ pub async fn insert_configuration_key_reference(
     allows_multiple: bool,
     allows_user_override: bool,
 ) -> Result<i32, crate::Error> {
-    Ok(
+    let transaction = begin_transaction(connection).await?;
+
+    let id =
         configuration_key_reference::Entity::insert(configuration_key_reference::ActiveModel {
             name: Set(name.to_owned()),
             description: Set(description.to_owned()),
@@ -98,10 +142,13 @@ pub async fn insert_configuration_key_reference(
             allows_user_override: Set(allows_user_override),
             ..Default::default()
         })
-        .exec(connection)
+        .exec(&transaction)
         .await?
-        .last_insert_id,
-    )
+        .last_insert_id;
+
+    transaction.commit().await?;
+
+    Ok(id)
 }
 
 /// Inserts a configuration entry into the database.
@@ -113,6 +160,8 @@ pub async fn insert_configuration_key_reference(
 /// * `user_id` - An optional user id to denote that the entry a user override
 ///               on the global value.
 /// * `value` - The string representation of the configuratin entry's value.
+///             Secret values are given in plaintext and encrypted before they
+///             are inserted.
 ///
 /// # Returns
 ///
@@ -120,7 +169,10 @@ pub async fn insert_configuration_key_reference(
 ///
 /// # Errors
 ///
-/// Returns any database errors.
+/// Returns any database errors. If the configuration key does not exist, an
+/// error is returned. If the value does not parse as the key's type or violates
+/// its value constraints, an error is returned. If the configuration key does
+/// not allow multiple values and already has an item, an error is returned.
 pub async fn insert_configuration_entry(
     connection: &DatabaseConnection,
     key_id: i32,
@@ -128,16 +180,58 @@ pub async fn insert_configuration_entry(
     user_id: Option<&str>,
     value: &str,
 ) -> Result<i32, crate::Error> {
-    Ok(
-        configuration_entries::Entity::insert(configuration_entries::ActiveModel {
-            key_id: Set(key_id),
-            order_index: Set(order_index),
-            value: Set(value.to_owned()),
-            user_id: Set(user_id.map(|x| x.to_owned())),
-            ..Default::default()
-        })
-        .exec(connection)
+    let transaction = begin_transaction(connection).await?;
+
+    let key = configuration_key_reference::Entity::find_by_id(key_id)
+        .one(&transaction)
+        .await?
+        .ok_or(crate::Error::ConfigurationKeyNotFound(key_id))?;
+
+    let configuration_type = configuration_type_reference::Entity::find_by_id(key.type_id)
+        .one(&transaction)
         .await?
-        .last_insert_id,
-    )
+        .ok_or(crate::Error::ConfigurationTypeNotFound(key.type_id))?;
+
+    let key = build_configuration_key_response(
+        key,
+        &vec![build_configuration_type_response(configuration_type)?],
+    )?;
+
+    // Secret values are given in plaintext, just like when they are written
+    // through the API
+    let value = encrypt_configuration_entry_text(&key, value.to_owned())?;
+
+    check_configuration_entry_item_text(&key, &value)?;
+
+    let scope = ConfigurationEntryScope::of_user(user_id);
+
+    let count = configuration_entries::Entity::find()
+        .filter(configuration_entries::Column::KeyId.eq(key_id))
+        .filter(scope.condition())
+        .filter(configuration_entries::Column::DeactivateTimestamp.is_null())
+        .count(&transaction)
+        .await?;
+
+    check_configuration_entry_item_count(
+        &key.name,
+        key.optional,
+        key.allows_multiple,
+        &scope,
+        count as usize + 1,
+    )?;
+
+    let id = configuration_entries::Entity::insert(configuration_entries::ActiveModel {
+        key_id: Set(key_id),
+        order_index: Set(order_index),
+        value: Set(value),
+        user_id: Set(user_id.map(|x| x.to_owned())),
+        ..Default::default()
+    })
+    .exec(&transaction)
+    .await?
+    .last_insert_id;
+
+    transaction.commit().await?;
+
+    Ok(id)
 }
@@ -23,8 +23,9 @@
 //! Code specific to testing the Prelude database layer.
 
 use crate::entities::{
-    configuration_entries, configuration_entries_audit, configuration_key_reference,
-    configuration_key_reference_audit, configuration_type_reference,
+    configuration_entries, configuration_entries_audit, configuration_group_members,
+    configuration_group_members_audit, configuration_groups, configuration_groups_audit,
+    configuration_key_reference, configuration_key_reference_audit, configuration_type_reference,
     configuration_type_reference_audit,
 };
 use sea_orm::DatabaseConnection;
@@ -48,6 +49,14 @@ pub async fn initialize_unit_database() -> Result<DatabaseConnection, crate::Err
         .exec(&connection)
         .await?;
 
+    configuration_group_members::Entity::delete_many()
+        .exec(&connection)
+        .await?;
+
+    configuration_groups::Entity::delete_many()
+        .exec(&connection)
+        .await?;
+
     configuration_key_reference::Entity::delete_many()
         .exec(&connection)
         .await?;
@@ -60,6 +69,14 @@ pub async fn initialize_unit_database() -> Result<DatabaseConnection, crate::Err
         .exec(&connection)
         .await?;
 
+    configuration_group_members_audit::Entity::delete_many()
+        .exec(&connection)
+        .await?;
+
+    configuration_groups_audit::Entity::delete_many()
+        .exec(&connection)
+        .await?;
+
     configuration_key_reference_audit::Entity::delete_many()
         .exec(&connection)
         .await?;
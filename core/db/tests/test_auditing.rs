@@ -24,9 +24,19 @@
 #![feature(async_fn_in_trait)]
 
 use db::{
-    entities::{configuration_type_reference, configuration_type_reference_audit},
+    entities::{
+        configuration_entries_audit, configuration_key_reference_audit,
+        configuration_type_reference, configuration_type_reference_audit,
+    },
+    queries::configuration::{create_configuration_type, update_configuration_type},
+    request_id::{current_request_id, scope_request_id},
+    seeding::{
+        insert_configuration_entry, insert_configuration_key_reference,
+        insert_configuration_type_reference,
+    },
     testing::initialize_unit_database,
 };
+use domain_api::configuration::{ConfigurationTypeCreateRequest, ConfigurationTypeUpdateRequest};
 use sea_orm::{ActiveModelTrait, EntityTrait, QueryOrder, Set};
 use serial_test::serial;
 
@@ -227,3 +237,120 @@ async fn test_auditing() -> Result<(), db::Error> {
 
     Ok(())
 }
+
+#[async_std::test]
+#[serial]
+async fn test_auditing_request_id() -> Result<(), db::Error> {
+    // Initialize unit testing database
+    let connection = initialize_unit_database().await?;
+
+    assert_eq!(current_request_id(), None);
+
+    // Write without a request id in scope
+    let boolean = create_configuration_type(
+        &connection,
+        &ConfigurationTypeCreateRequest {
+            name: "boolean".to_owned(),
+            description: "A true/false value".to_owned(),
+        },
+    )
+    .await?;
+
+    // Write with a request id in scope
+    scope_request_id("test-request-id".to_owned(), async {
+        assert_eq!(current_request_id(), Some("test-request-id".to_owned()));
+
+        update_configuration_type(
+            &connection,
+            boolean.id,
+            &ConfigurationTypeUpdateRequest {
+                description: "Another true/false value".to_owned(),
+            },
+        )
+        .await
+    })
+    .await?;
+
+    assert_eq!(current_request_id(), None);
+
+    // The request id must not leak into later transactions on the same
+    // connection
+    create_configuration_type(
+        &connection,
+        &ConfigurationTypeCreateRequest {
+            name: "integer".to_owned(),
+            description: "A signed integer value".to_owned(),
+        },
+    )
+    .await?;
+
+    // See if it was audited correctly
+    let audit_rows = configuration_type_reference_audit::Entity::find()
+        .order_by_asc(configuration_type_reference_audit::Column::AuditId)
+        .all(&connection)
+        .await?;
+
+    assert_eq!(audit_rows.len(), 3);
+    assert_eq!(audit_rows[0].audit_request_id, None);
+    assert_eq!(
+        audit_rows[1].audit_request_id,
+        Some("test-request-id".to_owned())
+    );
+    assert_eq!(audit_rows[2].audit_request_id, None);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_auditing_request_id_seeding() -> Result<(), db::Error> {
+    // Initialize unit testing database
+    let connection = initialize_unit_database().await?;
+
+    // Seed with a request id in scope
+    scope_request_id("test-seeding-request-id".to_owned(), async {
+        let boolean_id =
+            insert_configuration_type_reference(&connection, "boolean", "A true/false value")
+                .await?;
+
+        let key_id = insert_configuration_key_reference(
+            &connection,
+            "theme.darkMode",
+            "Whether or not to use dark mode",
+            boolean_id,
+            false,
+            false,
+            true,
+        )
+        .await?;
+
+        insert_configuration_entry(&connection, key_id, 1, None, "false").await
+    })
+    .await?;
+
+    // See if every write was audited with it
+    let request_id = Some("test-seeding-request-id".to_owned());
+
+    let type_audit_rows = configuration_type_reference_audit::Entity::find()
+        .all(&connection)
+        .await?;
+
+    assert_eq!(type_audit_rows.len(), 1);
+    assert_eq!(type_audit_rows[0].audit_request_id, request_id);
+
+    let key_audit_rows = configuration_key_reference_audit::Entity::find()
+        .all(&connection)
+        .await?;
+
+    assert_eq!(key_audit_rows.len(), 1);
+    assert_eq!(key_audit_rows[0].audit_request_id, request_id);
+
+    let entry_audit_rows = configuration_entries_audit::Entity::find()
+        .all(&connection)
+        .await?;
+
+    assert_eq!(entry_audit_rows.len(), 1);
+    assert_eq!(entry_audit_rows[0].audit_request_id, request_id);
+
+    Ok(())
+}
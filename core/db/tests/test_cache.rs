@@ -0,0 +1,269 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![allow(incomplete_features)]
+#![feature(async_fn_in_trait)]
+
+use db::{
+    queries::configuration::{
+        cache::{ConfigurationCache, ConfigurationSnapshot},
+        find_configuration_key_by_name, replace_configuration_entry_items,
+    },
+    seeding::{insert_configuration_key_reference, insert_configuration_type_reference},
+    testing::initialize_unit_database,
+};
+use domain_api::configuration::{ConfigurationEffectiveSource, ConfigurationValueResponse};
+use sea_orm::DatabaseConnection;
+use serial_test::serial;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+/// Wait for the cache to reflect a change, failing if it takes too long.
+async fn wait_for_snapshot(
+    cache: &ConfigurationCache,
+    connection: &DatabaseConnection,
+    predicate: impl Fn(&ConfigurationSnapshot) -> bool,
+) -> Result<Arc<ConfigurationSnapshot>, db::Error> {
+    for _ in 0..50 {
+        let snapshot = cache.snapshot(connection).await?;
+
+        if predicate(&snapshot) {
+            return Ok(snapshot);
+        }
+
+        async_std::task::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!("timed out waiting for the cache to be invalidated");
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_snapshot() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+    let integer_id =
+        insert_configuration_type_reference(&connection, "integer", "A whole number").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "theme.fontSize",
+        "The font size in points",
+        integer_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    let snapshot = ConfigurationSnapshot::load(&connection, &BTreeMap::new()).await?;
+
+    let system_enabled_code =
+        find_configuration_key_by_name(snapshot.keys(), "system.enabled.code")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    replace_configuration_entry_items(
+        &connection,
+        system_enabled_code,
+        None,
+        &[boolean_value(true)],
+    )
+    .await?;
+    replace_configuration_entry_items(
+        &connection,
+        system_enabled_code,
+        Some("user"),
+        &[boolean_value(false)],
+    )
+    .await?;
+
+    // Snapshots do not change once loaded
+    assert_eq!(snapshot.get_boolean("system.enabled.code", None)?, None);
+
+    let snapshot = ConfigurationSnapshot::load(&connection, &BTreeMap::new()).await?;
+
+    assert_eq!(snapshot.types().len(), 2);
+    assert_eq!(snapshot.keys().len(), 2);
+
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", None)?,
+        Some(true)
+    );
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", Some("user"))?,
+        Some(false)
+    );
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", Some("other"))?,
+        Some(true)
+    );
+    assert!(matches!(
+        snapshot.get_boolean("theme.fontSize", None),
+        Err(db::Error::ConfigurationValueTypeMismatch(_))
+    ));
+    assert!(matches!(
+        snapshot.get_boolean("system.enabled.missing", None),
+        Err(db::Error::ConfigurationKeyNotFoundByName(_))
+    ));
+    assert!(matches!(
+        snapshot.get_boolean("not a key", None),
+        Err(db::Error::ConfigurationKeyNameInvalid(_))
+    ));
+
+    // Keys without items are left out of the entries
+    let entries = snapshot.entries(Some("user"), None)?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].key.name, "system.enabled.code");
+    assert_eq!(entries[0].items_global.len(), 1);
+    assert_eq!(entries[0].user.as_ref().unwrap().items.len(), 1);
+
+    assert!(snapshot.entries(None, None)?[0].user.is_none());
+    assert!(snapshot.entries(None, Some("theme"))?.is_empty());
+    assert!(matches!(
+        snapshot.entries(None, Some("not a prefix")),
+        Err(db::Error::ConfigurationKeyNameInvalid(_))
+    ));
+
+    let entry = snapshot.entry("theme.fontSize", Some("user"))?;
+    assert!(entry.items_global.is_empty());
+    assert!(entry.user.is_none());
+
+    // Every key is resolved, including the ones without items
+    let effective = snapshot.effective(Some("user"));
+    assert_eq!(effective.len(), 2);
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::User);
+    assert_eq!(effective[0].value.as_ref().unwrap().as_boolean, Some(false));
+    assert_eq!(effective[1].source, ConfigurationEffectiveSource::Unset);
+
+    assert_eq!(
+        snapshot
+            .effective_entry("system.enabled.code", None)?
+            .source,
+        ConfigurationEffectiveSource::Global
+    );
+
+    // Environment variables are applied when the snapshot is loaded
+    let environment_overrides =
+        BTreeMap::from([("SYSTEM.ENABLED.CODE".to_owned(), "false".to_owned())]);
+
+    let snapshot = ConfigurationSnapshot::load(&connection, &environment_overrides).await?;
+
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", None)?,
+        Some(false)
+    );
+    assert_eq!(
+        snapshot
+            .effective_entry("system.enabled.code", Some("user"))?
+            .source,
+        ConfigurationEffectiveSource::Environment
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_cache() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let cache = ConfigurationCache::new(BTreeMap::new());
+
+    // Without a watcher, every snapshot is loaded from the database
+    let first = cache.snapshot(&connection).await?;
+    let second = cache.snapshot(&connection).await?;
+    assert!(!Arc::ptr_eq(&first, &second));
+
+    let watcher = cache.watch(&connection).await?;
+    let task = async_std::task::spawn(watcher.run());
+
+    // With a watcher, snapshots are shared until the configuration changes
+    let first = cache.snapshot(&connection).await?;
+    let second = cache.snapshot(&connection).await?;
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(first.get_boolean("system.enabled.code", None)?, None);
+
+    let key = find_configuration_key_by_name(first.keys(), "system.enabled.code")?;
+
+    replace_configuration_entry_items(
+        &connection,
+        key,
+        None,
+        &[ConfigurationValueResponse {
+            as_boolean: Some(true),
+            ..Default::default()
+        }],
+    )
+    .await?;
+
+    let snapshot = wait_for_snapshot(&cache, &connection, |snapshot| {
+        snapshot
+            .get_boolean("system.enabled.code", None)
+            .unwrap()
+            .is_some()
+    })
+    .await?;
+
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", None)?,
+        Some(true)
+    );
+
+    // Stopping the watcher stops the caching
+    task.cancel().await;
+
+    let first = cache.snapshot(&connection).await?;
+    let second = cache.snapshot(&connection).await?;
+    assert!(!Arc::ptr_eq(&first, &second));
+
+    Ok(())
+}
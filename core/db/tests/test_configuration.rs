@@ -24,16 +24,37 @@
 #![feature(async_fn_in_trait)]
 
 use db::{
+    connect_db,
+    entities::{configuration_entries, configuration_key_reference},
     queries::configuration::{
-        get_all_configuration_entries, get_all_configuration_keys, get_all_configuration_types,
+        build_configuration_entry_tree, cache::ConfigurationSnapshot,
+        check_configuration_environment_overrides, create_configuration_key,
+        create_configuration_type, deactivate_configuration_entry_items,
+        deactivate_configuration_key, deactivate_configuration_type,
+        find_configuration_key_by_name, get_all_configuration_entries, get_all_configuration_keys,
+        get_all_configuration_types, get_configuration_constraint_violations,
+        get_configuration_key_by_name, get_effective_configuration,
+        insert_configuration_entry_item, replace_configuration_entry_items,
+        update_configuration_key, update_configuration_type, ConfigurationConstraintViolation,
+        ConfigurationEntryScope,
     },
     seeding::{
         insert_configuration_entry, insert_configuration_key_reference,
         insert_configuration_type_reference,
     },
     testing::initialize_unit_database,
+    DatabaseInstance,
 };
+use domain_api::configuration::{
+    ConfigurationEffectiveSetResponse, ConfigurationEffectiveSource, ConfigurationEntrySetResponse,
+    ConfigurationKeyConstraintsResponse, ConfigurationKeyCreateRequest,
+    ConfigurationKeyUpdateRequest, ConfigurationTypeCreateRequest, ConfigurationTypeUpdateRequest,
+    ConfigurationValueResponse,
+};
+use futures::{future::join_all, pin_mut};
+use sea_orm::{EntityTrait, QuerySelect, Set, TransactionTrait};
 use serial_test::serial;
+use std::{collections::BTreeMap, time::Duration};
 
 #[async_std::test]
 #[serial]
@@ -139,6 +160,53 @@ async fn test_get_all_configuration_keys() -> Result<(), db::Error> {
     Ok(())
 }
 
+#[async_std::test]
+#[serial]
+async fn test_get_configuration_key_by_name() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let systems_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let key = get_configuration_key_by_name(&connection, "systems.enabled.code").await?;
+
+    assert_eq!(key.id, systems_enabled_code_id);
+    assert_eq!(key.name, "systems.enabled.code");
+    assert_eq!(key.configuration_type.id, boolean_id);
+    assert_eq!(key.configuration_type.name, "boolean");
+
+    assert!(matches!(
+        get_configuration_key_by_name(&connection, "systems.enabled.missing").await,
+        Err(db::Error::ConfigurationKeyNotFoundByName(_))
+    ));
+
+    assert!(matches!(
+        get_configuration_key_by_name(&connection, "systems/enabled").await,
+        Err(db::Error::ConfigurationKeyNameInvalid(_))
+    ));
+
+    // Deactivated keys are not found
+    deactivate_configuration_key(&connection, systems_enabled_code_id).await?;
+
+    assert!(matches!(
+        get_configuration_key_by_name(&connection, "systems.enabled.code").await,
+        Err(db::Error::ConfigurationKeyNotFoundByName(_))
+    ));
+
+    Ok(())
+}
+
 #[async_std::test]
 #[serial]
 async fn test_get_all_configuration_entries() -> Result<(), db::Error> {
@@ -170,13 +238,13 @@ async fn test_get_all_configuration_entries() -> Result<(), db::Error> {
 
     let keys = get_all_configuration_keys(&connection, &types).await?;
 
-    let entries = get_all_configuration_entries(&connection, &keys, None).await?;
+    let entries = get_all_configuration_entries(&connection, &keys, None, None).await?;
 
     assert_eq!(entries.len(), 0);
 
     insert_configuration_entry(&connection, systems_enabled_code_id, 1, None, "true").await?;
 
-    let entries = get_all_configuration_entries(&connection, &keys, None).await?;
+    let entries = get_all_configuration_entries(&connection, &keys, None, None).await?;
 
     assert_eq!(entries.len(), 1);
 
@@ -205,3 +273,1305 @@ async fn test_get_all_configuration_entries() -> Result<(), db::Error> {
 
     Ok(())
 }
+
+#[async_std::test]
+#[serial]
+async fn test_get_all_configuration_entries_unsupported_type() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    // Types written around the database layer are not checked
+    let color_id =
+        insert_configuration_type_reference(&connection, "color", "A color value").await?;
+
+    let systems_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let theme_color_id = insert_configuration_key_reference(
+        &connection,
+        "theme.color",
+        "The color of the theme",
+        color_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, systems_enabled_code_id, 1, None, "true").await?;
+
+    configuration_entries::Entity::insert(configuration_entries::ActiveModel {
+        key_id: Set(theme_color_id),
+        order_index: Set(1),
+        value: Set("red".to_owned()),
+        ..Default::default()
+    })
+    .exec(&connection)
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    // The items of keys with an unsupported type are skipped
+    let entries = get_all_configuration_entries(&connection, &keys, None, None).await?;
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].key.name, "systems.enabled.code");
+
+    let effective = get_effective_configuration(&connection, &keys, &BTreeMap::new(), None).await?;
+
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::Global);
+    assert_eq!(effective[1].source, ConfigurationEffectiveSource::Unset);
+
+    let snapshot = ConfigurationSnapshot::load(&connection, &BTreeMap::new()).await?;
+
+    assert_eq!(
+        snapshot.get_boolean("systems.enabled.code", None)?,
+        Some(true)
+    );
+    assert!(snapshot.entry("theme.color", None)?.items_global.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_get_configuration_entries_by_prefix() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    for (name, value) in [
+        ("systems.enabled", "true"),
+        ("systems.enabled.code", "true"),
+        ("systems.enabled.ticket", "false"),
+        ("systems.enabledLegacy", "false"),
+        ("systems_enabled.code", "false"),
+        ("theme.darkMode", "false"),
+    ] {
+        let key_id = insert_configuration_key_reference(
+            &connection,
+            name,
+            "A flag",
+            boolean_id,
+            false,
+            false,
+            false,
+        )
+        .await?;
+
+        insert_configuration_entry(&connection, key_id, 1, None, value).await?;
+    }
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    let names = |entries: ConfigurationEntrySetResponse| {
+        let mut names = entries
+            .into_iter()
+            .map(|entry| entry.key.name)
+            .collect::<Vec<String>>();
+
+        names.sort();
+
+        names
+    };
+
+    // Prefixes match whole segments only
+    let entries =
+        get_all_configuration_entries(&connection, &keys, None, Some("systems.enabled")).await?;
+
+    assert_eq!(
+        names(entries),
+        vec![
+            "systems.enabled",
+            "systems.enabled.code",
+            "systems.enabled.ticket"
+        ]
+    );
+
+    // Underscores are not wildcards
+    let entries =
+        get_all_configuration_entries(&connection, &keys, None, Some("systems_enabled.")).await?;
+
+    assert_eq!(names(entries), vec!["systems_enabled.code"]);
+
+    let entries =
+        get_all_configuration_entries(&connection, &keys, None, Some("systems.missing")).await?;
+
+    assert!(entries.is_empty());
+
+    assert!(matches!(
+        get_all_configuration_entries(&connection, &keys, None, Some("systems/enabled")).await,
+        Err(db::Error::ConfigurationKeyNameInvalid(_))
+    ));
+
+    // Entries can be nested by the segments of their key names
+    let tree = build_configuration_entry_tree(
+        get_all_configuration_entries(&connection, &keys, None, Some("systems.enabled")).await?,
+    );
+
+    assert_eq!(tree.entry, None);
+    assert_eq!(tree.children.len(), 1);
+
+    let enabled = &tree.children["systems"].children["enabled"];
+
+    assert_eq!(enabled.entry.as_ref().unwrap().key.name, "systems.enabled");
+    assert_eq!(
+        enabled.children.keys().collect::<Vec<&String>>(),
+        vec!["code", "ticket"]
+    );
+    assert_eq!(
+        enabled.children["code"]
+            .entry
+            .as_ref()
+            .unwrap()
+            .items_global[0]
+            .value
+            .as_boolean,
+        Some(true)
+    );
+    assert!(enabled.children["code"].children.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_configuration_entry_items() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let integer_id =
+        insert_configuration_type_reference(&connection, "integer", "A signed integer value")
+            .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.limits.upload",
+        "Maximum upload size",
+        integer_id,
+        true,
+        true,
+        false,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let key = find_configuration_key_by_name(&keys, "system.limits.upload")?;
+
+    assert!(find_configuration_key_by_name(&keys, "system.limits.download").is_err());
+
+    let integer_value = |x| ConfigurationValueResponse {
+        as_integer: Some(x),
+        ..Default::default()
+    };
+
+    // Insert two items
+    insert_configuration_entry_item(&connection, key, None, &integer_value(5)).await?;
+    let entry = insert_configuration_entry_item(&connection, key, None, &integer_value(6)).await?;
+
+    assert_eq!(entry.key.id, key.id);
+    assert_eq!(entry.items_global.len(), 2);
+    assert_eq!(entry.items_global[0].value.as_integer, Some(5));
+    assert_eq!(entry.items_global[1].value.as_integer, Some(6));
+    assert_eq!(entry.user, None);
+
+    // Values of the wrong type are rejected
+    assert!(insert_configuration_entry_item(
+        &connection,
+        key,
+        None,
+        &ConfigurationValueResponse {
+            as_string: Some("5".to_owned()),
+            ..Default::default()
+        }
+    )
+    .await
+    .is_err());
+
+    // Replace them
+    let entry =
+        replace_configuration_entry_items(&connection, key, None, &[integer_value(7)]).await?;
+
+    assert_eq!(entry.items_global.len(), 1);
+    assert_eq!(entry.items_global[0].value.as_integer, Some(7));
+
+    let entries = get_all_configuration_entries(&connection, &keys, None, None).await?;
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0], entry);
+
+    // Deactivate them
+    let entry = deactivate_configuration_entry_items(&connection, key, None).await?;
+
+    assert_eq!(entry.items_global.len(), 0);
+
+    let entries = get_all_configuration_entries(&connection, &keys, None, None).await?;
+
+    assert_eq!(entries.len(), 0);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_configuration_entry_items_concurrently() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let integer_id =
+        insert_configuration_type_reference(&connection, "integer", "A signed integer value")
+            .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.limits.upload",
+        "Maximum upload size",
+        integer_id,
+        true,
+        true,
+        false,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let key = find_configuration_key_by_name(&keys, "system.limits.upload")?;
+
+    let values = (0..8)
+        .map(|x| ConfigurationValueResponse {
+            as_integer: Some(x),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    // Every item gets its own order index
+    for result in join_all(
+        values
+            .iter()
+            .map(|value| insert_configuration_entry_item(&connection, key, None, value)),
+    )
+    .await
+    {
+        result?;
+    }
+
+    let mut order_indices = configuration_entries::Entity::find()
+        .all(&connection)
+        .await?
+        .into_iter()
+        .map(|row| row.order_index)
+        .collect::<Vec<i32>>();
+
+    order_indices.sort();
+
+    assert_eq!(order_indices, (1..=8).collect::<Vec<i32>>());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_deactivate_configuration_entry_items_concurrently() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let key_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, key_id, 1, None, "true").await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let key = find_configuration_key_by_name(&keys, "systems.enabled.code")?;
+
+    // Hold the key's lock the way a concurrent writer does
+    let other_connection = connect_db(DatabaseInstance::Unit)?;
+    let other_transaction = other_connection.begin().await?;
+
+    configuration_key_reference::Entity::find_by_id(key_id)
+        .lock_exclusive()
+        .one(&other_transaction)
+        .await?;
+
+    // Deactivating waits for the writer to finish
+    let deactivate = deactivate_configuration_entry_items(&connection, key, None);
+
+    pin_mut!(deactivate);
+
+    assert!(
+        async_std::future::timeout(Duration::from_millis(200), &mut deactivate)
+            .await
+            .is_err()
+    );
+
+    other_transaction.commit().await?;
+
+    let entry = deactivate.await?;
+
+    assert!(entry.items_global.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_configuration_entry_user_items() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    // Overrides are rejected for keys that do not allow them
+    let key = find_configuration_key_by_name(&keys, "systems.enabled.code")?;
+
+    assert!(matches!(
+        replace_configuration_entry_items(&connection, key, Some("user"), &[boolean_value(true)])
+            .await,
+        Err(db::Error::ConfigurationKeyUserOverrideNotAllowed(_))
+    ));
+
+    // Overrides are stored separately from global items
+    let key = find_configuration_key_by_name(&keys, "theme.darkMode")?;
+
+    replace_configuration_entry_items(&connection, key, None, &[boolean_value(false)]).await?;
+
+    let entry =
+        replace_configuration_entry_items(&connection, key, Some("user"), &[boolean_value(true)])
+            .await?;
+
+    assert_eq!(entry.items_global.len(), 1);
+    assert_eq!(entry.items_global[0].value.as_boolean, Some(false));
+
+    let user = entry.user.unwrap();
+
+    assert_eq!(user.user_id, "user");
+    assert_eq!(user.items.len(), 1);
+    assert_eq!(user.items[0].value.as_boolean, Some(true));
+
+    let entries = get_all_configuration_entries(&connection, &keys, Some("other"), None).await?;
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].user, None);
+
+    // Clearing overrides leaves the global items in place
+    let entry = deactivate_configuration_entry_items(&connection, key, Some("user")).await?;
+
+    assert_eq!(entry.items_global.len(), 1);
+    assert_eq!(entry.user, None);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_get_effective_configuration() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+    let string_id =
+        insert_configuration_type_reference(&connection, "string", "A string value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let systems_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let editor_languages_id = insert_configuration_key_reference(
+        &connection,
+        "editor.languages",
+        "The languages to enable in the editor",
+        string_id,
+        false,
+        true,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "editor.font",
+        "The font to use in the editor",
+        string_id,
+        true,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, None, "false").await?;
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, Some("user"), "true").await?;
+
+    // Legacy data written around the database layer, from before the key
+    // stopped allowing overrides and violating the single value rule
+    for (order_index, user_id, value) in [
+        (1, None, "false"),
+        (2, None, "true"),
+        (1, Some("user"), "false"),
+    ] {
+        configuration_entries::Entity::insert(configuration_entries::ActiveModel {
+            key_id: Set(systems_enabled_code_id),
+            order_index: Set(order_index),
+            user_id: Set(user_id.map(str::to_owned)),
+            value: Set(value.to_owned()),
+            ..Default::default()
+        })
+        .exec(&connection)
+        .await?;
+    }
+
+    insert_configuration_entry(&connection, editor_languages_id, 1, None, "rust").await?;
+    insert_configuration_entry(&connection, editor_languages_id, 2, None, "c").await?;
+    insert_configuration_entry(&connection, editor_languages_id, 1, Some("user"), "python").await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    let find = |effective: &ConfigurationEffectiveSetResponse, name: &str| {
+        effective
+            .iter()
+            .find(|entry| entry.key.name == name)
+            .cloned()
+            .unwrap()
+    };
+
+    // Without a user only global items are used
+    let effective = get_effective_configuration(&connection, &keys, &BTreeMap::new(), None).await?;
+
+    assert_eq!(effective.len(), 4);
+
+    let entry = find(&effective, "theme.darkMode");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::Global);
+    assert_eq!(entry.value.unwrap().as_boolean, Some(false));
+    assert_eq!(entry.values.len(), 1);
+
+    // With a user, overrides replace the global items
+    let effective =
+        get_effective_configuration(&connection, &keys, &BTreeMap::new(), Some("user")).await?;
+
+    let entry = find(&effective, "theme.darkMode");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::User);
+    assert_eq!(entry.value.unwrap().as_boolean, Some(true));
+
+    // Overrides are ignored for keys that do not allow them and the last item
+    // wins for keys that do not allow multiple values
+    let entry = find(&effective, "systems.enabled.code");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::Global);
+    assert_eq!(entry.value.unwrap().as_boolean, Some(true));
+    assert_eq!(entry.values.len(), 1);
+
+    // Keys that allow multiple values resolve to a list
+    let entry = find(&effective, "editor.languages");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::User);
+    assert_eq!(entry.value, None);
+    assert_eq!(entry.values.len(), 1);
+    assert_eq!(entry.values[0].as_string, Some("python".to_owned()));
+
+    let effective =
+        get_effective_configuration(&connection, &keys, &BTreeMap::new(), Some("other")).await?;
+
+    let entry = find(&effective, "editor.languages");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::Global);
+    assert_eq!(
+        entry
+            .values
+            .iter()
+            .map(|value| value.as_string.as_deref().unwrap())
+            .collect::<Vec<&str>>(),
+        vec!["rust", "c"]
+    );
+
+    // Keys without any items are unset
+    let entry = find(&effective, "editor.font");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::Unset);
+    assert_eq!(entry.value, None);
+    assert!(entry.values.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_get_effective_configuration_environment() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+    let string_id =
+        insert_configuration_type_reference(&connection, "string", "A string value").await?;
+
+    let systems_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "editor.languages",
+        "The languages to enable in the editor",
+        string_id,
+        true,
+        true,
+        false,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, systems_enabled_code_id, 1, None, "true").await?;
+    insert_configuration_entry(
+        &connection,
+        systems_enabled_code_id,
+        1,
+        Some("user"),
+        "true",
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    let find = |effective: &ConfigurationEffectiveSetResponse, name: &str| {
+        effective
+            .iter()
+            .find(|entry| entry.key.name == name)
+            .cloned()
+            .unwrap()
+    };
+
+    // Environment variables win over user overrides and match without regard
+    // to case
+    let environment_overrides = BTreeMap::from([
+        ("SYSTEMS.ENABLED.CODE".to_owned(), "false".to_owned()),
+        ("editor.languages".to_owned(), "rust".to_owned()),
+        ("EDITOR.MISSING".to_owned(), "ignored".to_owned()),
+    ]);
+
+    check_configuration_environment_overrides(&keys, &environment_overrides)?;
+
+    let effective =
+        get_effective_configuration(&connection, &keys, &environment_overrides, Some("user"))
+            .await?;
+
+    let entry = find(&effective, "systems.enabled.code");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::Environment);
+    assert_eq!(entry.value.unwrap().as_boolean, Some(false));
+    assert_eq!(entry.values.len(), 1);
+
+    let entry = find(&effective, "editor.languages");
+
+    assert_eq!(entry.source, ConfigurationEffectiveSource::Environment);
+    assert_eq!(entry.value, None);
+    assert_eq!(entry.values[0].as_string, Some("rust".to_owned()));
+
+    // Values are parsed as the type of their key when they are checked, and
+    // are ignored when they are read
+    let environment_overrides =
+        BTreeMap::from([("SYSTEMS.ENABLED.CODE".to_owned(), "maybe".to_owned())]);
+
+    assert!(matches!(
+        check_configuration_environment_overrides(&keys, &environment_overrides),
+        Err(db::Error::ConfigurationOverrideInvalid(name, error))
+            if name == "SYSTEMS.ENABLED.CODE"
+                && matches!(*error, db::Error::ConfigurationValueParseErrorBoolean(_))
+    ));
+
+    let effective =
+        get_effective_configuration(&connection, &keys, &environment_overrides, None).await?;
+
+    assert_eq!(
+        find(&effective, "systems.enabled.code").source,
+        ConfigurationEffectiveSource::Global
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_key_constraints() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let systems_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let key = find_configuration_key_by_name(&keys, "systems.enabled.code")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    // A required key without a global value is a violation
+    assert_eq!(
+        get_configuration_constraint_violations(&connection, &keys).await?,
+        vec![ConfigurationConstraintViolation::Required {
+            key_name: "systems.enabled.code".to_owned()
+        }]
+    );
+
+    insert_configuration_entry(&connection, systems_enabled_code_id, 1, None, "true").await?;
+
+    assert_eq!(
+        get_configuration_constraint_violations(&connection, &keys).await?,
+        vec![]
+    );
+
+    // Single-valued keys reject a second value on every write path
+    assert!(matches!(
+        insert_configuration_entry(&connection, systems_enabled_code_id, 2, None, "false").await,
+        Err(db::Error::ConfigurationKeyMultipleNotAllowed(_))
+    ));
+
+    assert!(matches!(
+        insert_configuration_entry_item(&connection, key, None, &boolean_value(false)).await,
+        Err(db::Error::ConfigurationKeyMultipleNotAllowed(_))
+    ));
+
+    assert!(matches!(
+        replace_configuration_entry_items(
+            &connection,
+            key,
+            None,
+            &[boolean_value(true), boolean_value(false)]
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyMultipleNotAllowed(_))
+    ));
+
+    // Required keys cannot lose their global value
+    assert!(matches!(
+        deactivate_configuration_entry_items(&connection, key, None).await,
+        Err(db::Error::ConfigurationKeyRequired(_))
+    ));
+
+    // User overrides may be cleared
+    insert_configuration_entry(
+        &connection,
+        systems_enabled_code_id,
+        1,
+        Some("user"),
+        "false",
+    )
+    .await?;
+
+    deactivate_configuration_entry_items(&connection, key, Some("user")).await?;
+
+    // Legacy data written around the database layer is reported
+    let legacy_id = configuration_entries::Entity::insert(configuration_entries::ActiveModel {
+        key_id: Set(systems_enabled_code_id),
+        order_index: Set(2),
+        value: Set("false".to_owned()),
+        ..Default::default()
+    })
+    .exec(&connection)
+    .await?
+    .last_insert_id;
+
+    let violations = get_configuration_constraint_violations(&connection, &keys).await?;
+
+    assert_eq!(violations.len(), 1);
+    assert!(matches!(
+        &violations[0],
+        ConfigurationConstraintViolation::MultipleNotAllowed {
+            key_name,
+            scope: ConfigurationEntryScope::Global,
+            entry_ids,
+        } if key_name == "systems.enabled.code" && entry_ids.len() == 2 && entry_ids[1] == legacy_id
+    ));
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_configuration_types_and_keys() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    // Create types
+    let boolean = create_configuration_type(
+        &connection,
+        &ConfigurationTypeCreateRequest {
+            name: "boolean".to_owned(),
+            description: "A true/false value".to_owned(),
+        },
+    )
+    .await?;
+
+    let integer = create_configuration_type(
+        &connection,
+        &ConfigurationTypeCreateRequest {
+            name: "integer".to_owned(),
+            description: "A signed integer".to_owned(),
+        },
+    )
+    .await?;
+
+    assert!(matches!(
+        create_configuration_type(
+            &connection,
+            &ConfigurationTypeCreateRequest {
+                name: "boolean".to_owned(),
+                description: "Another true/false value".to_owned(),
+            },
+        )
+        .await,
+        Err(db::Error::ConfigurationTypeAlreadyExists(_))
+    ));
+
+    let integer = update_configuration_type(
+        &connection,
+        integer.id,
+        &ConfigurationTypeUpdateRequest {
+            description: "A signed integer value".to_owned(),
+        },
+    )
+    .await?;
+
+    assert_eq!(integer.description, "A signed integer value");
+
+    let types = get_all_configuration_types(&connection).await?;
+
+    assert_eq!(types, vec![boolean.clone(), integer.clone()]);
+
+    // Create a key
+    let key = create_configuration_key(
+        &connection,
+        &types,
+        &ConfigurationKeyCreateRequest {
+            name: "systems.enabled.code".to_owned(),
+            description: "Whether or not the Code system is enabled".to_owned(),
+            type_id: boolean.id,
+            optional: true,
+            allows_multiple: false,
+            allows_user_override: false,
+            constraints: Default::default(),
+            rollout: None,
+        },
+    )
+    .await?;
+
+    assert_eq!(key.configuration_type, boolean);
+
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    assert_eq!(keys, vec![key.clone()]);
+
+    insert_configuration_entry(&connection, key.id, 1, None, "true").await?;
+
+    // Types that are in use cannot be deactivated
+    assert!(matches!(
+        deactivate_configuration_type(&connection, boolean.id).await,
+        Err(db::Error::ConfigurationTypeInUse(_))
+    ));
+
+    // The type cannot change if the values would not parse
+    let update_request = ConfigurationKeyUpdateRequest {
+        description: "Whether or not the Code system is turned on".to_owned(),
+        type_id: integer.id,
+        optional: false,
+        allows_multiple: false,
+        allows_user_override: true,
+        constraints: Default::default(),
+        rollout: None,
+    };
+
+    assert!(matches!(
+        update_configuration_key(&connection, &types, key.id, &update_request).await,
+        Err(db::Error::ConfigurationKeyTypeChangeIncompatible(_))
+    ));
+
+    let key = update_configuration_key(
+        &connection,
+        &types,
+        key.id,
+        &ConfigurationKeyUpdateRequest {
+            type_id: boolean.id,
+            ..update_request
+        },
+    )
+    .await?;
+
+    assert_eq!(
+        key.description,
+        "Whether or not the Code system is turned on"
+    );
+    assert!(!key.optional);
+    assert!(key.allows_user_override);
+
+    // Deactivating a key deactivates its entries
+    deactivate_configuration_key(&connection, key.id).await?;
+
+    assert_eq!(
+        get_all_configuration_keys(&connection, &types).await?,
+        vec![]
+    );
+    assert_eq!(
+        get_all_configuration_entries(&connection, &vec![key], None, None).await?,
+        vec![]
+    );
+
+    deactivate_configuration_type(&connection, boolean.id).await?;
+
+    assert_eq!(
+        get_all_configuration_types(&connection).await?,
+        vec![integer]
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_key_value_constraints() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let integer_id =
+        insert_configuration_type_reference(&connection, "integer", "A signed integer").await?;
+
+    let string_id =
+        insert_configuration_type_reference(&connection, "string", "A string value").await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+
+    let integer_value = |x| ConfigurationValueResponse {
+        as_integer: Some(x),
+        ..Default::default()
+    };
+
+    let string_value = |x: &str| ConfigurationValueResponse {
+        as_string: Some(x.to_owned()),
+        ..Default::default()
+    };
+
+    let create_request = |name: &str, type_id, constraints| ConfigurationKeyCreateRequest {
+        name: name.to_owned(),
+        description: "A constrained key".to_owned(),
+        type_id,
+        optional: true,
+        allows_multiple: true,
+        allows_user_override: false,
+        constraints,
+        rollout: None,
+    };
+
+    // Constraints must fit the type of the key
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &types,
+            &create_request(
+                "limits.invalid",
+                string_id,
+                ConfigurationKeyConstraintsResponse {
+                    minimum: Some(1.0),
+                    ..Default::default()
+                },
+            ),
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyConstraintsInvalid(_, _))
+    ));
+
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &types,
+            &create_request(
+                "limits.invalid",
+                integer_id,
+                ConfigurationKeyConstraintsResponse {
+                    minimum: Some(10.0),
+                    maximum: Some(1.0),
+                    ..Default::default()
+                },
+            ),
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyConstraintsInvalid(_, _))
+    ));
+
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &types,
+            &create_request(
+                "limits.invalid",
+                string_id,
+                ConfigurationKeyConstraintsResponse {
+                    pattern: Some("(".to_owned()),
+                    ..Default::default()
+                },
+            ),
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyConstraintsInvalid(_, _))
+    ));
+
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &types,
+            &create_request(
+                "limits.invalid",
+                string_id,
+                ConfigurationKeyConstraintsResponse {
+                    allowed_values: Some(vec![integer_value(1)]),
+                    ..Default::default()
+                },
+            ),
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyConstraintsInvalid(_, _))
+    ));
+
+    // Ranges
+    let workers = create_configuration_key(
+        &connection,
+        &types,
+        &create_request(
+            "limits.workers",
+            integer_id,
+            ConfigurationKeyConstraintsResponse {
+                minimum: Some(1.0),
+                maximum: Some(16.0),
+                ..Default::default()
+            },
+        ),
+    )
+    .await?;
+
+    assert_eq!(workers.constraints.minimum, Some(1.0));
+    assert_eq!(workers.constraints.maximum, Some(16.0));
+
+    insert_configuration_entry_item(&connection, &workers, None, &integer_value(16)).await?;
+
+    assert!(matches!(
+        insert_configuration_entry_item(&connection, &workers, None, &integer_value(17)).await,
+        Err(db::Error::ConfigurationValueOutOfRange(_))
+    ));
+
+    assert!(matches!(
+        replace_configuration_entry_items(&connection, &workers, None, &[integer_value(0)]).await,
+        Err(db::Error::ConfigurationValueOutOfRange(_))
+    ));
+
+    assert!(matches!(
+        insert_configuration_entry(&connection, workers.id, 2, None, "100").await,
+        Err(db::Error::ConfigurationValueOutOfRange(_))
+    ));
+
+    // Existing values must satisfy new constraints
+    assert!(matches!(
+        update_configuration_key(
+            &connection,
+            &types,
+            workers.id,
+            &ConfigurationKeyUpdateRequest {
+                description: "A constrained key".to_owned(),
+                type_id: integer_id,
+                optional: true,
+                allows_multiple: true,
+                allows_user_override: false,
+                constraints: ConfigurationKeyConstraintsResponse {
+                    maximum: Some(8.0),
+                    ..Default::default()
+                },
+                rollout: None,
+            },
+        )
+        .await,
+        Err(db::Error::ConfigurationValueOutOfRange(_))
+    ));
+
+    // Patterns
+    let hostname = create_configuration_key(
+        &connection,
+        &types,
+        &create_request(
+            "network.hostname",
+            string_id,
+            ConfigurationKeyConstraintsResponse {
+                pattern: Some("^[a-z.]+$".to_owned()),
+                ..Default::default()
+            },
+        ),
+    )
+    .await?;
+
+    insert_configuration_entry_item(&connection, &hostname, None, &string_value("localhost"))
+        .await?;
+
+    assert!(matches!(
+        insert_configuration_entry_item(&connection, &hostname, None, &string_value("Local Host"))
+            .await,
+        Err(db::Error::ConfigurationValuePatternMismatch(_))
+    ));
+
+    // Allowed values
+    let level = create_configuration_key(
+        &connection,
+        &types,
+        &create_request(
+            "logging.level",
+            string_id,
+            ConfigurationKeyConstraintsResponse {
+                allowed_values: Some(vec![string_value("info"), string_value("debug")]),
+                ..Default::default()
+            },
+        ),
+    )
+    .await?;
+
+    replace_configuration_entry_items(&connection, &level, None, &[string_value("debug")]).await?;
+
+    assert!(matches!(
+        replace_configuration_entry_items(&connection, &level, None, &[string_value("trace")])
+            .await,
+        Err(db::Error::ConfigurationValueNotAllowed(_))
+    ));
+
+    // Constraints are loaded along with the keys
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    assert_eq!(keys, vec![workers, hostname, level]);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_rich_configuration_values() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    for name in ["duration", "url", "enum"] {
+        create_configuration_type(
+            &connection,
+            &ConfigurationTypeCreateRequest {
+                name: name.to_owned(),
+                description: format!("A {name} value"),
+            },
+        )
+        .await?;
+    }
+
+    // Only supported kinds of values can be used as types
+    assert!(matches!(
+        create_configuration_type(
+            &connection,
+            &ConfigurationTypeCreateRequest {
+                name: "color".to_owned(),
+                description: "A color value".to_owned(),
+            },
+        )
+        .await,
+        Err(db::Error::ConfigurationTypeUnsupported(_))
+    ));
+
+    let types = get_all_configuration_types(&connection).await?;
+
+    let create_request =
+        |name: &str, type_index: usize, constraints| ConfigurationKeyCreateRequest {
+            name: name.to_owned(),
+            description: "A richly typed key".to_owned(),
+            type_id: types[type_index].id,
+            optional: true,
+            allows_multiple: false,
+            allows_user_override: false,
+            constraints,
+            rollout: None,
+        };
+
+    // Durations and URLs are stored in their canonical form
+    let timeout = create_configuration_key(
+        &connection,
+        &types,
+        &create_request("integration.webhook.timeout", 0, Default::default()),
+    )
+    .await?;
+
+    let entry = insert_configuration_entry_item(
+        &connection,
+        &timeout,
+        None,
+        &ConfigurationValueResponse {
+            as_duration: Some("90s".to_owned()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    assert_eq!(
+        entry.items_global[0].value.as_duration.as_deref(),
+        Some("1m 30s")
+    );
+
+    let url = create_configuration_key(
+        &connection,
+        &types,
+        &create_request("integration.webhook.url", 1, Default::default()),
+    )
+    .await?;
+
+    let entry = insert_configuration_entry_item(
+        &connection,
+        &url,
+        None,
+        &ConfigurationValueResponse {
+            as_url: Some("HTTPS://Example.com/hooks".to_owned()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    assert_eq!(
+        entry.items_global[0].value.as_url.as_deref(),
+        Some("https://example.com/hooks")
+    );
+
+    assert!(matches!(
+        replace_configuration_entry_items(
+            &connection,
+            &url,
+            None,
+            &[ConfigurationValueResponse {
+                as_url: Some("not a url".to_owned()),
+                ..Default::default()
+            }],
+        )
+        .await,
+        Err(db::Error::ConfigurationValueTypeMismatch(_))
+    ));
+
+    // Enum keys need a set of allowed values
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &types,
+            &create_request("logging.level", 2, Default::default()),
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyConstraintsInvalid(_, _))
+    ));
+
+    let enum_value = |x: &str| ConfigurationValueResponse {
+        as_enum: Some(x.to_owned()),
+        ..Default::default()
+    };
+
+    let level = create_configuration_key(
+        &connection,
+        &types,
+        &create_request(
+            "logging.level",
+            2,
+            ConfigurationKeyConstraintsResponse {
+                allowed_values: Some(vec![enum_value("info"), enum_value("debug")]),
+                ..Default::default()
+            },
+        ),
+    )
+    .await?;
+
+    insert_configuration_entry_item(&connection, &level, None, &enum_value("info")).await?;
+
+    assert!(matches!(
+        replace_configuration_entry_items(&connection, &level, None, &[enum_value("trace")]).await,
+        Err(db::Error::ConfigurationValueNotAllowed(_))
+    ));
+
+    Ok(())
+}
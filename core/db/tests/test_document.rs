@@ -0,0 +1,266 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![allow(incomplete_features)]
+#![feature(async_fn_in_trait)]
+
+use db::{
+    queries::configuration::{
+        document::{
+            apply_configuration_document, export_configuration_document,
+            plan_configuration_document, ConfigurationDocument, ConfigurationDocumentChange,
+            ConfigurationDocumentValue,
+        },
+        find_configuration_key_by_name, get_all_configuration_keys, get_all_configuration_types,
+        ConfigurationEntryScope,
+    },
+    testing::initialize_unit_database,
+};
+use serde_json::json;
+use serial_test::serial;
+
+/// Build a configuration document from JSON.
+fn document(value: serde_json::Value) -> ConfigurationDocument {
+    serde_json::from_value(value).unwrap()
+}
+
+#[async_std::test]
+#[serial]
+async fn test_apply_configuration_document() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let initial = document(json!({
+        "types": [
+            { "name": "boolean", "description": "A true/false value" },
+            { "name": "duration", "description": "A span of time" }
+        ],
+        "keys": [
+            {
+                "name": "system.enabled.code",
+                "description": "Whether or not the Code system is enabled",
+                "type": "boolean",
+                "allowsUserOverride": true
+            },
+            {
+                "name": "integration.webhook.timeout",
+                "description": "How long to wait for webhook deliveries",
+                "type": "duration"
+            }
+        ],
+        "entries": [
+            { "key": "system.enabled.code", "global": [true], "users": { "user": ["false"] } },
+            { "key": "integration.webhook.timeout", "global": ["90m"] }
+        ]
+    }));
+
+    // Everything is inserted into an empty database
+    let plan = apply_configuration_document(&connection, &initial, false).await?;
+
+    assert_eq!(
+        plan.iter().map(|x| x.to_string()).collect::<Vec<String>>(),
+        vec![
+            "+ type boolean",
+            "+ type duration",
+            "+ key system.enabled.code",
+            "+ key integration.webhook.timeout",
+            "+ entry system.enabled.code (global): [] -> [\"true\"]",
+            "+ entry system.enabled.code (user \"user\"): [] -> [\"false\"]",
+            "+ entry integration.webhook.timeout (global): [] -> [\"1h 30m\"]",
+        ]
+    );
+
+    // Exporting gives canonical values and native values where possible
+    let exported = export_configuration_document(&connection).await?;
+
+    assert_eq!(exported.types, initial.types);
+    assert_eq!(exported.keys, initial.keys);
+    assert_eq!(exported.entries.len(), 2);
+    assert_eq!(
+        exported.entries[0].global,
+        vec![ConfigurationDocumentValue::Boolean(true)]
+    );
+    assert_eq!(
+        exported.entries[0].users["user"],
+        vec![ConfigurationDocumentValue::Boolean(false)]
+    );
+    assert_eq!(
+        exported.entries[1].global,
+        vec![ConfigurationDocumentValue::Text("1h 30m".to_owned())]
+    );
+
+    // Applying an unchanged document changes nothing
+    assert!(plan_configuration_document(&connection, &exported)
+        .await?
+        .is_empty());
+
+    let changed = document(json!({
+        "types": [
+            { "name": "boolean", "description": "A true or false value" },
+            { "name": "duration", "description": "A span of time" }
+        ],
+        "keys": [
+            {
+                "name": "system.enabled.code",
+                "description": "Whether or not the Code system is enabled",
+                "type": "boolean",
+                "allowsMultiple": true
+            },
+            {
+                "name": "system.enabled.ticket",
+                "description": "Whether or not the Ticket system is enabled",
+                "type": "boolean",
+                "optional": true
+            }
+        ],
+        "entries": [
+            { "key": "system.enabled.code", "global": [true, false] }
+        ]
+    }));
+
+    // A dry run plans the changes without writing them
+    let plan = apply_configuration_document(&connection, &changed, true).await?;
+
+    assert_eq!(
+        plan,
+        vec![
+            ConfigurationDocumentChange::UpdateType(changed.types[0].clone()),
+            ConfigurationDocumentChange::InsertKey(changed.keys[1].clone()),
+            ConfigurationDocumentChange::ReplaceEntryItems {
+                key_name: "system.enabled.code".to_owned(),
+                scope: ConfigurationEntryScope::Global,
+                secret: false,
+                old: vec!["true".to_owned()],
+                new: vec!["true".to_owned(), "false".to_owned()],
+            },
+            ConfigurationDocumentChange::ReplaceEntryItems {
+                key_name: "system.enabled.code".to_owned(),
+                scope: ConfigurationEntryScope::User("user".to_owned()),
+                secret: false,
+                old: vec!["false".to_owned()],
+                new: vec![],
+            },
+            ConfigurationDocumentChange::UpdateKey(changed.keys[0].clone()),
+            ConfigurationDocumentChange::DeactivateKey("integration.webhook.timeout".to_owned()),
+        ]
+    );
+
+    assert_eq!(export_configuration_document(&connection).await?, exported);
+
+    // Keys and their entries are changed together
+    assert_eq!(
+        apply_configuration_document(&connection, &changed, false).await?,
+        plan
+    );
+
+    let exported = export_configuration_document(&connection).await?;
+
+    assert_eq!(exported.types, changed.types);
+    assert_eq!(exported.keys, changed.keys);
+    assert_eq!(
+        exported.entries[0].global,
+        vec![
+            ConfigurationDocumentValue::Boolean(true),
+            ConfigurationDocumentValue::Boolean(false)
+        ]
+    );
+    assert!(exported.entries[0].users.is_empty());
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+
+    assert!(find_configuration_key_by_name(&keys, "integration.webhook.timeout").is_err());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_apply_configuration_document_invalid() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let types = json!([{ "name": "boolean", "description": "A true/false value" }]);
+
+    let key = json!({
+        "name": "system.enabled.code",
+        "description": "Whether or not the Code system is enabled",
+        "type": "boolean"
+    });
+
+    // Names must be unique
+    assert!(matches!(
+        plan_configuration_document(
+            &connection,
+            &document(json!({ "types": types, "keys": [key, key] }))
+        )
+        .await,
+        Err(db::Error::ConfigurationDocumentDuplicate(_))
+    ));
+
+    // Entries need keys and keys need types
+    assert!(matches!(
+        plan_configuration_document(
+            &connection,
+            &document(json!({ "types": types, "entries": [{ "key": "x", "global": [true] }] }))
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyNotFoundByName(_))
+    ));
+
+    assert!(matches!(
+        plan_configuration_document(
+            &connection,
+            &document(json!({ "types": [], "keys": [key] }))
+        )
+        .await,
+        Err(db::Error::ConfigurationTypeNotFoundByName(_))
+    ));
+
+    // Values must parse as their key's type
+    assert!(matches!(
+        plan_configuration_document(
+            &connection,
+            &document(json!({
+                "types": types,
+                "keys": [key],
+                "entries": [{ "key": "system.enabled.code", "global": ["yes"] }]
+            }))
+        )
+        .await,
+        Err(db::Error::ConfigurationValueParseErrorBoolean(_))
+    ));
+
+    // Required keys need global items, and nothing is written if any change
+    // fails
+    assert!(matches!(
+        apply_configuration_document(
+            &connection,
+            &document(json!({ "types": types, "keys": [key] })),
+            false
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyRequired(_))
+    ));
+
+    assert!(get_all_configuration_types(&connection).await?.is_empty());
+
+    Ok(())
+}
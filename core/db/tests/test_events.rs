@@ -0,0 +1,216 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![allow(incomplete_features)]
+#![feature(async_fn_in_trait)]
+
+use db::{
+    queries::configuration::{
+        cache::ConfigurationCache, events::ConfigurationEventListener,
+        find_configuration_key_by_name, get_all_configuration_keys, get_all_configuration_types,
+        replace_configuration_entry_items, update_configuration_key,
+    },
+    seeding::{insert_configuration_key_reference, insert_configuration_type_reference},
+    testing::initialize_unit_database,
+};
+use domain_api::configuration::{
+    ConfigurationEventKind, ConfigurationEventResponse, ConfigurationKeyUpdateRequest,
+    ConfigurationValueResponse,
+};
+use sea_orm::DatabaseConnection;
+use serial_test::serial;
+use std::{collections::BTreeMap, time::Duration};
+
+/// Wait a short time for the next event, returning `None` if there is none.
+async fn try_next_event(
+    listener: &mut ConfigurationEventListener,
+    connection: &DatabaseConnection,
+) -> Result<Option<ConfigurationEventResponse>, db::Error> {
+    match async_std::future::timeout(Duration::from_secs(2), listener.next_event(connection)).await
+    {
+        Ok(event) => event.map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_events() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        true,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.enabled.code",
+        "Whether or not code is enabled",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let theme_dark_mode = find_configuration_key_by_name(&keys, "theme.darkMode")?;
+    let system_enabled_code = find_configuration_key_by_name(&keys, "system.enabled.code")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    let cache = ConfigurationCache::new(BTreeMap::new());
+    let watcher = cache.watch(&connection).await?;
+    let task = async_std::task::spawn(watcher.run());
+
+    // Listeners share the watcher's connection
+    let mut listener = ConfigurationEventListener::subscribe(&cache, Some("alice"), Some("theme"))?;
+    let mut other_listener = ConfigurationEventListener::subscribe(&cache, Some("bob"), None)?;
+
+    // Keys outside of the prefix and the overrides of other users are skipped
+    replace_configuration_entry_items(
+        &connection,
+        system_enabled_code,
+        None,
+        &[boolean_value(false)],
+    )
+    .await?;
+    replace_configuration_entry_items(
+        &connection,
+        theme_dark_mode,
+        Some("bob"),
+        &[boolean_value(true)],
+    )
+    .await?;
+
+    replace_configuration_entry_items(
+        &connection,
+        theme_dark_mode,
+        Some("alice"),
+        &[boolean_value(true)],
+    )
+    .await?;
+
+    let event = try_next_event(&mut listener, &connection)
+        .await?
+        .expect("expected an event for the user override");
+
+    assert_eq!(event.kind, ConfigurationEventKind::Entry);
+    assert_eq!(event.key_name, "theme.darkMode");
+    assert_eq!(event.user_id.as_deref(), Some("alice"));
+
+    let entry = event.entry.expect("expected the entry of an active key");
+    let user = entry.user.expect("expected the user override");
+    assert_eq!(user.user_id, "alice");
+    assert_eq!(user.items.len(), 1);
+    assert_eq!(user.items[0].value.as_boolean, Some(true));
+
+    // Changes to the key itself are sent too
+    update_configuration_key(
+        &connection,
+        &types,
+        theme_dark_mode_id,
+        &ConfigurationKeyUpdateRequest {
+            description: "Whether or not to use the dark theme".to_owned(),
+            type_id: boolean_id,
+            optional: true,
+            allows_multiple: false,
+            allows_user_override: true,
+            constraints: Default::default(),
+            rollout: None,
+        },
+    )
+    .await?;
+
+    let event = try_next_event(&mut listener, &connection)
+        .await?
+        .expect("expected an event for the key");
+
+    assert_eq!(event.kind, ConfigurationEventKind::Key);
+    assert_eq!(event.key_name, "theme.darkMode");
+    assert_eq!(event.user_id, None);
+    assert_eq!(
+        event.entry.unwrap().key.description,
+        "Whether or not to use the dark theme"
+    );
+
+    assert_eq!(try_next_event(&mut listener, &connection).await?, None);
+
+    let event = try_next_event(&mut other_listener, &connection)
+        .await?
+        .expect("expected an event for the other user's override");
+
+    assert_eq!(event.key_name, "system.enabled.code");
+
+    let event = try_next_event(&mut other_listener, &connection)
+        .await?
+        .expect("expected an event for the other user's override");
+
+    assert_eq!(event.user_id.as_deref(), Some("bob"));
+
+    // Listeners are closed once the watcher stops
+    task.cancel().await;
+
+    assert!(matches!(
+        listener.next_event(&connection).await,
+        Err(db::Error::ConfigurationChangesUnavailable)
+    ));
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_events_invalid_prefix() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let cache = ConfigurationCache::new(BTreeMap::new());
+
+    // Without a watcher there are no changes to listen for
+    assert!(matches!(
+        ConfigurationEventListener::subscribe(&cache, None, None),
+        Err(db::Error::ConfigurationChangesUnavailable)
+    ));
+
+    let _watcher = cache.watch(&connection).await?;
+
+    assert!(matches!(
+        ConfigurationEventListener::subscribe(&cache, None, Some("not a prefix")),
+        Err(db::Error::ConfigurationKeyNameInvalid(_))
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,367 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use chrono::Utc;
+use db::{
+    entities::configuration_group_members,
+    queries::configuration::{
+        cache::ConfigurationSnapshot,
+        find_configuration_key_by_name, get_all_configuration_entries, get_all_configuration_keys,
+        get_all_configuration_types, get_effective_configuration,
+        groups::{
+            add_configuration_group_member, get_all_configuration_groups,
+            get_configuration_group_ids, remove_configuration_group_member,
+            update_configuration_group,
+        },
+        history::get_all_configuration_entries_as_of,
+        replace_configuration_entry_items, replace_configuration_entry_items_in_scope,
+        ConfigurationEntryScope,
+    },
+    seeding::{insert_configuration_key_reference, insert_configuration_type_reference},
+    testing::initialize_unit_database,
+};
+use domain_api::configuration::{
+    ConfigurationEffectiveSource, ConfigurationGroupUpdateRequest, ConfigurationValueResponse,
+};
+use futures::future::join_all;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serial_test::serial;
+use std::{collections::BTreeMap, time::Duration};
+
+#[async_std::test]
+#[serial]
+async fn test_group_membership() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    assert!(get_all_configuration_groups(&connection).await?.is_empty());
+
+    add_configuration_group_member(&connection, "beta", "user").await?;
+    add_configuration_group_member(&connection, "alpha", "user").await?;
+
+    // Adding a member twice has no effect
+    let group = add_configuration_group_member(&connection, "beta", "user").await?;
+
+    assert_eq!(group.group_id, "beta");
+    assert_eq!(group.user_ids, vec!["user".to_owned()]);
+
+    assert_eq!(
+        get_configuration_group_ids(&connection, "user").await?,
+        vec!["alpha".to_owned(), "beta".to_owned()]
+    );
+    assert!(get_configuration_group_ids(&connection, "other")
+        .await?
+        .is_empty());
+
+    let group = remove_configuration_group_member(&connection, "beta", "user").await?;
+
+    assert!(group.user_ids.is_empty());
+    assert_eq!(
+        get_configuration_group_ids(&connection, "user").await?,
+        vec!["alpha".to_owned()]
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_group_membership_concurrently() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    // Concurrent adds of the same user insert a single membership
+    join_all((0..8).map(|_| add_configuration_group_member(&connection, "beta", "user")))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let active_members = || {
+        configuration_group_members::Entity::find()
+            .filter(configuration_group_members::Column::DeactivateTimestamp.is_null())
+            .count(&connection)
+    };
+
+    assert_eq!(active_members().await?, 1);
+
+    // A removed user can be added again
+    remove_configuration_group_member(&connection, "beta", "user").await?;
+
+    let group = add_configuration_group_member(&connection, "beta", "user").await?;
+
+    assert_eq!(group.user_ids, vec!["user".to_owned()]);
+    assert_eq!(active_members().await?, 1);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_group_precedence() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let type_set = get_all_configuration_types(&connection).await?;
+    let key_set = get_all_configuration_keys(&connection, &type_set).await?;
+    let key = find_configuration_key_by_name(&key_set, "system.enabled.code")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    replace_configuration_entry_items(&connection, key, None, &[boolean_value(false)]).await?;
+    replace_configuration_entry_items_in_scope(
+        &connection,
+        key,
+        &ConfigurationEntryScope::Group("beta".to_owned()),
+        &[boolean_value(true)],
+    )
+    .await?;
+
+    add_configuration_group_member(&connection, "beta", "member").await?;
+    add_configuration_group_member(&connection, "beta", "user").await?;
+
+    replace_configuration_entry_items(&connection, key, Some("user"), &[boolean_value(false)])
+        .await?;
+
+    // Group items override global items for members only
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("member"))
+            .await?;
+
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::Group);
+    assert_eq!(effective[0].value, Some(boolean_value(true)));
+
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("other")).await?;
+
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::Global);
+
+    // User items override group items
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("user")).await?;
+
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::User);
+    assert_eq!(effective[0].value, Some(boolean_value(false)));
+
+    // Snapshots resolve the same way
+    let snapshot = ConfigurationSnapshot::load(&connection, &BTreeMap::new()).await?;
+
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", Some("member"))?,
+        Some(true)
+    );
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", Some("user"))?,
+        Some(false)
+    );
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", Some("other"))?,
+        Some(false)
+    );
+    assert_eq!(
+        snapshot
+            .entry_in_scope(
+                "system.enabled.code",
+                &ConfigurationEntryScope::Group("beta".to_owned())
+            )?
+            .groups[0]
+            .group_id,
+        "beta"
+    );
+
+    // Former members fall back to the global items
+    remove_configuration_group_member(&connection, "beta", "member").await?;
+
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("member"))
+            .await?;
+
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::Global);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_group_priority() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "system.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let type_set = get_all_configuration_types(&connection).await?;
+    let key_set = get_all_configuration_keys(&connection, &type_set).await?;
+    let key = find_configuration_key_by_name(&key_set, "system.enabled.code")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    replace_configuration_entry_items_in_scope(
+        &connection,
+        key,
+        &ConfigurationEntryScope::Group("alpha".to_owned()),
+        &[boolean_value(false)],
+    )
+    .await?;
+    replace_configuration_entry_items_in_scope(
+        &connection,
+        key,
+        &ConfigurationEntryScope::Group("beta".to_owned()),
+        &[boolean_value(true)],
+    )
+    .await?;
+
+    add_configuration_group_member(&connection, "alpha", "user").await?;
+    add_configuration_group_member(&connection, "beta", "user").await?;
+
+    // Groups with the same priority are ordered by id
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("user")).await?;
+
+    assert_eq!(effective[0].value, Some(boolean_value(false)));
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let before_priority = Utc::now();
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    // A higher priority wins regardless of the id
+    let group = update_configuration_group(
+        &connection,
+        "beta",
+        &ConfigurationGroupUpdateRequest { priority: 10 },
+    )
+    .await?;
+
+    assert_eq!(group.priority, 10);
+    assert_eq!(group.user_ids, vec!["user".to_owned()]);
+
+    assert_eq!(
+        get_configuration_group_ids(&connection, "user").await?,
+        vec!["beta".to_owned(), "alpha".to_owned()]
+    );
+
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("user")).await?;
+
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::Group);
+    assert_eq!(effective[0].value, Some(boolean_value(true)));
+
+    let entries = get_all_configuration_entries(&connection, &key_set, Some("user"), None).await?;
+
+    assert_eq!(entries[0].groups[0].group_id, "beta");
+    assert_eq!(entries[0].groups[1].group_id, "alpha");
+
+    let snapshot = ConfigurationSnapshot::load(&connection, &BTreeMap::new()).await?;
+
+    assert_eq!(
+        snapshot.get_boolean("system.enabled.code", Some("user"))?,
+        Some(true)
+    );
+
+    // Setting the priority again replaces it
+    update_configuration_group(
+        &connection,
+        "alpha",
+        &ConfigurationGroupUpdateRequest { priority: 20 },
+    )
+    .await?;
+
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("user")).await?;
+
+    assert_eq!(effective[0].value, Some(boolean_value(false)));
+
+    let groups = get_all_configuration_groups(&connection).await?;
+
+    assert_eq!(groups[0].group_id, "alpha");
+    assert_eq!(groups[0].priority, 20);
+    assert_eq!(groups[1].priority, 10);
+
+    // Past configuration is ordered by the priorities at that time
+    let entries = get_all_configuration_entries_as_of(
+        &connection,
+        &key_set,
+        Some("user"),
+        None,
+        before_priority,
+    )
+    .await?;
+
+    assert_eq!(entries[0].groups[0].group_id, "alpha");
+    assert_eq!(entries[0].groups[1].group_id, "beta");
+
+    update_configuration_group(
+        &connection,
+        "alpha",
+        &ConfigurationGroupUpdateRequest { priority: 0 },
+    )
+    .await?;
+
+    let entries =
+        get_all_configuration_entries_as_of(&connection, &key_set, Some("user"), None, Utc::now())
+            .await?;
+
+    assert_eq!(entries[0].groups[0].group_id, "beta");
+
+    // Groups with a priority are listed even without members
+    update_configuration_group(
+        &connection,
+        "empty",
+        &ConfigurationGroupUpdateRequest { priority: 5 },
+    )
+    .await?;
+
+    let groups = get_all_configuration_groups(&connection).await?;
+
+    assert_eq!(groups[2].group_id, "empty");
+    assert!(groups[2].user_ids.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,511 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![allow(incomplete_features)]
+#![feature(async_fn_in_trait)]
+
+use chrono::{DateTime, Utc};
+use db::{
+    queries::configuration::{
+        deactivate_configuration_entry_items, find_configuration_key_by_name,
+        get_all_configuration_entries, get_all_configuration_keys, get_all_configuration_types,
+        history::{
+            get_all_configuration_entries_as_of, get_all_configuration_keys_as_of,
+            get_all_configuration_types_as_of, get_configuration_diff,
+            rollback_configuration_entries, ConfigurationAuditPoint,
+        },
+        replace_configuration_entry_items, update_configuration_key,
+    },
+    seeding::{insert_configuration_key_reference, insert_configuration_type_reference},
+    testing::initialize_unit_database,
+};
+use domain_api::configuration::{
+    ConfigurationChangeKind, ConfigurationEntrySetResponse, ConfigurationKeyUpdateRequest,
+    ConfigurationValueResponse,
+};
+use serial_test::serial;
+use std::time::Duration;
+
+/// Get the current time, making sure that it is strictly between the writes
+/// before and after it.
+async fn checkpoint() -> DateTime<Utc> {
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let now = Utc::now();
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    now
+}
+
+/// Get the boolean values of the global and user items of the first entry.
+fn boolean_values(entries: &ConfigurationEntrySetResponse) -> (Vec<bool>, Vec<bool>) {
+    let entry = &entries[0];
+
+    (
+        entry
+            .items_global
+            .iter()
+            .map(|item| item.value.as_boolean.unwrap())
+            .collect(),
+        entry.user.as_ref().map_or(Vec::new(), |user| {
+            user.items
+                .iter()
+                .map(|item| item.value.as_boolean.unwrap())
+                .collect()
+        }),
+    )
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_as_of() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let before_types = checkpoint().await;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let key = find_configuration_key_by_name(&keys, "theme.darkMode")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    replace_configuration_entry_items(&connection, key, None, &[boolean_value(false)]).await?;
+
+    let first = checkpoint().await;
+
+    replace_configuration_entry_items(&connection, key, None, &[boolean_value(true)]).await?;
+    replace_configuration_entry_items(&connection, key, Some("user"), &[boolean_value(false)])
+        .await?;
+
+    let second = checkpoint().await;
+
+    deactivate_configuration_entry_items(&connection, key, Some("user")).await?;
+
+    update_configuration_key(
+        &connection,
+        &types,
+        theme_dark_mode_id,
+        &ConfigurationKeyUpdateRequest {
+            description: "Whether or not to use the dark theme".to_owned(),
+            type_id: boolean_id,
+            optional: false,
+            allows_multiple: false,
+            allows_user_override: true,
+            constraints: Default::default(),
+            rollout: None,
+        },
+    )
+    .await?;
+
+    let third = checkpoint().await;
+
+    // Nothing existed before the type was created
+    assert!(get_all_configuration_types_as_of(&connection, before_types)
+        .await?
+        .is_empty());
+
+    // Types and keys are rebuilt as they were
+    let types_first = get_all_configuration_types_as_of(&connection, first).await?;
+
+    assert_eq!(types_first, types);
+
+    let keys_first = get_all_configuration_keys_as_of(&connection, &types_first, first).await?;
+
+    assert_eq!(keys_first.len(), 1);
+    assert_eq!(keys_first[0].description, "Whether or not to use dark mode");
+
+    let keys_third = get_all_configuration_keys_as_of(&connection, &types_first, third).await?;
+
+    assert_eq!(
+        keys_third[0].description,
+        "Whether or not to use the dark theme"
+    );
+
+    // Entries are rebuilt as they were
+    let entries =
+        get_all_configuration_entries_as_of(&connection, &keys_first, Some("user"), None, first)
+            .await?;
+
+    assert_eq!(boolean_values(&entries), (vec![false], vec![]));
+
+    let entries =
+        get_all_configuration_entries_as_of(&connection, &keys_first, Some("user"), None, second)
+            .await?;
+
+    assert_eq!(boolean_values(&entries), (vec![true], vec![false]));
+
+    let entries =
+        get_all_configuration_entries_as_of(&connection, &keys_first, None, None, second).await?;
+
+    assert_eq!(boolean_values(&entries), (vec![true], vec![]));
+
+    let entries =
+        get_all_configuration_entries_as_of(&connection, &keys_third, Some("user"), None, third)
+            .await?;
+
+    assert_eq!(boolean_values(&entries), (vec![true], vec![]));
+
+    // Prefixes are applied to the keys as they were
+    let entries =
+        get_all_configuration_entries_as_of(&connection, &keys_first, None, Some("theme"), second)
+            .await?;
+
+    assert_eq!(entries.len(), 1);
+
+    let entries = get_all_configuration_entries_as_of(
+        &connection,
+        &keys_first,
+        None,
+        Some("systems"),
+        second,
+    )
+    .await?;
+
+    assert!(entries.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_configuration_diff() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let key = find_configuration_key_by_name(&keys, "theme.darkMode")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    let entry =
+        replace_configuration_entry_items(&connection, key, None, &[boolean_value(false)]).await?;
+
+    let global_id_first = entry.items_global[0].id;
+
+    let from = checkpoint().await;
+
+    let entry =
+        replace_configuration_entry_items(&connection, key, None, &[boolean_value(true)]).await?;
+
+    let global_id_second = entry.items_global[0].id;
+
+    let entry =
+        replace_configuration_entry_items(&connection, key, Some("user"), &[boolean_value(false)])
+            .await?;
+
+    let user_id_first = entry.user.unwrap().items[0].id;
+
+    update_configuration_key(
+        &connection,
+        &types,
+        theme_dark_mode_id,
+        &ConfigurationKeyUpdateRequest {
+            description: "Whether or not to use the dark theme".to_owned(),
+            type_id: boolean_id,
+            optional: false,
+            allows_multiple: false,
+            allows_user_override: true,
+            constraints: Default::default(),
+            rollout: None,
+        },
+    )
+    .await?;
+
+    let systems_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    let to = checkpoint().await;
+
+    let diff = get_configuration_diff(&connection, from, to).await?;
+
+    // Keys
+    assert_eq!(diff.keys.len(), 2);
+
+    assert_eq!(diff.keys[0].kind, ConfigurationChangeKind::Changed);
+    assert_eq!(
+        diff.keys[0].old.as_ref().unwrap().description,
+        "Whether or not to use dark mode"
+    );
+    assert_eq!(
+        diff.keys[0].new.as_ref().unwrap().description,
+        "Whether or not to use the dark theme"
+    );
+
+    assert_eq!(diff.keys[1].kind, ConfigurationChangeKind::Added);
+    assert_eq!(diff.keys[1].old, None);
+    assert_eq!(
+        diff.keys[1].new.as_ref().unwrap().id,
+        systems_enabled_code_id
+    );
+
+    // Entry items
+    assert_eq!(diff.entries.len(), 3);
+
+    assert_eq!(diff.entries[0].id, global_id_first);
+    assert_eq!(diff.entries[0].kind, ConfigurationChangeKind::Deactivated);
+    assert_eq!(diff.entries[0].key_name, "theme.darkMode");
+    assert_eq!(diff.entries[0].user_id, None);
+    assert_eq!(diff.entries[0].old, Some(boolean_value(false)));
+    assert_eq!(diff.entries[0].new, None);
+
+    assert_eq!(diff.entries[1].id, global_id_second);
+    assert_eq!(diff.entries[1].kind, ConfigurationChangeKind::Added);
+    assert_eq!(diff.entries[1].old, None);
+    assert_eq!(diff.entries[1].new, Some(boolean_value(true)));
+
+    assert_eq!(diff.entries[2].id, user_id_first);
+    assert_eq!(diff.entries[2].kind, ConfigurationChangeKind::Added);
+    assert_eq!(diff.entries[2].user_id, Some("user".to_owned()));
+
+    // Changes are attributed to their audit records
+    let audit = diff.entries[0].audit.as_ref().unwrap();
+
+    assert!(audit.client_query.to_lowercase().contains("update"));
+    assert!(!audit.client_host.is_empty());
+
+    let audit = diff.entries[1].audit.as_ref().unwrap();
+
+    assert!(audit.client_query.to_lowercase().contains("insert"));
+
+    // Nothing changes within a single point in time
+    let diff = get_configuration_diff(&connection, to, to).await?;
+
+    assert!(diff.keys.is_empty());
+    assert!(diff.entries.is_empty());
+
+    assert!(matches!(
+        get_configuration_diff(&connection, to, from).await,
+        Err(db::Error::AuditRangeInvalid(_, _))
+    ));
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_rollback_configuration_entries() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether or not the Code system is enabled",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let theme_dark_mode = find_configuration_key_by_name(&keys, "theme.darkMode")?;
+    let systems_enabled_code = find_configuration_key_by_name(&keys, "systems.enabled.code")?;
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    let values = |prefix| {
+        let connection = &connection;
+        let keys = &keys;
+
+        async move {
+            get_all_configuration_entries(connection, keys, Some("user"), Some(prefix))
+                .await
+                .map(|entries| boolean_values(&entries))
+        }
+    };
+
+    replace_configuration_entry_items(&connection, theme_dark_mode, None, &[boolean_value(false)])
+        .await?;
+    replace_configuration_entry_items(
+        &connection,
+        systems_enabled_code,
+        None,
+        &[boolean_value(true)],
+    )
+    .await?;
+
+    let point = checkpoint().await;
+
+    replace_configuration_entry_items(&connection, theme_dark_mode, None, &[boolean_value(true)])
+        .await?;
+    replace_configuration_entry_items(
+        &connection,
+        theme_dark_mode,
+        Some("user"),
+        &[boolean_value(false)],
+    )
+    .await?;
+    replace_configuration_entry_items(
+        &connection,
+        systems_enabled_code,
+        None,
+        &[boolean_value(false)],
+    )
+    .await?;
+
+    // Only the entries under the prefix are rolled back
+    let rollback = rollback_configuration_entries(
+        &connection,
+        ConfigurationAuditPoint::Timestamp(point),
+        Some("theme"),
+    )
+    .await?;
+
+    assert_eq!(values("theme").await?, (vec![false], vec![]));
+    assert_eq!(values("systems").await?, (vec![false], vec![]));
+
+    assert_eq!(rollback.entries.len(), 3);
+
+    assert_eq!(
+        rollback.entries[0].kind,
+        ConfigurationChangeKind::Deactivated
+    );
+    assert_eq!(rollback.entries[0].user_id, None);
+    assert_eq!(rollback.entries[0].old, Some(boolean_value(true)));
+
+    assert_eq!(rollback.entries[1].kind, ConfigurationChangeKind::Added);
+    assert_eq!(rollback.entries[1].user_id, None);
+    assert_eq!(rollback.entries[1].new, Some(boolean_value(false)));
+
+    assert_eq!(
+        rollback.entries[2].kind,
+        ConfigurationChangeKind::Deactivated
+    );
+    assert_eq!(rollback.entries[2].user_id, Some("user".to_owned()));
+    assert_eq!(rollback.entries[2].old, Some(boolean_value(false)));
+
+    // The rollback is recorded as normal audited writes
+    let deactivate_audit_id = rollback.entries[0].audit.as_ref().unwrap().audit_id;
+    let insert_audit = rollback.entries[1].audit.as_ref().unwrap();
+
+    assert!(insert_audit.client_query.to_lowercase().contains("insert"));
+
+    // Restoring a state where a required entry had no items fails without
+    // writing anything
+    assert!(matches!(
+        rollback_configuration_entries(
+            &connection,
+            ConfigurationAuditPoint::AuditId(deactivate_audit_id),
+            None,
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyRequired(_))
+    ));
+
+    assert_eq!(values("theme").await?, (vec![false], vec![]));
+
+    // Audit ids include the change that they record
+    let rollback = rollback_configuration_entries(
+        &connection,
+        ConfigurationAuditPoint::AuditId(insert_audit.audit_id),
+        None,
+    )
+    .await?;
+
+    assert_eq!(values("theme").await?, (vec![false], vec![false]));
+    assert_eq!(rollback.entries.len(), 1);
+    assert_eq!(rollback.entries[0].kind, ConfigurationChangeKind::Added);
+
+    // Without a prefix, everything is rolled back
+    let rollback = rollback_configuration_entries(
+        &connection,
+        ConfigurationAuditPoint::Timestamp(point),
+        None,
+    )
+    .await?;
+
+    assert_eq!(values("theme").await?, (vec![false], vec![]));
+    assert_eq!(values("systems").await?, (vec![true], vec![]));
+    assert_eq!(rollback.entries.len(), 3);
+
+    // Rolling back to the current state changes nothing
+    let rollback = rollback_configuration_entries(
+        &connection,
+        ConfigurationAuditPoint::Timestamp(point),
+        None,
+    )
+    .await?;
+
+    assert!(rollback.entries.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![allow(incomplete_features)]
+#![feature(async_fn_in_trait)]
+
+use db::{
+    queries::migrations::{get_applied_migrations, get_migration_status, ping_database},
+    testing::initialize_unit_database,
+    Error,
+};
+use serial_test::serial;
+
+#[async_std::test]
+#[serial]
+async fn test_migration_status() -> Result<(), Error> {
+    let connection = initialize_unit_database().await?;
+
+    ping_database(&connection).await?;
+
+    let applied = get_applied_migrations(&connection).await?;
+
+    assert!(!applied.is_empty());
+
+    // Every migration has been applied
+    let status = get_migration_status(&connection, &applied).await?;
+
+    assert_eq!(status.applied, applied);
+    assert!(status.pending.is_empty());
+    assert!(status.up_to_date);
+
+    // A migration that has not been applied yet
+    let mut migrations = applied.clone();
+
+    migrations.push("m99991231_000000_not_applied".to_owned());
+
+    let status = get_migration_status(&connection, &migrations).await?;
+
+    assert_eq!(status.applied, applied);
+    assert_eq!(status.pending, vec!["m99991231_000000_not_applied"]);
+    assert!(!status.up_to_date);
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use db::{
+    queries::configuration::{
+        cache::ConfigurationSnapshot, create_configuration_key, evaluate_configuration_key_rollout,
+        get_all_configuration_keys, get_all_configuration_types, get_effective_configuration,
+        groups::add_configuration_group_member, replace_configuration_entry_items,
+        update_configuration_key,
+    },
+    seeding::insert_configuration_type_reference,
+    testing::initialize_unit_database,
+};
+use domain_api::configuration::{
+    ConfigurationEffectiveSource, ConfigurationKeyCreateRequest, ConfigurationKeyRolloutResponse,
+    ConfigurationKeyUpdateRequest, ConfigurationValueResponse,
+};
+use serial_test::serial;
+use std::collections::BTreeMap;
+
+#[test]
+fn test_evaluate_configuration_key_rollout() {
+    let rollout = ConfigurationKeyRolloutResponse {
+        percentage: 0.0,
+        allow_user_ids: vec!["allowed".to_owned(), "denied".to_owned()],
+        deny_user_ids: vec!["denied".to_owned()],
+        group_ids: vec!["beta".to_owned()],
+    };
+
+    let evaluate = |user_id, group_ids: &[String]| {
+        evaluate_configuration_key_rollout("system.enabled.deploy", &rollout, user_id, group_ids)
+    };
+
+    assert!(evaluate(Some("allowed"), &[]));
+    assert!(evaluate(Some("member"), &["beta".to_owned()]));
+    assert!(!evaluate(Some("denied"), &["beta".to_owned()]));
+    assert!(!evaluate(Some("other"), &["alpha".to_owned()]));
+    assert!(!evaluate(None, &[]));
+
+    // Percentages select a stable share of users that only grows
+    let user_ids = (0..1000)
+        .map(|i| format!("user-{i}"))
+        .collect::<Vec<String>>();
+
+    let selected = |percentage| {
+        user_ids
+            .iter()
+            .filter(|user_id| {
+                evaluate_configuration_key_rollout(
+                    "system.enabled.deploy",
+                    &ConfigurationKeyRolloutResponse {
+                        percentage,
+                        ..Default::default()
+                    },
+                    Some(user_id),
+                    &[],
+                )
+            })
+            .cloned()
+            .collect::<Vec<String>>()
+    };
+
+    assert!(selected(0.0).is_empty());
+    assert_eq!(selected(100.0).len(), user_ids.len());
+
+    let quarter = selected(25.0);
+    let half = selected(50.0);
+
+    assert!((150..350).contains(&quarter.len()));
+    assert!((400..600).contains(&half.len()));
+    assert!(quarter.iter().all(|user_id| half.contains(user_id)));
+    assert_eq!(selected(50.0), half);
+}
+
+#[async_std::test]
+#[serial]
+async fn test_rollout_rules() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+    insert_configuration_type_reference(&connection, "integer", "A whole number").await?;
+
+    let type_set = get_all_configuration_types(&connection).await?;
+
+    let rollout = ConfigurationKeyRolloutResponse {
+        percentage: 0.0,
+        allow_user_ids: vec!["allowed".to_owned()],
+        deny_user_ids: Vec::new(),
+        group_ids: vec!["beta".to_owned()],
+    };
+
+    let create_request = |name: &str, type_id, rollout| ConfigurationKeyCreateRequest {
+        name: name.to_owned(),
+        description: "A key with a rollout rule".to_owned(),
+        type_id,
+        optional: true,
+        allows_multiple: false,
+        allows_user_override: true,
+        constraints: Default::default(),
+        rollout,
+    };
+
+    // Rules must fit the type of the key
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &type_set,
+            &create_request("limits.invalid", type_set[1].id, Some(rollout.clone())),
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyRolloutInvalid(_, _))
+    ));
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &type_set,
+            &create_request(
+                "system.enabled.invalid",
+                type_set[0].id,
+                Some(ConfigurationKeyRolloutResponse {
+                    percentage: 150.0,
+                    ..Default::default()
+                }),
+            ),
+        )
+        .await,
+        Err(db::Error::ValidatorValidationErrors(_))
+    ));
+
+    let key = create_configuration_key(
+        &connection,
+        &type_set,
+        &create_request(
+            "system.enabled.deploy",
+            type_set[0].id,
+            Some(rollout.clone()),
+        ),
+    )
+    .await?;
+
+    assert_eq!(key.rollout, Some(rollout));
+
+    let key_set = get_all_configuration_keys(&connection, &type_set).await?;
+
+    assert_eq!(key_set, vec![key.clone()]);
+
+    let boolean_value = |x| ConfigurationValueResponse {
+        as_boolean: Some(x),
+        ..Default::default()
+    };
+
+    replace_configuration_entry_items(&connection, &key, None, &[boolean_value(true)]).await?;
+    replace_configuration_entry_items(&connection, &key, Some("override"), &[boolean_value(true)])
+        .await?;
+    add_configuration_group_member(&connection, "beta", "member").await?;
+
+    let snapshot = ConfigurationSnapshot::load(&connection, &BTreeMap::new()).await?;
+
+    for (user_id, source, value) in [
+        (Some("allowed"), ConfigurationEffectiveSource::Rollout, true),
+        (Some("member"), ConfigurationEffectiveSource::Rollout, true),
+        (Some("other"), ConfigurationEffectiveSource::Rollout, false),
+        (None, ConfigurationEffectiveSource::Rollout, false),
+        // User overrides are not subject to the rule
+        (Some("override"), ConfigurationEffectiveSource::User, true),
+    ] {
+        let effective =
+            get_effective_configuration(&connection, &key_set, &BTreeMap::new(), user_id).await?;
+
+        assert_eq!(effective[0].source, source);
+        assert_eq!(effective[0].value, Some(boolean_value(value)));
+        assert_eq!(snapshot.effective(user_id), effective);
+        assert_eq!(
+            snapshot.get_boolean("system.enabled.deploy", user_id)?,
+            Some(value)
+        );
+    }
+
+    // A false value turns the key off for everyone
+    replace_configuration_entry_items(&connection, &key, None, &[boolean_value(false)]).await?;
+
+    let effective =
+        get_effective_configuration(&connection, &key_set, &BTreeMap::new(), Some("allowed"))
+            .await?;
+
+    assert_eq!(effective[0].source, ConfigurationEffectiveSource::Global);
+    assert_eq!(effective[0].value, Some(boolean_value(false)));
+
+    // Removing the rule turns the key back into a plain boolean
+    let key = update_configuration_key(
+        &connection,
+        &type_set,
+        key.id,
+        &ConfigurationKeyUpdateRequest {
+            description: key.description.clone(),
+            type_id: key.configuration_type.id,
+            optional: key.optional,
+            allows_multiple: key.allows_multiple,
+            allows_user_override: key.allows_user_override,
+            constraints: key.constraints.clone(),
+            rollout: None,
+        },
+    )
+    .await?;
+
+    assert_eq!(key.rollout, None);
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![allow(incomplete_features)]
+#![feature(async_fn_in_trait)]
+
+use db::{
+    entities::{configuration_entries, configuration_entries_audit},
+    queries::configuration::{
+        create_configuration_key, find_configuration_key_by_name, get_all_configuration_entries,
+        get_all_configuration_entries_with_secrets, get_all_configuration_keys,
+        get_all_configuration_types, insert_configuration_entry_item, update_configuration_key,
+    },
+    seeding::{
+        insert_configuration_entry, insert_configuration_key_reference,
+        insert_configuration_type_reference,
+    },
+    testing::initialize_unit_database,
+};
+use domain_api::configuration::{
+    ConfigurationKeyConstraintsResponse, ConfigurationKeyCreateRequest,
+    ConfigurationKeyUpdateRequest, ConfigurationValueResponse,
+};
+use sea_orm::EntityTrait;
+use serial_test::serial;
+
+const CONFIGURATION_SECRET_KEY: &str = "6Wd4VsJ9ZfXMDg0Xx7mPv7cE4lTq8oRSa8R2ZjHfLkI=";
+
+#[async_std::test]
+#[serial]
+async fn test_secret_configuration_values() -> Result<(), db::Error> {
+    std::env::set_var("CONFIGURATION_SECRET_KEY", CONFIGURATION_SECRET_KEY);
+
+    let connection = initialize_unit_database().await?;
+
+    let secret_id =
+        insert_configuration_type_reference(&connection, "secret", "An encrypted string").await?;
+
+    let string_id =
+        insert_configuration_type_reference(&connection, "string", "A string value").await?;
+
+    let token_id = insert_configuration_key_reference(
+        &connection,
+        "integration.github.token",
+        "The GitHub API token",
+        secret_id,
+        true,
+        true,
+        false,
+    )
+    .await?;
+
+    let types = get_all_configuration_types(&connection).await?;
+    let keys = get_all_configuration_keys(&connection, &types).await?;
+    let key = find_configuration_key_by_name(&keys, "integration.github.token")?;
+
+    // Both write paths encrypt the value
+    insert_configuration_entry(&connection, token_id, 1, None, "seeded-token").await?;
+
+    let entry = insert_configuration_entry_item(
+        &connection,
+        key,
+        None,
+        &ConfigurationValueResponse {
+            as_secret: Some("written-token".to_owned()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // Written values are redacted in the response
+    assert!(entry.items_global.iter().all(|item| item.value.redacted));
+    assert!(entry
+        .items_global
+        .iter()
+        .all(|item| item.value.as_secret.is_none()));
+
+    // Neither the source nor the audit table contain the plaintext
+    for value in configuration_entries::Entity::find()
+        .all(&connection)
+        .await?
+        .into_iter()
+        .map(|row| row.value)
+        .chain(
+            configuration_entries_audit::Entity::find()
+                .all(&connection)
+                .await?
+                .into_iter()
+                .filter_map(|row| row.value),
+        )
+    {
+        assert!(!value.contains("seeded-token"));
+        assert!(!value.contains("written-token"));
+    }
+
+    // Values are redacted unless they are explicitly requested
+    let entries = get_all_configuration_entries(&connection, &keys, None, None).await?;
+
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0]
+        .items_global
+        .iter()
+        .all(|item| item.value.redacted && item.value.as_secret.is_none()));
+
+    let entries =
+        get_all_configuration_entries_with_secrets(&connection, &keys, None, None).await?;
+
+    assert_eq!(
+        entries[0]
+            .items_global
+            .iter()
+            .map(|item| item.value.as_secret.as_deref())
+            .collect::<Vec<Option<&str>>>(),
+        vec![Some("seeded-token"), Some("written-token")]
+    );
+
+    // Redacted values cannot be written back
+    assert!(matches!(
+        insert_configuration_entry_item(
+            &connection,
+            key,
+            None,
+            &ConfigurationValueResponse {
+                redacted: true,
+                ..Default::default()
+            },
+        )
+        .await,
+        Err(db::Error::ConfigurationValueTypeMismatch(_))
+    ));
+
+    // Secret keys cannot have allowed values, because they are stored in
+    // plaintext
+    assert!(matches!(
+        create_configuration_key(
+            &connection,
+            &types,
+            &ConfigurationKeyCreateRequest {
+                name: "integration.slack.token".to_owned(),
+                description: "The Slack API token".to_owned(),
+                type_id: secret_id,
+                optional: true,
+                allows_multiple: false,
+                allows_user_override: false,
+                constraints: ConfigurationKeyConstraintsResponse {
+                    allowed_values: Some(vec![ConfigurationValueResponse {
+                        as_secret: Some("token".to_owned()),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                },
+                rollout: None,
+            },
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyConstraintsInvalid(_, _))
+    ));
+
+    // Encrypted values cannot be turned into plaintext by changing the type
+    assert!(matches!(
+        update_configuration_key(
+            &connection,
+            &types,
+            token_id,
+            &ConfigurationKeyUpdateRequest {
+                description: "The GitHub API token".to_owned(),
+                type_id: string_id,
+                optional: true,
+                allows_multiple: true,
+                allows_user_override: false,
+                constraints: Default::default(),
+                rollout: None,
+            },
+        )
+        .await,
+        Err(db::Error::ConfigurationKeyTypeChangeIncompatible(_))
+    ));
+
+    Ok(())
+}
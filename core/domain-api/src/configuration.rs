@@ -23,6 +23,7 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use validator::Validate;
 
 lazy_static! {
@@ -43,6 +44,30 @@ pub struct ConfigurationTypeResponse {
 
 pub type ConfigurationTypeSetResponse = Vec<ConfigurationTypeResponse>;
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone, Default)]
+pub struct ConfigurationKeyConstraintsResponse {
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    #[validate(length(min = 1))]
+    pub pattern: Option<String>,
+    #[validate(length(min = 1))]
+    #[serde(rename = "allowedValues")]
+    pub allowed_values: Option<Vec<ConfigurationValueResponse>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone, Default)]
+pub struct ConfigurationKeyRolloutResponse {
+    #[validate(range(min = 0.0, max = 100.0))]
+    #[serde(default)]
+    pub percentage: f64,
+    #[serde(default, rename = "allowUserIds")]
+    pub allow_user_ids: Vec<String>,
+    #[serde(default, rename = "denyUserIds")]
+    pub deny_user_ids: Vec<String>,
+    #[serde(default, rename = "groupIds")]
+    pub group_ids: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
 pub struct ConfigurationKeyResponse {
     #[validate(range(min = 1))]
@@ -59,6 +84,10 @@ pub struct ConfigurationKeyResponse {
     pub allows_multiple: bool,
     #[serde(rename = "allowsUserOverride")]
     pub allows_user_override: bool,
+    #[validate]
+    pub constraints: ConfigurationKeyConstraintsResponse,
+    #[validate]
+    pub rollout: Option<ConfigurationKeyRolloutResponse>,
 }
 
 pub type ConfigurationKeySetResponse = Vec<ConfigurationKeyResponse>;
@@ -73,8 +102,24 @@ pub struct ConfigurationValueResponse {
     pub as_float: Option<f64>,
     #[serde(rename = "asString")]
     pub as_string: Option<String>,
+    #[serde(rename = "asDuration")]
+    pub as_duration: Option<String>,
+    #[serde(rename = "asUrl")]
+    pub as_url: Option<String>,
+    #[serde(rename = "asDatetime")]
+    pub as_datetime: Option<String>,
+    #[serde(rename = "asJson")]
+    pub as_json: Option<serde_json::Value>,
+    #[serde(rename = "asEnum")]
+    pub as_enum: Option<String>,
+    #[serde(rename = "asSecret")]
+    pub as_secret: Option<String>,
+    #[serde(default)]
+    pub redacted: bool,
 }
 
+pub type ConfigurationValueRequest = ConfigurationValueResponse;
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
 pub struct ConfigurationEntryItemResponse {
     #[validate(range(min = 1))]
@@ -91,13 +136,237 @@ pub struct ConfigurationEntryUserResponse {
     pub items: Vec<ConfigurationEntryItemResponse>,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationEntryGroupResponse {
+    #[validate(length(min = 1))]
+    #[serde(rename = "groupId")]
+    pub group_id: String,
+    #[validate(length(min = 1))]
+    pub items: Vec<ConfigurationEntryItemResponse>,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
 pub struct ConfigurationEntryResponse {
     pub key: ConfigurationKeyResponse,
     #[validate(length(min = 1))]
     #[serde(rename = "itemsGlobal")]
     pub items_global: Vec<ConfigurationEntryItemResponse>,
+    #[validate]
+    pub groups: Vec<ConfigurationEntryGroupResponse>,
     pub user: Option<ConfigurationEntryUserResponse>,
 }
 
 pub type ConfigurationEntrySetResponse = Vec<ConfigurationEntryResponse>;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone, Default)]
+pub struct ConfigurationEntryTreeResponse {
+    #[validate]
+    pub entry: Option<ConfigurationEntryResponse>,
+    pub children: BTreeMap<String, ConfigurationEntryTreeResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigurationEffectiveSource {
+    Global,
+    Group,
+    User,
+    Rollout,
+    Environment,
+    Unset,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationEffectiveEntryResponse {
+    pub key: ConfigurationKeyResponse,
+    pub source: ConfigurationEffectiveSource,
+    pub value: Option<ConfigurationValueResponse>,
+    pub values: Vec<ConfigurationValueResponse>,
+}
+
+pub type ConfigurationEffectiveSetResponse = Vec<ConfigurationEffectiveEntryResponse>;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigurationChangeKind {
+    Added,
+    Changed,
+    Deactivated,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Validate, Clone)]
+pub struct ConfigurationChangeAuditResponse {
+    #[validate(range(min = 1))]
+    #[serde(rename = "auditId")]
+    pub audit_id: i32,
+    pub timestamp: String,
+    #[serde(rename = "clientHost")]
+    pub client_host: String,
+    #[serde(rename = "clientQuery")]
+    pub client_query: String,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationKeyChangeResponse {
+    pub kind: ConfigurationChangeKind,
+    #[validate]
+    pub old: Option<ConfigurationKeyResponse>,
+    #[validate]
+    pub new: Option<ConfigurationKeyResponse>,
+    #[validate]
+    pub audit: Option<ConfigurationChangeAuditResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationEntryItemChangeResponse {
+    pub kind: ConfigurationChangeKind,
+    #[validate(range(min = 1))]
+    pub id: i32,
+    #[validate(length(min = 1))]
+    #[validate(regex = "CONFIGURATION_KEY_NAME_REGEX")]
+    #[serde(rename = "keyName")]
+    pub key_name: String,
+    #[serde(rename = "userId")]
+    pub user_id: Option<String>,
+    #[serde(rename = "groupId")]
+    pub group_id: Option<String>,
+    pub old: Option<ConfigurationValueResponse>,
+    pub new: Option<ConfigurationValueResponse>,
+    #[validate]
+    pub audit: Option<ConfigurationChangeAuditResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationDiffResponse {
+    pub from: String,
+    pub to: String,
+    #[validate]
+    pub keys: Vec<ConfigurationKeyChangeResponse>,
+    #[validate]
+    pub entries: Vec<ConfigurationEntryItemChangeResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigurationEventKind {
+    Key,
+    Entry,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationEventResponse {
+    pub kind: ConfigurationEventKind,
+    #[validate(length(min = 1))]
+    #[validate(regex = "CONFIGURATION_KEY_NAME_REGEX")]
+    #[serde(rename = "keyName")]
+    pub key_name: String,
+    #[validate(length(min = 1))]
+    #[serde(rename = "userId")]
+    pub user_id: Option<String>,
+    #[validate(length(min = 1))]
+    #[serde(rename = "groupId")]
+    pub group_id: Option<String>,
+    #[validate]
+    pub entry: Option<ConfigurationEntryResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationGroupResponse {
+    #[validate(length(min = 1))]
+    #[serde(rename = "groupId")]
+    pub group_id: String,
+    pub priority: i32,
+    #[serde(rename = "userIds")]
+    pub user_ids: Vec<String>,
+}
+
+pub type ConfigurationGroupSetResponse = Vec<ConfigurationGroupResponse>;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationRollbackResponse {
+    #[validate]
+    pub entries: Vec<ConfigurationEntryItemChangeResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationEntryRequest {
+    #[validate(length(min = 1))]
+    pub items: Vec<ConfigurationValueRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationRollbackRequest {
+    #[validate(range(min = 1))]
+    #[serde(rename = "auditId")]
+    pub audit_id: Option<i32>,
+    #[validate(length(min = 1))]
+    pub timestamp: Option<String>,
+    #[validate(length(min = 1))]
+    #[validate(regex = "CONFIGURATION_KEY_NAME_REGEX")]
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationGroupUpdateRequest {
+    pub priority: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationTypeCreateRequest {
+    #[validate(length(min = 1))]
+    #[validate(regex = "CONFIGURATION_TYPE_NAME_REGEX")]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationTypeUpdateRequest {
+    #[validate(length(min = 1))]
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationKeyCreateRequest {
+    #[validate(length(min = 1))]
+    #[validate(regex = "CONFIGURATION_KEY_NAME_REGEX")]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub description: String,
+    #[validate(range(min = 1))]
+    #[serde(rename = "typeId")]
+    pub type_id: i32,
+    pub optional: bool,
+    #[serde(rename = "allowsMultiple")]
+    pub allows_multiple: bool,
+    #[serde(rename = "allowsUserOverride")]
+    pub allows_user_override: bool,
+    #[serde(default)]
+    #[validate]
+    pub constraints: ConfigurationKeyConstraintsResponse,
+    #[serde(default)]
+    #[validate]
+    pub rollout: Option<ConfigurationKeyRolloutResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Validate, Clone)]
+pub struct ConfigurationKeyUpdateRequest {
+    #[validate(length(min = 1))]
+    pub description: String,
+    #[validate(range(min = 1))]
+    #[serde(rename = "typeId")]
+    pub type_id: i32,
+    pub optional: bool,
+    #[serde(rename = "allowsMultiple")]
+    pub allows_multiple: bool,
+    #[serde(rename = "allowsUserOverride")]
+    pub allows_user_override: bool,
+    #[serde(default)]
+    #[validate]
+    pub constraints: ConfigurationKeyConstraintsResponse,
+    #[serde(default)]
+    #[validate]
+    pub rollout: Option<ConfigurationKeyRolloutResponse>,
+}
@@ -0,0 +1,52 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Validate, Clone)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Validate, Clone)]
+pub struct HealthMigrationsResponse {
+    pub applied: Vec<String>,
+    pub pending: Vec<String>,
+    #[serde(rename = "upToDate")]
+    pub up_to_date: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Validate, Clone)]
+pub struct HealthReadinessResponse {
+    pub status: HealthStatus,
+    pub database: HealthStatus,
+    #[validate]
+    pub migrations: Option<HealthMigrationsResponse>,
+}
@@ -28,6 +28,7 @@
 #![allow(missing_docs)]
 
 pub mod configuration;
+pub mod health;
 
 use serde::{Deserialize, Serialize};
 use validator::Validate;
@@ -36,4 +37,15 @@ use validator::Validate;
 pub struct ErrorWithMessageResponse {
     #[validate(length(min = 1))]
     pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate]
+    pub errors: Vec<FieldErrorResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Validate, Clone)]
+pub struct FieldErrorResponse {
+    #[validate(length(min = 1))]
+    pub field: String,
+    #[validate(length(min = 1))]
+    pub rule: String,
 }
@@ -0,0 +1,87 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Keeping the configuration cache up to date.
+
+use db::queries::configuration::cache::ConfigurationCache;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Method,
+    Orbit, Request, Response, Rocket,
+};
+use sea_orm::DatabaseConnection;
+use tracing::warn;
+
+/// Keeps the managed [`ConfigurationCache`] up to date.
+///
+/// Once the server has launched, a background task invalidates the cache
+/// whenever the configuration tables change. Responses to requests other than
+/// `GET` invalidate it as well, so that writes can be read back right away.
+pub struct ConfigurationCacheFairing;
+
+#[rocket::async_trait]
+impl Fairing for ConfigurationCacheFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Configuration cache",
+            kind: Kind::Liftoff | Kind::Response,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (Some(connection), Some(cache)) = (
+            rocket.state::<DatabaseConnection>(),
+            rocket.state::<ConfigurationCache>(),
+        ) else {
+            return;
+        };
+
+        let watcher = match cache.watch(connection).await {
+            Ok(watcher) => watcher,
+            Err(error) => {
+                warn!(error = %error, "configuration cache is disabled");
+                return;
+            }
+        };
+
+        let shutdown = rocket.shutdown();
+
+        rocket::tokio::spawn(async move {
+            rocket::tokio::select! {
+                result = watcher.run() => {
+                    if let Err(error) = result {
+                        warn!(error = %error, "configuration cache is disabled");
+                    }
+                }
+                _ = shutdown => {}
+            }
+        });
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, _response: &mut Response<'r>) {
+        if request.method() != Method::Get {
+            if let Some(cache) = request.rocket().state::<ConfigurationCache>() {
+                cache.invalidate();
+            }
+        }
+    }
+}
@@ -20,31 +20,376 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+pub mod group;
+pub mod groups;
 pub mod keys;
 pub mod types;
+pub mod user;
 
+use crate::{
+    error::ErrorResponse,
+    identity::{Admin, UserId},
+    request_id::RequestId,
+    validated::Validated,
+};
+use chrono::{DateTime, Utc};
 use db::queries::configuration::{
-    get_all_configuration_entries, get_all_configuration_keys, get_all_configuration_types,
+    build_configuration_entry_tree,
+    cache::{ConfigurationCache, ConfigurationSnapshot},
+    deactivate_configuration_entry_items,
+    events::ConfigurationEventListener,
+    get_all_configuration_entries_with_secrets, get_configuration_key_by_name,
+    history::{
+        get_all_configuration_entries_as_of, get_all_configuration_entries_with_secrets_as_of,
+        get_all_configuration_keys_as_of, get_all_configuration_types_as_of,
+        get_configuration_diff, rollback_configuration_entries, ConfigurationAuditPoint,
+    },
+    insert_configuration_entry_item, replace_configuration_entry_items,
+};
+use domain_api::{
+    configuration::{
+        ConfigurationDiffResponse, ConfigurationEffectiveSetResponse, ConfigurationEntryRequest,
+        ConfigurationEntryResponse, ConfigurationEntrySetResponse, ConfigurationEntryTreeResponse,
+        ConfigurationKeyResponse, ConfigurationRollbackRequest, ConfigurationRollbackResponse,
+        ConfigurationValueRequest,
+    },
+    ErrorWithMessageResponse,
+};
+use rocket::{
+    http::Status,
+    response::stream::{Event, EventStream},
+    serde::json::Json,
+    Shutdown, State,
 };
-use domain_api::configuration::ConfigurationEntrySetResponse;
-use rocket::{serde::json::Json, State};
 use sea_orm::DatabaseConnection;
+use std::sync::Arc;
+use tracing::error;
+
+/// Query parameters for reading configuration entries
+#[derive(Debug, FromForm)]
+pub struct ConfigurationQuery {
+    /// Whether to return secret values in plaintext instead of redacting them.
+    /// Only administrators may do this until users can be authenticated.
+    #[field(name = "revealSecrets")]
+    pub reveal_secrets: bool,
+    /// Only return entries for keys whose names are this dotted prefix or
+    /// start with it followed by a dot.
+    pub prefix: Option<String>,
+    /// Whether to nest the entries by the dotted segments of their key names
+    /// instead of returning a flat list.
+    pub tree: bool,
+    /// Return the entries as they were at this RFC 3339 timestamp instead of
+    /// the current ones.
+    #[field(name = "asOf")]
+    pub as_of: Option<String>,
+}
 
-#[get("/")]
-pub async fn index(db: &State<DatabaseConnection>) -> Json<ConfigurationEntrySetResponse> {
+/// The configuration entries in the shape requested by [`ConfigurationQuery`]
+#[derive(Debug, Responder)]
+pub enum ConfigurationEntriesResponse {
+    /// A flat list of configuration entries
+    Set(Json<ConfigurationEntrySetResponse>),
+    /// Configuration entries nested by key name
+    Tree(Json<ConfigurationEntryTreeResponse>),
+}
+
+#[get("/?<query..>")]
+pub async fn index(
+    db: &State<DatabaseConnection>,
+    cache: &State<ConfigurationCache>,
+    user_id: Option<UserId>,
+    admin: Option<Admin>,
+    query: ConfigurationQuery,
+) -> Result<ConfigurationEntriesResponse, ErrorResponse> {
     let connection = db as &DatabaseConnection;
 
-    let configuration_types = get_all_configuration_types(connection)
+    let user_id = user_id.as_ref().map(|x| x.0.as_str());
+    let prefix = query.prefix.as_deref();
+
+    let as_of = query
+        .as_of
+        .as_deref()
+        .map(|as_of| parse_timestamp("query parameter", "asOf", as_of))
+        .transpose()?;
+
+    if query.reveal_secrets {
+        check_secret_reader(admin)?;
+    }
+
+    let configuration_entries = if let Some(as_of) = as_of {
+        let configuration_types = get_all_configuration_types_as_of(connection, as_of).await?;
+
+        let configuration_keys =
+            get_all_configuration_keys_as_of(connection, &configuration_types, as_of).await?;
+
+        if query.reveal_secrets {
+            get_all_configuration_entries_with_secrets_as_of(
+                connection,
+                &configuration_keys,
+                user_id,
+                prefix,
+                as_of,
+            )
+            .await
+        } else {
+            get_all_configuration_entries_as_of(
+                connection,
+                &configuration_keys,
+                user_id,
+                prefix,
+                as_of,
+            )
+            .await
+        }
+    } else {
+        let snapshot = load_configuration_snapshot(connection, cache).await?;
+
+        // Only redacted values are cached
+        if query.reveal_secrets {
+            get_all_configuration_entries_with_secrets(connection, snapshot.keys(), user_id, prefix)
+                .await
+        } else {
+            snapshot.entries(user_id, prefix)
+        }
+    }?;
+
+    Ok(if query.tree {
+        ConfigurationEntriesResponse::Tree(Json(build_configuration_entry_tree(
+            configuration_entries,
+        )))
+    } else {
+        ConfigurationEntriesResponse::Set(Json(configuration_entries))
+    })
+}
+
+#[get("/effective")]
+pub async fn effective(
+    db: &State<DatabaseConnection>,
+    cache: &State<ConfigurationCache>,
+    user_id: Option<UserId>,
+) -> Result<Json<ConfigurationEffectiveSetResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let snapshot = load_configuration_snapshot(connection, cache).await?;
+
+    Ok(Json(
+        snapshot.effective(user_id.as_ref().map(|x| x.0.as_str())),
+    ))
+}
+
+#[get("/diff?<from>&<to>")]
+pub async fn diff(
+    db: &State<DatabaseConnection>,
+    from: &str,
+    to: Option<&str>,
+) -> Result<Json<ConfigurationDiffResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let from = parse_timestamp("query parameter", "from", from)?;
+
+    let to = to
+        .map(|to| parse_timestamp("query parameter", "to", to))
+        .transpose()?
+        .unwrap_or_else(Utc::now);
+
+    get_configuration_diff(connection, from, to)
         .await
-        .expect("failed to get configuration types from database");
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
 
-    let configuration_keys = get_all_configuration_keys(connection, &configuration_types)
+#[post("/rollback", data = "<request>")]
+pub async fn rollback(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    request: Validated<Json<ConfigurationRollbackRequest>>,
+) -> Result<Json<ConfigurationRollbackResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let point = match (request.audit_id, request.timestamp.as_deref()) {
+        (Some(audit_id), None) => ConfigurationAuditPoint::AuditId(audit_id),
+        (None, Some(timestamp)) => {
+            ConfigurationAuditPoint::Timestamp(parse_timestamp("field", "timestamp", timestamp)?)
+        }
+        _ => {
+            return Err(ErrorResponse::new(
+                Status::UnprocessableEntity,
+                "exactly one of \"auditId\" and \"timestamp\" must be given",
+            ))
+        }
+    };
+
+    rollback_configuration_entries(connection, point, request.prefix.as_deref())
         .await
-        .expect("failed to get configuration keys from database");
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
 
-    Json(
-        get_all_configuration_entries(connection, &configuration_keys, None)
-            .await
-            .expect("failed to get configuration entries from database"),
-    )
+#[get("/events?<prefix>")]
+pub async fn events<'r>(
+    db: &'r State<DatabaseConnection>,
+    cache: &State<ConfigurationCache>,
+    request_id: &RequestId,
+    user_id: Option<UserId>,
+    prefix: Option<String>,
+    mut shutdown: Shutdown,
+) -> Result<EventStream![Event + 'r], ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let mut listener = ConfigurationEventListener::subscribe(
+        cache,
+        user_id.as_ref().map(|x| x.0.as_str()),
+        prefix.as_deref(),
+    )?;
+
+    // The stream outlives the request's span, so the id is logged explicitly
+    let request_id = request_id.to_string();
+
+    Ok(EventStream! {
+        loop {
+            let event = rocket::tokio::select! {
+                event = listener.next_event(connection) => event,
+                _ = &mut shutdown => break,
+            };
+
+            match event {
+                Ok(event) => yield Event::json(&event).event("change"),
+                Err(error) => {
+                    error!(
+                        request_id = %request_id,
+                        error = %error,
+                        "configuration event stream failed"
+                    );
+
+                    yield Event::json(&ErrorWithMessageResponse {
+                        message: error.to_string(),
+                        errors: Vec::new(),
+                    })
+                    .event("error");
+
+                    break;
+                }
+            }
+        }
+    })
+}
+
+#[get("/<name>")]
+pub async fn show(
+    db: &State<DatabaseConnection>,
+    cache: &State<ConfigurationCache>,
+    user_id: Option<UserId>,
+    name: &str,
+) -> Result<Json<ConfigurationEntryResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let snapshot = load_configuration_snapshot(connection, cache).await?;
+
+    snapshot
+        .entry(name, user_id.as_ref().map(|x| x.0.as_str()))
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[post("/<name>", data = "<value>")]
+pub async fn create(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    name: &str,
+    value: Validated<Json<ConfigurationValueRequest>>,
+) -> Result<Json<ConfigurationEntryResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let key = load_configuration_key(connection, name).await?;
+
+    insert_configuration_entry_item(connection, &key, None, &value)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[put("/<name>", data = "<entry>")]
+pub async fn update(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    name: &str,
+    entry: Validated<Json<ConfigurationEntryRequest>>,
+) -> Result<Json<ConfigurationEntryResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let key = load_configuration_key(connection, name).await?;
+
+    replace_configuration_entry_items(connection, &key, None, &entry.items)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[delete("/<name>")]
+pub async fn delete(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    name: &str,
+) -> Result<Json<ConfigurationEntryResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let key = load_configuration_key(connection, name).await?;
+
+    deactivate_configuration_entry_items(connection, &key, None)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+/// Load a single active configuration key by name.
+pub(crate) async fn load_configuration_key(
+    connection: &DatabaseConnection,
+    name: &str,
+) -> Result<ConfigurationKeyResponse, ErrorResponse> {
+    get_configuration_key_by_name(connection, name)
+        .await
+        .map_err(ErrorResponse::from)
+}
+
+/// Load a snapshot of the active configuration through the cache.
+pub(crate) async fn load_configuration_snapshot(
+    connection: &DatabaseConnection,
+    cache: &ConfigurationCache,
+) -> Result<Arc<ConfigurationSnapshot>, ErrorResponse> {
+    cache
+        .snapshot(connection)
+        .await
+        .map_err(ErrorResponse::from)
+}
+
+/// Make sure that the request is allowed to see secret configuration values.
+///
+/// The user id header is not authenticated, so only administrators may see
+/// them.
+// TODO: https://github.com/sophie-katz/prelude/issues/11
+pub(crate) fn check_secret_reader(admin: Option<Admin>) -> Result<(), ErrorResponse> {
+    match admin {
+        Some(Admin) => Ok(()),
+        None => Err(ErrorResponse::new(
+            Status::Forbidden,
+            "an admin token is required to see secret configuration values",
+        )),
+    }
+}
+
+/// Parse an RFC 3339 timestamp from a named part of the request, such as a
+/// query parameter or a field of the body.
+pub(crate) fn parse_timestamp(
+    kind: &str,
+    name: &str,
+    text: &str,
+) -> Result<DateTime<Utc>, ErrorResponse> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|error| {
+            ErrorResponse::new(
+                Status::UnprocessableEntity,
+                format!("{kind} {name:#?} is not an RFC 3339 timestamp: {error}"),
+            )
+        })
 }
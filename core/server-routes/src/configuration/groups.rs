@@ -0,0 +1,102 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::{error::ErrorResponse, identity::Admin, validated::Validated};
+use db::queries::configuration::groups::{
+    add_configuration_group_member, get_all_configuration_groups, get_configuration_group,
+    remove_configuration_group_member, update_configuration_group,
+};
+use domain_api::configuration::{
+    ConfigurationGroupResponse, ConfigurationGroupSetResponse, ConfigurationGroupUpdateRequest,
+};
+use rocket::{serde::json::Json, State};
+use sea_orm::DatabaseConnection;
+
+#[get("/")]
+pub async fn index(
+    db: &State<DatabaseConnection>,
+) -> Result<Json<ConfigurationGroupSetResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    get_all_configuration_groups(connection)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[get("/<group_id>")]
+pub async fn show(
+    db: &State<DatabaseConnection>,
+    group_id: &str,
+) -> Result<Json<ConfigurationGroupResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    get_configuration_group(connection, group_id)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[put("/<group_id>", data = "<group>")]
+pub async fn configure(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    group_id: &str,
+    group: Validated<Json<ConfigurationGroupUpdateRequest>>,
+) -> Result<Json<ConfigurationGroupResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    update_configuration_group(connection, group_id, &group)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[put("/<group_id>/members/<user_id>")]
+pub async fn update(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    group_id: &str,
+    user_id: &str,
+) -> Result<Json<ConfigurationGroupResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    add_configuration_group_member(connection, group_id, user_id)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[delete("/<group_id>/members/<user_id>")]
+pub async fn delete(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    group_id: &str,
+    user_id: &str,
+) -> Result<Json<ConfigurationGroupResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    remove_configuration_group_member(connection, group_id, user_id)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
@@ -20,22 +20,74 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use db::queries::configuration::{get_all_configuration_keys, get_all_configuration_types};
-use domain_api::configuration::ConfigurationKeySetResponse;
-use rocket::{serde::json::Json, State};
+use super::load_configuration_snapshot;
+use crate::{error::ErrorResponse, identity::Admin, validated::Validated};
+use db::queries::configuration::{
+    cache::ConfigurationCache, create_configuration_key, deactivate_configuration_key,
+    get_all_configuration_types, update_configuration_key,
+};
+use domain_api::configuration::{
+    ConfigurationKeyCreateRequest, ConfigurationKeyResponse, ConfigurationKeySetResponse,
+    ConfigurationKeyUpdateRequest,
+};
+use rocket::{response::status, serde::json::Json, State};
 use sea_orm::DatabaseConnection;
 
 #[get("/")]
-pub async fn index(db: &State<DatabaseConnection>) -> Json<ConfigurationKeySetResponse> {
+pub async fn index(
+    db: &State<DatabaseConnection>,
+    cache: &State<ConfigurationCache>,
+) -> Result<Json<ConfigurationKeySetResponse>, ErrorResponse> {
     let connection = db as &DatabaseConnection;
 
-    let types = get_all_configuration_types(connection)
+    let snapshot = load_configuration_snapshot(connection, cache).await?;
+
+    Ok(Json(snapshot.keys().clone()))
+}
+
+#[post("/", data = "<key>")]
+pub async fn create(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    key: Validated<Json<ConfigurationKeyCreateRequest>>,
+) -> Result<Json<ConfigurationKeyResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let types = get_all_configuration_types(connection).await?;
+
+    create_configuration_key(connection, &types, &key)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[put("/<id>", data = "<key>")]
+pub async fn update(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    id: i32,
+    key: Validated<Json<ConfigurationKeyUpdateRequest>>,
+) -> Result<Json<ConfigurationKeyResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let types = get_all_configuration_types(connection).await?;
+
+    update_configuration_key(connection, &types, id, &key)
         .await
-        .expect("failed to get configuration types from database");
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[delete("/<id>")]
+pub async fn delete(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    id: i32,
+) -> Result<status::NoContent, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
 
-    Json(
-        get_all_configuration_keys(connection, &types)
-            .await
-            .expect("failed to get configuration types from database"),
-    )
+    deactivate_configuration_key(connection, id)
+        .await
+        .map(|_| status::NoContent)
+        .map_err(ErrorResponse::from)
 }
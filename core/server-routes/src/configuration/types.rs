@@ -20,18 +20,70 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use db::queries::configuration::get_all_configuration_types;
-use domain_api::configuration::ConfigurationTypeSetResponse;
-use rocket::{serde::json::Json, State};
+use super::load_configuration_snapshot;
+use crate::{error::ErrorResponse, identity::Admin, validated::Validated};
+use db::queries::configuration::{
+    cache::ConfigurationCache, create_configuration_type, deactivate_configuration_type,
+    update_configuration_type,
+};
+use domain_api::configuration::{
+    ConfigurationTypeCreateRequest, ConfigurationTypeResponse, ConfigurationTypeSetResponse,
+    ConfigurationTypeUpdateRequest,
+};
+use rocket::{response::status, serde::json::Json, State};
 use sea_orm::DatabaseConnection;
 
 #[get("/")]
-pub async fn index(db: &State<DatabaseConnection>) -> Json<ConfigurationTypeSetResponse> {
+pub async fn index(
+    db: &State<DatabaseConnection>,
+    cache: &State<ConfigurationCache>,
+) -> Result<Json<ConfigurationTypeSetResponse>, ErrorResponse> {
     let connection = db as &DatabaseConnection;
 
-    Json(
-        get_all_configuration_types(connection)
-            .await
-            .expect("failed to get configuration types from database"),
-    )
+    let snapshot = load_configuration_snapshot(connection, cache).await?;
+
+    Ok(Json(snapshot.types().clone()))
+}
+
+#[post("/", data = "<configuration_type>")]
+pub async fn create(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    configuration_type: Validated<Json<ConfigurationTypeCreateRequest>>,
+) -> Result<Json<ConfigurationTypeResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    create_configuration_type(connection, &configuration_type)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[put("/<id>", data = "<configuration_type>")]
+pub async fn update(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    id: i32,
+    configuration_type: Validated<Json<ConfigurationTypeUpdateRequest>>,
+) -> Result<Json<ConfigurationTypeResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    update_configuration_type(connection, id, &configuration_type)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[delete("/<id>")]
+pub async fn delete(
+    db: &State<DatabaseConnection>,
+    _admin: Admin,
+    id: i32,
+) -> Result<status::NoContent, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    deactivate_configuration_type(connection, id)
+        .await
+        .map(|_| status::NoContent)
+        .map_err(ErrorResponse::from)
 }
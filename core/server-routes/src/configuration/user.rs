@@ -0,0 +1,84 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::{load_configuration_key, load_configuration_snapshot};
+use crate::{error::ErrorResponse, identity::UserId, validated::Validated};
+use db::queries::configuration::{
+    cache::ConfigurationCache, deactivate_configuration_entry_items,
+    replace_configuration_entry_items,
+};
+use domain_api::configuration::{ConfigurationEntryRequest, ConfigurationEntryResponse};
+use rocket::{serde::json::Json, State};
+use sea_orm::DatabaseConnection;
+
+// These routes are ranked after `/configuration/keys/<id>` and
+// `/configuration/types/<id>`, which have the same shape.
+
+#[get("/<name>/user", rank = 2)]
+pub async fn index(
+    db: &State<DatabaseConnection>,
+    cache: &State<ConfigurationCache>,
+    user_id: UserId,
+    name: &str,
+) -> Result<Json<ConfigurationEntryResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let snapshot = load_configuration_snapshot(connection, cache).await?;
+
+    snapshot
+        .entry(name, Some(&user_id.0))
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[put("/<name>/user", rank = 2, data = "<entry>")]
+pub async fn update(
+    db: &State<DatabaseConnection>,
+    user_id: UserId,
+    name: &str,
+    entry: Validated<Json<ConfigurationEntryRequest>>,
+) -> Result<Json<ConfigurationEntryResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let key = load_configuration_key(connection, name).await?;
+
+    replace_configuration_entry_items(connection, &key, Some(&user_id.0), &entry.items)
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
+
+#[delete("/<name>/user", rank = 2)]
+pub async fn delete(
+    db: &State<DatabaseConnection>,
+    user_id: UserId,
+    name: &str,
+) -> Result<Json<ConfigurationEntryResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    let key = load_configuration_key(connection, name).await?;
+
+    deactivate_configuration_entry_items(connection, &key, Some(&user_id.0))
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
@@ -0,0 +1,464 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Error responses and catchers.
+//!
+//! Every error is returned with a JSON body matching
+//! [`ErrorWithMessageResponse`], including the errors that Rocket produces
+//! itself when no route matches or a request body cannot be parsed. Errors
+//! caused by invalid request bodies also list each failing field and rule.
+//! Errors that are not caused by the request only carry a generic message and
+//! the request id, so that details of the database are never sent to clients.
+
+use crate::request_id::RequestId;
+use db::request_id::current_request_id;
+use domain_api::{ErrorWithMessageResponse, FieldErrorResponse};
+use rocket::{
+    http::Status,
+    response::{self, status, Responder},
+    serde::json::Json,
+    Catcher, Request,
+};
+use sea_orm::{DbErr, RuntimeErr, SqlxError};
+use std::sync::Mutex;
+use tracing::{debug, error};
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+/// An error response with a status code and a JSON message body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorResponse {
+    /// The status of the response
+    pub status: Status,
+    /// The message of the response body
+    pub message: String,
+    /// The fields of the request body that failed validation
+    pub errors: Vec<FieldErrorResponse>,
+}
+
+impl ErrorResponse {
+    /// Create an error response with a status and a message.
+    pub fn new(status: Status, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Store the error in the request-local cache so that the catcher for its
+    /// status can respond with it.
+    ///
+    /// Request and data guards can only fail with a status, so this is how they
+    /// report why the request was rejected.
+    ///
+    /// A later error replaces an earlier one, since the earlier guard may have
+    /// been optional.
+    pub(crate) fn cache(self, request: &Request<'_>) {
+        if let Ok(mut cached) = request
+            .local_cache(|| CachedErrorResponse(Mutex::new(None)))
+            .0
+            .lock()
+        {
+            *cached = Some(self);
+        }
+    }
+
+    /// Get the error stored by [`ErrorResponse::cache`] if it has the given
+    /// status.
+    fn cached(request: &Request<'_>, status: Status) -> Option<Self> {
+        request
+            .local_cache(|| CachedErrorResponse(Mutex::new(None)))
+            .0
+            .lock()
+            .ok()?
+            .as_ref()
+            .filter(|error| error.status == status)
+            .cloned()
+    }
+}
+
+/// Wrapper so that the cached error does not collide with other values of the
+/// same type in the request-local cache
+struct CachedErrorResponse(Mutex<Option<ErrorResponse>>);
+
+impl From<db::Error> for ErrorResponse {
+    /// Map a database error to the status that matches its variant. Errors
+    /// that are not caused by the request are returned as
+    /// `500 Internal Server Error`, unless the database is unreachable.
+    ///
+    /// Only errors caused by the request keep the error as their message. Other
+    /// errors are logged in full and respond with a generic message.
+    fn from(error: db::Error) -> Self {
+        let status = match &error {
+            db::Error::ConfigurationTypeNotFound(_)
+            | db::Error::ConfigurationTypeNotFoundByName(_)
+            | db::Error::ConfigurationKeyNotFound(_)
+            | db::Error::ConfigurationKeyNotFoundByName(_) => Status::NotFound,
+            db::Error::ConfigurationTypeAlreadyExists(_)
+            | db::Error::ConfigurationTypeInUse(_)
+            | db::Error::ConfigurationKeyAlreadyExists(_) => Status::Conflict,
+            db::Error::ConfigurationKeyUserOverrideNotAllowed(_) => Status::Forbidden,
+            db::Error::ConfigurationTypeUnsupported(_)
+            | db::Error::ConfigurationKeyNameInvalid(_)
+            | db::Error::AuditRangeInvalid(_, _)
+            | db::Error::ConfigurationKeyMultipleNotAllowed(_)
+            | db::Error::ConfigurationKeyRequired(_)
+            | db::Error::ConfigurationKeyTypeChangeIncompatible(_)
+            | db::Error::ConfigurationKeyConstraintsInvalid(_, _)
+            | db::Error::ConfigurationKeyRolloutInvalid(_, _)
+            | db::Error::ConfigurationValueTypeMismatch(_)
+            | db::Error::ConfigurationValueOutOfRange(_)
+            | db::Error::ConfigurationValuePatternMismatch(_)
+            | db::Error::ConfigurationValueNotAllowed(_)
+            | db::Error::ValidatorValidationErrors(_) => Status::UnprocessableEntity,
+            db::Error::SeaORMDbErr(error) if is_connection_error(error) => {
+                Status::ServiceUnavailable
+            }
+            db::Error::SqlxError(error) if is_sqlx_connection_error(error) => {
+                Status::ServiceUnavailable
+            }
+            _ => Status::InternalServerError,
+        };
+
+        if status.code >= 500 {
+            error!(error = %error, status = status.code, "database error");
+        } else {
+            debug!(error = %error, status = status.code, "request rejected");
+        }
+
+        if status.code >= 500 {
+            return Self::new(
+                status,
+                server_error_message(status, current_request_id().as_deref()),
+            );
+        }
+
+        let errors = match &error {
+            db::Error::ValidatorValidationErrors(errors) => field_errors(errors),
+            _ => Vec::new(),
+        };
+
+        Self {
+            errors,
+            ..Self::new(status, error.to_string())
+        }
+    }
+}
+
+impl From<ValidationErrors> for ErrorResponse {
+    fn from(errors: ValidationErrors) -> Self {
+        Self {
+            errors: field_errors(&errors),
+            ..Self::new(Status::UnprocessableEntity, errors.to_string())
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        status::Custom(
+            self.status,
+            Json(ErrorWithMessageResponse {
+                message: self.message,
+                errors: self.errors,
+            }),
+        )
+        .respond_to(request)
+    }
+}
+
+/// The message of an error that is not caused by the request.
+///
+/// # Arguments
+///
+/// * `status`     - The status of the response
+/// * `request_id` - The id of the request, if there is one
+///
+/// # Returns
+///
+/// A generic message for the status that includes the request id, so that
+/// the full error can be found in the logs.
+fn server_error_message(status: Status, request_id: Option<&str>) -> String {
+    let message = if status == Status::ServiceUnavailable {
+        "could not connect to database"
+    } else {
+        "internal server error"
+    };
+
+    match request_id {
+        Some(request_id) => format!("{message} (request id: {request_id})"),
+        None => message.to_owned(),
+    }
+}
+
+/// Flatten validation errors into one entry per failing field and rule.
+///
+/// Nested fields are joined with `.` and list items are indexed with `[n]`,
+/// for example `constraints.allowedValues` or `items[0].asString`. Field
+/// names are converted to camel case to match the JSON request bodies.
+pub fn field_errors(errors: &ValidationErrors) -> Vec<FieldErrorResponse> {
+    let mut result = Vec::new();
+
+    collect_field_errors(errors, "", &mut result);
+
+    result.sort_by(|a, b| (&a.field, &a.rule).cmp(&(&b.field, &b.rule)));
+
+    result
+}
+
+fn collect_field_errors(
+    errors: &ValidationErrors,
+    prefix: &str,
+    result: &mut Vec<FieldErrorResponse>,
+) {
+    for (field, kind) in errors.errors() {
+        let path = if field == &"__all__" {
+            prefix.to_owned()
+        } else if prefix.is_empty() {
+            to_camel_case(field)
+        } else {
+            format!("{prefix}.{}", to_camel_case(field))
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                result.extend(field_errors.iter().map(|error| FieldErrorResponse {
+                    field: path.clone(),
+                    rule: error.code.to_string(),
+                }))
+            }
+            ValidationErrorsKind::Struct(errors) => collect_field_errors(errors, &path, result),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_field_errors(errors, &format!("{path}[{index}]"), result);
+                }
+            }
+        }
+    }
+}
+
+/// Convert a snake case field name to camel case.
+fn to_camel_case(name: &str) -> String {
+    let mut parts = name.split('_');
+
+    parts
+        .next()
+        .map(str::to_owned)
+        .into_iter()
+        .chain(parts.map(|part| {
+            let mut chars = part.chars();
+
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        }))
+        .collect()
+}
+
+/// Check whether a SeaORM error means that the database could not be reached.
+fn is_connection_error(error: &DbErr) -> bool {
+    match error {
+        DbErr::ConnectionAcquire | DbErr::Conn(_) => true,
+        DbErr::Exec(RuntimeErr::SqlxError(error)) | DbErr::Query(RuntimeErr::SqlxError(error)) => {
+            is_sqlx_connection_error(error)
+        }
+        _ => false,
+    }
+}
+
+/// Check whether an SQLx error means that the database could not be reached.
+fn is_sqlx_connection_error(error: &SqlxError) -> bool {
+    matches!(
+        error,
+        SqlxError::Io(_) | SqlxError::Tls(_) | SqlxError::PoolTimedOut | SqlxError::PoolClosed
+    )
+}
+
+/// The catchers that replace Rocket's default HTML error pages.
+pub fn catchers() -> Vec<Catcher> {
+    catchers![
+        bad_request,
+        unauthorized,
+        forbidden,
+        not_found,
+        unprocessable_entity,
+        internal_server_error
+    ]
+}
+
+#[catch(400)]
+fn bad_request(request: &Request<'_>) -> ErrorResponse {
+    ErrorResponse::cached(request, Status::BadRequest)
+        .unwrap_or_else(|| ErrorResponse::new(Status::BadRequest, "the request is malformed"))
+}
+
+#[catch(401)]
+fn unauthorized(request: &Request<'_>) -> ErrorResponse {
+    ErrorResponse::cached(request, Status::Unauthorized)
+        .unwrap_or_else(|| ErrorResponse::new(Status::Unauthorized, "the request is unauthorized"))
+}
+
+#[catch(403)]
+fn forbidden(request: &Request<'_>) -> ErrorResponse {
+    ErrorResponse::cached(request, Status::Forbidden)
+        .unwrap_or_else(|| ErrorResponse::new(Status::Forbidden, "the request is forbidden"))
+}
+
+#[catch(404)]
+fn not_found(request: &Request<'_>) -> ErrorResponse {
+    ErrorResponse::new(
+        Status::NotFound,
+        format!("no route matches {} {}", request.method(), request.uri()),
+    )
+}
+
+#[catch(422)]
+fn unprocessable_entity(request: &Request<'_>) -> ErrorResponse {
+    ErrorResponse::cached(request, Status::UnprocessableEntity).unwrap_or_else(|| {
+        ErrorResponse::new(
+            Status::UnprocessableEntity,
+            "the request could not be parsed",
+        )
+    })
+}
+
+#[catch(500)]
+fn internal_server_error(request: &Request<'_>) -> ErrorResponse {
+    ErrorResponse::new(
+        Status::InternalServerError,
+        server_error_message(
+            Status::InternalServerError,
+            Some(RequestId::of(request).as_str()),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        field_errors, DbErr, ErrorResponse, FieldErrorResponse, RuntimeErr, SqlxError, Status,
+        ValidationErrors,
+    };
+    use db::request_id::scope_request_id;
+    use domain_api::configuration::{
+        ConfigurationKeyConstraintsResponse, ConfigurationKeyCreateRequest,
+        ConfigurationKeyRolloutResponse,
+    };
+    use validator::Validate;
+
+    #[test]
+    fn test_status_of_db_errors() {
+        let status_of = |error| ErrorResponse::from(error).status;
+
+        assert_eq!(
+            status_of(db::Error::ConfigurationKeyNotFound(1)),
+            Status::NotFound
+        );
+        assert_eq!(
+            status_of(db::Error::ValidatorValidationErrors(ValidationErrors::new())),
+            Status::UnprocessableEntity
+        );
+        assert_eq!(
+            status_of(db::Error::SeaORMDbErr(DbErr::ConnectionAcquire)),
+            Status::ServiceUnavailable
+        );
+        assert_eq!(
+            status_of(db::Error::SeaORMDbErr(DbErr::Query(RuntimeErr::SqlxError(
+                SqlxError::PoolTimedOut
+            )))),
+            Status::ServiceUnavailable
+        );
+        assert_eq!(
+            status_of(db::Error::SeaORMDbErr(DbErr::RecordNotFound(
+                "missing".to_owned()
+            ))),
+            Status::InternalServerError
+        );
+    }
+
+    #[async_std::test]
+    async fn test_message_of_db_errors() {
+        let internal = "relation \"configuration_entries\" does not exist";
+
+        // Database failures are not echoed to the client
+        let response = scope_request_id("request".to_owned(), async {
+            ErrorResponse::from(db::Error::SeaORMDbErr(DbErr::Custom(internal.to_owned())))
+        })
+        .await;
+
+        assert_eq!(response.status, Status::InternalServerError);
+        assert_eq!(
+            response.message,
+            "internal server error (request id: request)"
+        );
+        assert!(!response.message.contains(internal));
+
+        let response = ErrorResponse::from(db::Error::SeaORMDbErr(DbErr::Conn(
+            RuntimeErr::Internal(internal.to_owned()),
+        )));
+
+        assert_eq!(response.status, Status::ServiceUnavailable);
+        assert_eq!(response.message, "could not connect to database");
+
+        // Errors caused by the request keep their message
+        assert_eq!(
+            ErrorResponse::from(db::Error::ConfigurationKeyNotFound(1)).message,
+            db::Error::ConfigurationKeyNotFound(1).to_string()
+        );
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let request = ConfigurationKeyCreateRequest {
+            name: "has spaces".to_owned(),
+            description: "".to_owned(),
+            type_id: 1,
+            optional: false,
+            allows_multiple: false,
+            allows_user_override: false,
+            constraints: ConfigurationKeyConstraintsResponse {
+                allowed_values: Some(Vec::new()),
+                ..Default::default()
+            },
+            rollout: Some(ConfigurationKeyRolloutResponse {
+                percentage: 101.0,
+                ..Default::default()
+            }),
+        };
+
+        let field_error = |field: &str, rule: &str| FieldErrorResponse {
+            field: field.to_owned(),
+            rule: rule.to_owned(),
+        };
+
+        assert_eq!(
+            field_errors(&request.validate().unwrap_err()),
+            vec![
+                field_error("constraints.allowedValues", "length"),
+                field_error("description", "length"),
+                field_error("name", "regex"),
+                field_error("rollout.percentage", "range"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Routes for orchestrators to probe the health of the server.
+
+use crate::error::ErrorResponse;
+use db::queries::migrations::{get_migration_status, ping_database};
+use domain_api::health::{
+    HealthMigrationsResponse, HealthReadinessResponse, HealthResponse, HealthStatus,
+};
+use migration::{Migrator, MigratorTrait};
+use rocket::{http::Status, response::status, serde::json::Json, State};
+use sea_orm::DatabaseConnection;
+
+/// The names of all migrations that the server expects to have been applied.
+fn migration_names() -> Vec<String> {
+    Migrator::migrations()
+        .iter()
+        .map(|migration| migration.name().to_owned())
+        .collect()
+}
+
+#[get("/live")]
+pub fn live() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: HealthStatus::Up,
+    })
+}
+
+#[get("/ready")]
+pub async fn ready(
+    db: &State<DatabaseConnection>,
+) -> status::Custom<Json<HealthReadinessResponse>> {
+    let connection = db as &DatabaseConnection;
+
+    let database_up = ping_database(connection).await.is_ok();
+
+    let migrations = if database_up {
+        get_migration_status(connection, &migration_names())
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    // The server is only ready once the schema has caught up with it
+    let ready = migrations
+        .as_ref()
+        .map(|migrations| migrations.up_to_date)
+        .unwrap_or(false);
+
+    let status_of = |up| {
+        if up {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        }
+    };
+
+    status::Custom(
+        if ready {
+            Status::Ok
+        } else {
+            Status::ServiceUnavailable
+        },
+        Json(HealthReadinessResponse {
+            status: status_of(ready),
+            database: status_of(database_up),
+            migrations,
+        }),
+    )
+}
+
+#[get("/migrations")]
+pub async fn migrations(
+    db: &State<DatabaseConnection>,
+) -> Result<Json<HealthMigrationsResponse>, ErrorResponse> {
+    let connection = db as &DatabaseConnection;
+
+    get_migration_status(connection, &migration_names())
+        .await
+        .map(Json)
+        .map_err(ErrorResponse::from)
+}
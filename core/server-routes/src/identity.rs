@@ -0,0 +1,143 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Identification of the user making a request.
+
+// TODO: https://github.com/sophie-katz/prelude/issues/11
+
+use crate::error::ErrorResponse;
+use config_env::Configuration;
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    Request,
+};
+
+/// The name of the header from which the user id is read.
+pub const USER_ID_HEADER: &str = "X-User-Id";
+
+/// The name of the header from which the admin token is read.
+pub const AUTHORIZATION_HEADER: &str = "Authorization";
+
+/// The scheme that the admin token is sent with.
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// The id of the user making a request.
+///
+/// Until authorization is implemented, this is read verbatim from the
+/// `X-User-Id` header. Requests without the header fail with
+/// `401 Unauthorized`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UserId(pub String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserId {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.headers().get_one(USER_ID_HEADER) {
+            Some(user_id) if !user_id.is_empty() => Outcome::Success(UserId(user_id.to_owned())),
+            _ => {
+                ErrorResponse::new(
+                    Status::Unauthorized,
+                    format!("a user id is required in the {USER_ID_HEADER} header"),
+                )
+                .cache(request);
+
+                Outcome::Failure((Status::Unauthorized, ()))
+            }
+        }
+    }
+}
+
+/// Proof that a request was made by an administrator.
+///
+/// Requests must send `Authorization: Bearer <token>` with the token in
+/// `ADMIN_API_TOKEN`. Requests without the header fail with
+/// `401 Unauthorized` and requests with another token fail with
+/// `403 Forbidden`. If `ADMIN_API_TOKEN` is not set, every request fails with
+/// `403 Forbidden`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let admin_api_token = request
+            .rocket()
+            .state::<Configuration>()
+            .and_then(|configuration| configuration.admin_api_token.as_deref());
+
+        let token = request
+            .headers()
+            .get_one(AUTHORIZATION_HEADER)
+            .and_then(|value| value.strip_prefix(BEARER_PREFIX));
+
+        let error = match (admin_api_token, token) {
+            (Some(admin_api_token), Some(token)) if tokens_match(admin_api_token, token) => {
+                return Outcome::Success(Admin)
+            }
+            (None, _) => ErrorResponse::new(
+                Status::Forbidden,
+                "administration is disabled because no admin token is configured",
+            ),
+            (Some(_), None) => ErrorResponse::new(
+                Status::Unauthorized,
+                "an admin token is required in the Authorization header",
+            ),
+            (Some(_), Some(_)) => {
+                ErrorResponse::new(Status::Forbidden, "the admin token is invalid")
+            }
+        };
+
+        let status = error.status;
+
+        error.cache(request);
+
+        Outcome::Failure((status, ()))
+    }
+}
+
+/// Compare tokens in time that does not depend on where they differ.
+fn tokens_match(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tokens_match;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+        assert!(!tokens_match("secret", ""));
+    }
+}
@@ -25,16 +25,102 @@
 #[macro_use]
 extern crate rocket;
 
+use cache::ConfigurationCacheFairing;
+use config_env::Configuration;
+use db::queries::configuration::cache::ConfigurationCache;
+use metrics::{Metrics, MetricsFairing};
+use overrides::ConfigurationOverridesFairing;
+use request_id::{request_scoped, RequestIdFairing};
 use rocket::{build, Build, Rocket};
 use sea_orm::DatabaseConnection;
 
+pub mod cache;
 pub mod configuration;
+pub mod error;
+pub mod health;
+pub mod identity;
+pub mod logging;
+pub mod metrics;
+pub mod overrides;
+pub mod request_id;
+pub mod validated;
 
 /// Build Rocket instance
-pub fn rocket(db: DatabaseConnection) -> Rocket<Build> {
+///
+/// # Arguments
+///
+/// * `db` - The database connection that the routes use
+/// * `configuration` - The configuration loaded from the environment, which
+///                     holds the admin token and the configuration overrides
+///                     among other things
+pub fn rocket(mut db: DatabaseConnection, configuration: Configuration) -> Rocket<Build> {
+    let metrics = Metrics::new();
+
+    metrics.observe_queries(&mut db);
+
     build()
         .manage(db)
-        .mount("/configuration", routes![configuration::index])
-        .mount("/configuration/types", routes![configuration::types::index])
-        .mount("/configuration/keys", routes![configuration::keys::index])
+        .manage(ConfigurationCache::new(
+            configuration.configuration_overrides.clone(),
+        ))
+        .manage(configuration)
+        .manage(metrics)
+        .attach(RequestIdFairing)
+        .attach(ConfigurationOverridesFairing)
+        .attach(ConfigurationCacheFairing)
+        .attach(MetricsFairing)
+        .register("/", error::catchers())
+        .mount(
+            "/configuration",
+            request_scoped(routes![
+                configuration::index,
+                configuration::effective,
+                configuration::diff,
+                configuration::rollback,
+                configuration::events,
+                configuration::show,
+                configuration::create,
+                configuration::update,
+                configuration::delete,
+                configuration::user::index,
+                configuration::user::update,
+                configuration::user::delete,
+                configuration::group::index,
+                configuration::group::update,
+                configuration::group::delete
+            ]),
+        )
+        .mount(
+            "/configuration/groups",
+            request_scoped(routes![
+                configuration::groups::index,
+                configuration::groups::show,
+                configuration::groups::configure,
+                configuration::groups::update,
+                configuration::groups::delete
+            ]),
+        )
+        .mount(
+            "/configuration/types",
+            request_scoped(routes![
+                configuration::types::index,
+                configuration::types::create,
+                configuration::types::update,
+                configuration::types::delete
+            ]),
+        )
+        .mount(
+            "/configuration/keys",
+            request_scoped(routes![
+                configuration::keys::index,
+                configuration::keys::create,
+                configuration::keys::update,
+                configuration::keys::delete
+            ]),
+        )
+        .mount(
+            "/health",
+            request_scoped(routes![health::live, health::ready, health::migrations]),
+        )
+        .mount("/metrics", request_scoped(routes![metrics::index]))
 }
@@ -0,0 +1,59 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Logging for the server.
+//!
+//! Logs are written to standard output with [`tracing`], either as one JSON
+//! object per line or as human-readable text. Records from the `log` crate,
+//! such as Rocket's own messages, are written the same way.
+
+use config_env::{Configuration, LogFormat};
+use std::error;
+use tracing_subscriber::EnvFilter;
+
+/// Install the global logger described by the configuration.
+///
+/// # Arguments
+///
+/// * `configuration` - The configuration to take the log format and filter
+///                     from
+///
+/// # Errors
+///
+/// Returns an error if the filter is invalid or if a global logger has already
+/// been installed.
+pub fn initialize_logging(
+    configuration: &Configuration,
+) -> Result<(), Box<dyn error::Error + Send + Sync>> {
+    let builder =
+        tracing_subscriber::fmt().with_env_filter(EnvFilter::try_new(&configuration.log_filter)?);
+
+    match configuration.log_format {
+        LogFormat::Json => {
+            // Rocket colors its messages unless its own logger is installed
+            rocket::yansi::Paint::disable();
+
+            builder.json().flatten_event(true).try_init()
+        }
+        LogFormat::Text => builder.try_init(),
+    }
+}
@@ -0,0 +1,282 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Prometheus metrics for the server and its database connection.
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::ContentType,
+    Data, Request, Response, State,
+};
+use sea_orm::{metric, DatabaseConnection};
+use std::time::Instant;
+
+/// The label used for requests that did not match any route
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The metrics exposed at `/metrics`.
+///
+/// Request metrics are recorded by [`MetricsFairing`] and query metrics by the
+/// callback installed with [`Metrics::observe_queries`]. Connection pool usage
+/// is read whenever the metrics are rendered.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    db_query_duration: HistogramVec,
+    db_query_failures: IntCounterVec,
+}
+
+impl Metrics {
+    /// Create and register all metrics.
+    pub fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("prelude".to_owned()), None).expect("metric prefix is valid");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Number of HTTP requests handled"),
+            &["method", "route", "status"],
+        )
+        .expect("metric is valid");
+
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests",
+            ),
+            &["method", "route"],
+        )
+        .expect("metric is valid");
+
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Number of open database connections in the pool",
+            ),
+            &["state"],
+        )
+        .expect("metric is valid");
+
+        let db_query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "db_query_duration_seconds",
+                "Time taken to run database queries",
+            ),
+            &["operation"],
+        )
+        .expect("metric is valid");
+
+        let db_query_failures = IntCounterVec::new(
+            Opts::new(
+                "db_query_failures_total",
+                "Number of database queries that failed",
+            ),
+            &["operation"],
+        )
+        .expect("metric is valid");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration.clone()),
+            Box::new(db_pool_connections.clone()),
+            Box::new(db_query_duration.clone()),
+            Box::new(db_query_failures.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_pool_connections,
+            db_query_duration,
+            db_query_failures,
+        }
+    }
+
+    /// Time every query run on a database connection, including the queries
+    /// from `db::queries`.
+    pub fn observe_queries(&self, connection: &mut DatabaseConnection) {
+        let metrics = self.clone();
+
+        connection.set_metric_callback(move |info| metrics.observe_query(info));
+    }
+
+    /// Record a query that was run.
+    fn observe_query(&self, info: &metric::Info<'_>) {
+        let operation = query_operation(&info.statement.sql);
+
+        self.db_query_duration
+            .with_label_values(&[operation])
+            .observe(info.elapsed.as_secs_f64());
+
+        if info.failed {
+            self.db_query_failures.with_label_values(&[operation]).inc();
+        }
+    }
+
+    /// Record a request that was handled.
+    fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+
+        self.http_request_duration
+            .with_label_values(&[method, route])
+            .observe(seconds);
+    }
+
+    /// Render all metrics in the Prometheus text format.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection to read the pool usage of
+    pub fn render(&self, connection: &DatabaseConnection) -> String {
+        // Only connection pools have usage to report
+        if let DatabaseConnection::SqlxPostgresPoolConnection(_) = connection {
+            let pool = connection.get_postgres_connection_pool();
+
+            let idle = pool.num_idle() as i64;
+
+            self.db_pool_connections
+                .with_label_values(&["idle"])
+                .set(idle);
+            self.db_pool_connections
+                .with_label_values(&["active"])
+                .set(i64::from(pool.size()) - idle);
+        }
+
+        let mut buffer = Vec::new();
+
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("metrics can be encoded");
+
+        String::from_utf8(buffer).expect("metrics are valid UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the kind of a SQL statement to label query metrics with.
+///
+/// Only the common statements are labelled by name so that the number of
+/// labels stays small.
+fn query_operation(sql: &str) -> &'static str {
+    let keyword = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+
+    match keyword.as_str() {
+        "SELECT" => "select",
+        "INSERT" => "insert",
+        "UPDATE" => "update",
+        "DELETE" => "delete",
+        _ => "other",
+    }
+}
+
+/// The time that a request was received at
+struct RequestStart(Option<Instant>);
+
+/// Records the count, status and latency of every request in the managed
+/// [`Metrics`].
+///
+/// Requests are labelled with the URI pattern of the route that handled them,
+/// such as `/configuration/<name>`, so that the number of labels stays small.
+pub struct MetricsFairing;
+
+#[rocket::async_trait]
+impl Fairing for MetricsFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(metrics) = request.rocket().state::<Metrics>() else {
+            return;
+        };
+
+        let Some(start) = request.local_cache(|| RequestStart(None)).0 else {
+            return;
+        };
+
+        let route = request
+            .route()
+            .map(|route| route.uri.to_string())
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned());
+
+        metrics.observe_request(
+            request.method().as_str(),
+            &route,
+            response.status().code,
+            start.elapsed().as_secs_f64(),
+        );
+    }
+}
+
+#[get("/")]
+pub fn index(db: &State<DatabaseConnection>, metrics: &State<Metrics>) -> (ContentType, String) {
+    let connection = db as &DatabaseConnection;
+
+    (
+        ContentType::new("text", "plain").with_params([("version", "0.0.4"), ("charset", "utf-8")]),
+        metrics.render(connection),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::query_operation;
+
+    #[test]
+    fn test_query_operation() {
+        assert_eq!(query_operation("SELECT 1"), "select");
+        assert_eq!(query_operation("  insert INTO t VALUES (1)"), "insert");
+        assert_eq!(
+            query_operation("WITH t AS (SELECT 1) SELECT * FROM t"),
+            "other"
+        );
+        assert_eq!(query_operation(""), "other");
+    }
+}
@@ -0,0 +1,93 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Checking the configuration overrides from the environment at startup.
+
+use config_env::Configuration;
+use db::{
+    queries::configuration::{
+        check_configuration_environment_overrides, get_all_configuration_keys,
+        get_all_configuration_types,
+    },
+    Error,
+};
+use rocket::{
+    fairing::{self, Fairing, Info, Kind},
+    Build, Rocket,
+};
+use sea_orm::DatabaseConnection;
+use tracing::{error, warn};
+
+/// Stops the server from launching if an environment variable pins a
+/// configuration key to a value that cannot be used.
+///
+/// Reads skip such values with a warning instead of failing, so this is where
+/// they are reported. If the keys cannot be loaded, for example because the
+/// database is down, the overrides are not checked and the server launches.
+pub struct ConfigurationOverridesFairing;
+
+#[rocket::async_trait]
+impl Fairing for ConfigurationOverridesFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Configuration overrides",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let (Some(connection), Some(configuration)) = (
+            rocket.state::<DatabaseConnection>(),
+            rocket.state::<Configuration>(),
+        ) else {
+            return Ok(rocket);
+        };
+
+        let keys = match get_all_configuration_types(connection).await {
+            Ok(types) => get_all_configuration_keys(connection, &types).await,
+            Err(error) => Err(error),
+        };
+
+        let keys = match keys {
+            Ok(keys) => keys,
+            Err(error) => {
+                warn!(error = %error, "configuration overrides were not checked");
+                return Ok(rocket);
+            }
+        };
+
+        match check_configuration_environment_overrides(
+            &keys,
+            &configuration.configuration_overrides,
+        ) {
+            Ok(()) => Ok(rocket),
+            Err(Error::ConfigurationOverrideInvalid(name, error)) => {
+                error!(key_name = %name, error = %error, "configuration override is invalid");
+                Err(rocket)
+            }
+            Err(error) => {
+                error!(error = %error, "configuration overrides could not be checked");
+                Err(rocket)
+            }
+        }
+    }
+}
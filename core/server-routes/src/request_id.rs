@@ -0,0 +1,213 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Request ids for correlating logs and audit records.
+//!
+//! Every request is given an id, taken from its `X-Request-Id` header or
+//! generated if the header is missing or invalid. The id is returned in the
+//! `X-Request-Id` header of the response, recorded on every log line written
+//! while handling the request, and stored with the audit records of any
+//! changes that the request makes.
+
+use db::request_id::scope_request_id;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Header,
+    request::{FromRequest, Outcome},
+    route::{self, Handler},
+    Data, Request, Response, Route,
+};
+use std::{
+    fmt::{self, Display},
+    time::Instant,
+};
+use tracing::{info, info_span, Instrument};
+use uuid::Uuid;
+
+/// The header that request ids are read from and returned in
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The longest request id that is accepted from a client
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// The id of a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Generate a new random request id.
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    /// Parse a request id sent by a client.
+    ///
+    /// # Returns
+    ///
+    /// The request id, or `None` if the value is empty, too long or contains
+    /// characters other than visible ASCII.
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.is_empty()
+            || value.len() > MAX_REQUEST_ID_LENGTH
+            || !value.bytes().all(|byte| byte.is_ascii_graphic())
+        {
+            return None;
+        }
+
+        Some(Self(value.to_owned()))
+    }
+
+    /// Get the id of a request.
+    ///
+    /// The id is assigned by [`RequestIdFairing`]. If the fairing is not
+    /// attached, an id is generated the first time this is called.
+    pub fn of<'r>(request: &'r Request<'_>) -> &'r Self {
+        request.local_cache(Self::generate)
+    }
+
+    /// Get the id as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r RequestId {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        Outcome::Success(RequestId::of(request))
+    }
+}
+
+/// The time that a request was received at
+struct RequestReceived(Option<Instant>);
+
+/// Assigns an id to every request, returns it in the `X-Request-Id` header of
+/// the response and logs the outcome of the request.
+pub struct RequestIdFairing;
+
+#[rocket::async_trait]
+impl Fairing for RequestIdFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request ID",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let request_id = request
+            .headers()
+            .get_one(REQUEST_ID_HEADER)
+            .and_then(RequestId::parse)
+            .unwrap_or_else(RequestId::generate);
+
+        request.local_cache(|| request_id);
+        request.local_cache(|| RequestReceived(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let request_id = RequestId::of(request);
+
+        response.set_header(Header::new(REQUEST_ID_HEADER, request_id.to_string()));
+
+        let duration_ms = request
+            .local_cache(|| RequestReceived(None))
+            .0
+            .map(|received| received.elapsed().as_secs_f64() * 1000.0);
+
+        info!(
+            request_id = %request_id,
+            method = %request.method(),
+            uri = %request.uri(),
+            status = response.status().code,
+            duration_ms,
+            "request completed"
+        );
+    }
+}
+
+/// A route handler that runs the handler it wraps with the id of the request
+/// in scope.
+#[derive(Clone)]
+struct RequestScopedHandler(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for RequestScopedHandler {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        let request_id = RequestId::of(request);
+
+        let span = info_span!(
+            "request",
+            request_id = %request_id,
+            method = %request.method(),
+            route = request.route().map(|route| route.uri.to_string()),
+        );
+
+        scope_request_id(request_id.to_string(), self.0.handle(request, data))
+            .instrument(span)
+            .await
+    }
+}
+
+/// Wrap the handlers of routes so that they run inside a span with the id of
+/// the request, and so that database writes record the id in the audit
+/// tables.
+pub fn request_scoped(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(RequestScopedHandler(route.handler));
+            route
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RequestId, MAX_REQUEST_ID_LENGTH};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            RequestId::parse("0b5f3c2e-8d51-4a8e-9a43-5f0f6a1f2c7d")
+                .as_ref()
+                .map(RequestId::as_str),
+            Some("0b5f3c2e-8d51-4a8e-9a43-5f0f6a1f2c7d")
+        );
+        assert_eq!(RequestId::parse(""), None);
+        assert_eq!(RequestId::parse("has spaces"), None);
+        assert_eq!(RequestId::parse("line\nbreak"), None);
+        assert_eq!(
+            RequestId::parse(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)),
+            None
+        );
+        assert!(RequestId::parse(&"a".repeat(MAX_REQUEST_ID_LENGTH)).is_some());
+    }
+}
@@ -0,0 +1,80 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A data guard that validates request bodies.
+
+use crate::error::ErrorResponse;
+use rocket::{
+    data::{self, Data, FromData},
+    outcome::Outcome,
+    serde::json::Json,
+    Request,
+};
+use serde::de::DeserializeOwned;
+use std::ops::Deref;
+use validator::Validate;
+
+/// A request body that has been parsed and passed validation.
+///
+/// Use `Validated<Json<T>>` in place of `Json<T>` for every request body. If
+/// the body cannot be parsed or fails validation, the request is rejected
+/// before the handler runs and the catcher for the status responds with an
+/// [`ErrorResponse`] listing each failing field and rule.
+#[derive(Debug)]
+pub struct Validated<T>(pub T);
+
+impl<T> Validated<T> {
+    /// Unwrap the validated value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Validated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: DeserializeOwned + Validate> FromData<'r> for Validated<Json<T>> {
+    type Error = ErrorResponse;
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let error = match Json::<T>::from_data(request, data).await {
+            Outcome::Success(value) => match value.validate() {
+                Ok(()) => return Outcome::Success(Validated(value)),
+                Err(errors) => ErrorResponse::from(errors),
+            },
+            Outcome::Failure((status, error)) => ErrorResponse::new(status, error.to_string()),
+            Outcome::Forward(data) => return Outcome::Forward(data),
+        };
+
+        let status = error.status;
+
+        error.clone().cache(request);
+
+        Outcome::Failure((status, error))
+    }
+}
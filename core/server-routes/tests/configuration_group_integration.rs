@@ -0,0 +1,316 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config_env::Configuration;
+use db::{
+    seeding::{
+        insert_configuration_entry, insert_configuration_key_reference,
+        insert_configuration_type_reference,
+    },
+    testing::initialize_unit_database,
+};
+use rocket::{
+    http::{Header, Status},
+    local::asynchronous::Client,
+};
+use serde_json::json;
+use serial_test::serial;
+
+#[async_std::test]
+#[serial]
+async fn test_groups() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, None, "false").await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Only administrators may write group overrides and members
+    let response = client
+        .put("/configuration/theme.darkMode/group/beta")
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client
+        .delete("/configuration/theme.darkMode/group/beta")
+        .header(Header::new("Authorization", "Bearer user"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .put("/configuration/groups/beta/members/user")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client
+        .delete("/configuration/groups/beta/members/user")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client.get("/configuration/groups").dispatch().await;
+
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!([])
+    );
+
+    // Keys that do not allow overrides are rejected
+    let response = client
+        .put("/configuration/systems.enabled.code/group/beta")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    // Create group override
+    let response = client
+        .put("/configuration/theme.darkMode/group/beta")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], false);
+    assert_eq!(body["groups"][0]["groupId"], "beta");
+    assert_eq!(body["groups"][0]["items"][0]["value"]["asBoolean"], true);
+
+    // Add member
+    let response = client
+        .put("/configuration/groups/beta/members/user")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["groupId"], "beta");
+    assert_eq!(body["userIds"], json!(["user"]));
+
+    let response = client.get("/configuration/groups").dispatch().await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["groupId"], "beta");
+
+    // The group override is effective for members only
+    let response = client
+        .get("/configuration/effective")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["source"], "group");
+    assert_eq!(body[0]["value"]["asBoolean"], true);
+
+    let response = client
+        .get("/configuration/effective")
+        .header(Header::new("X-User-Id", "other"))
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["source"], "global");
+
+    // Remove member
+    let response = client
+        .delete("/configuration/groups/beta/members/user")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["userIds"], json!([]));
+
+    // Clear group override
+    let response = client
+        .delete("/configuration/theme.darkMode/group/beta")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["groups"], json!([]));
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_group_priority() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    for (group_id, value) in [("alpha", false), ("beta", true)] {
+        let response = client
+            .put(format!("/configuration/theme.darkMode/group/{group_id}"))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .json(&json!({ "items": [{ "asBoolean": value }] }))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .put(format!("/configuration/groups/{group_id}/members/user"))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    // Groups with the same priority are ordered by id
+    let response = client
+        .get("/configuration/effective")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["value"]["asBoolean"], false);
+
+    // Setting a priority requires the admin token
+    let response = client
+        .put("/configuration/groups/beta")
+        .json(&json!({ "priority": 10 }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client
+        .put("/configuration/groups/beta")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "priority": 10 }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["groupId"], "beta");
+    assert_eq!(body["priority"], 10);
+    assert_eq!(body["userIds"], json!(["user"]));
+
+    // The group with the higher priority wins
+    let response = client
+        .get("/configuration/effective")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["source"], "group");
+    assert_eq!(body[0]["value"]["asBoolean"], true);
+
+    let response = client.get("/configuration/groups/alpha").dispatch().await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["priority"], 0);
+
+    Ok(())
+}
@@ -20,16 +20,26 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use chrono::{SecondsFormat, Utc};
+use config_env::Configuration;
 use db::{
+    connect_db,
     seeding::{
         insert_configuration_entry, insert_configuration_key_reference,
         insert_configuration_type_reference,
     },
     testing::initialize_unit_database,
+    DatabaseInstance,
+};
+use rocket::{
+    error::ErrorKind,
+    http::{ContentType, Header, Status},
+    local::asynchronous::Client,
+    tokio::io::AsyncReadExt,
 };
-use rocket::{http::Status, local::asynchronous::Client};
 use serde_json::json;
 use serial_test::serial;
+use std::{collections::BTreeMap, time::Duration};
 
 #[async_std::test]
 #[serial]
@@ -53,7 +63,7 @@ async fn test_index() -> Result<(), db::Error> {
     let entry_id =
         insert_configuration_entry(&connection, systems_enabled_code_id, 1, None, "true").await?;
 
-    let client = Client::tracked(server_routes::rocket(connection))
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
         .await
         .expect("error creating Rocket instance");
 
@@ -75,7 +85,14 @@ async fn test_index() -> Result<(), db::Error> {
                     },
                     "optional": false,
                     "allowsMultiple": false,
-                    "allowsUserOverride": false
+                    "allowsUserOverride": false,
+                    "constraints": {
+                        "minimum": null,
+                        "maximum": null,
+                        "pattern": null,
+                        "allowedValues": null
+                    },
+                    "rollout": null
                 },
                 "itemsGlobal": [
                     {
@@ -84,10 +101,18 @@ async fn test_index() -> Result<(), db::Error> {
                             "asBoolean": true,
                             "asInteger": null,
                             "asFloat": null,
-                            "asString": null
+                            "asString": null,
+                            "asDuration": null,
+                            "asUrl": null,
+                            "asDatetime": null,
+                            "asJson": null,
+                            "asEnum": null,
+                            "asSecret": null,
+                            "redacted": false
                         }
                     }
                 ],
+                "groups": [],
                 "user": null
             }
         ])
@@ -95,3 +120,888 @@ async fn test_index() -> Result<(), db::Error> {
 
     Ok(())
 }
+
+#[async_std::test]
+#[serial]
+async fn test_index_prefix() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    for (name, value) in [
+        ("systems.enabled.code", "true"),
+        ("systems.enabled.ticket", "false"),
+        ("theme.darkMode", "false"),
+    ] {
+        let key_id = insert_configuration_key_reference(
+            &connection,
+            name,
+            "A flag",
+            boolean_id,
+            false,
+            false,
+            false,
+        )
+        .await?;
+
+        insert_configuration_entry(&connection, key_id, 1, None, value).await?;
+    }
+
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client
+        .get("/configuration?prefix=systems.enabled")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    // Nested by key name
+    let response = client
+        .get("/configuration?prefix=systems&tree=true")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    let enabled = &body["children"]["systems"]["children"]["enabled"];
+
+    assert_eq!(body["entry"], serde_json::Value::Null);
+    assert_eq!(enabled["entry"], serde_json::Value::Null);
+    assert_eq!(
+        enabled["children"]["code"]["entry"]["key"]["name"],
+        "systems.enabled.code"
+    );
+    assert_eq!(
+        enabled["children"]["ticket"]["entry"]["itemsGlobal"][0]["value"]["asBoolean"],
+        false
+    );
+    assert_eq!(body["children"]["theme"], serde_json::Value::Null);
+
+    // Invalid prefixes are rejected
+    let response = client
+        .get("/configuration?prefix=systems/enabled")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_index_as_of() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let as_of = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": false }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    // The value is read as it was
+    let response = client
+        .get(format!("/configuration?asOf={as_of}"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["itemsGlobal"][0]["value"]["asBoolean"], true);
+
+    let response = client.get("/configuration").dispatch().await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["itemsGlobal"][0]["value"]["asBoolean"], false);
+
+    // Invalid timestamps are rejected
+    let response = client.get("/configuration?asOf=yesterday").dispatch().await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_diff() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let from = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let request_id = response
+        .headers()
+        .get_one("X-Request-Id")
+        .map(str::to_owned);
+
+    assert!(request_id.is_some());
+
+    // The end defaults to now
+    let response = client
+        .get(format!("/configuration/diff?from={from}"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["keys"], json!([]));
+    assert_eq!(body["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(body["entries"][0]["kind"], "added");
+    assert_eq!(body["entries"][0]["keyName"], "systems.enabled.code");
+    assert_eq!(body["entries"][0]["old"], serde_json::Value::Null);
+    assert_eq!(body["entries"][0]["new"]["asBoolean"], true);
+    assert!(body["entries"][0]["audit"]["clientQuery"].is_string());
+    assert_eq!(
+        body["entries"][0]["audit"]["requestId"].as_str(),
+        request_id.as_deref()
+    );
+
+    // The range must be ordered
+    let response = client
+        .get(format!(
+            "/configuration/diff?from=2100-01-01T00:00:00Z&to={from}"
+        ))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_rollback() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": false }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+
+    async_std::task::sleep(Duration::from_millis(10)).await;
+
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    // Only administrators may roll back
+    let response = client
+        .post("/configuration/rollback")
+        .json(&json!({ "timestamp": timestamp, "prefix": "systems" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client
+        .post("/configuration/rollback")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "timestamp": timestamp, "prefix": "systems" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(body["entries"][0]["kind"], "deactivated");
+    assert_eq!(body["entries"][0]["old"]["asBoolean"], true);
+    assert_eq!(body["entries"][1]["kind"], "added");
+    assert_eq!(body["entries"][1]["new"]["asBoolean"], false);
+    assert!(body["entries"][1]["audit"]["auditId"].is_i64());
+
+    let response = client
+        .get("/configuration/systems.enabled.code")
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], false);
+
+    // Exactly one audit point must be given
+    let response = client
+        .post("/configuration/rollback")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "auditId": 1, "timestamp": timestamp }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = client
+        .post("/configuration/rollback")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "timestamp": "yesterday" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_events() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        true,
+        false,
+        true,
+    )
+    .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client
+        .get("/configuration/events?prefix=not%20a%20prefix")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let mut response = client
+        .get("/configuration/events?prefix=systems")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::EventStream));
+
+    for (path, value) in [
+        ("/configuration/theme.darkMode", true),
+        ("/configuration/systems.enabled.code", false),
+    ] {
+        let update = client
+            .put(path)
+            .header(Header::new("Authorization", "Bearer admin"))
+            .json(&json!({ "items": [{ "asBoolean": value }] }))
+            .dispatch()
+            .await;
+
+        assert_eq!(update.status(), Status::Ok);
+    }
+
+    let mut body = Vec::new();
+    let mut buffer = [0; 4096];
+
+    while !body.ends_with(b"\n\n") {
+        let length = async_std::future::timeout(Duration::from_secs(5), response.read(&mut buffer))
+            .await
+            .expect("timed out waiting for an event")
+            .unwrap();
+
+        assert_ne!(length, 0, "event stream ended early");
+
+        body.extend_from_slice(&buffer[..length]);
+    }
+
+    let body = String::from_utf8(body).unwrap();
+
+    assert!(body.contains("event:change\n"), "{body}");
+
+    let data = body
+        .lines()
+        .find_map(|line| line.strip_prefix("data:"))
+        .expect("expected event data");
+
+    let event = serde_json::from_str::<serde_json::Value>(data).unwrap();
+
+    assert_eq!(event["kind"], "entry");
+    assert_eq!(event["keyName"], "systems.enabled.code");
+    assert_eq!(event["userId"], json!(null));
+    assert_eq!(
+        event["entry"]["itemsGlobal"][0]["value"]["asBoolean"],
+        json!(false)
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_cache() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let systems_enabled_code_id = insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client
+        .get("/configuration/systems.enabled.code")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap()["itemsGlobal"],
+        json!([])
+    );
+
+    // Writes from outside of the server invalidate the cache too
+    let connection = connect_db(DatabaseInstance::Unit)?;
+
+    insert_configuration_entry(&connection, systems_enabled_code_id, 1, None, "true").await?;
+
+    for _ in 0..50 {
+        let response = client
+            .get("/configuration/systems.enabled.code")
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+
+        let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+        if body["itemsGlobal"] != json!([]) {
+            assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], json!(true));
+
+            return Ok(());
+        }
+
+        async_std::task::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!("timed out waiting for the cache to be invalidated");
+}
+
+#[async_std::test]
+#[serial]
+async fn test_show() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, None, "false").await?;
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, Some("user"), "true").await?;
+
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client.get("/configuration/theme.darkMode").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["key"]["id"], theme_dark_mode_id);
+    assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], false);
+    assert_eq!(body["user"], serde_json::Value::Null);
+
+    // The requesting user's items are included
+    let response = client
+        .get("/configuration/theme.darkMode")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["user"]["items"][0]["value"]["asBoolean"], true);
+
+    // Unknown keys are not found
+    let response = client
+        .get("/configuration/theme.lightMode")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!({ "message": "configuration key not found for name \"theme.lightMode\"" })
+    );
+
+    // Static routes are not shadowed
+    let response = client.get("/configuration/keys").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response
+        .into_json::<serde_json::Value>()
+        .await
+        .unwrap()
+        .is_array());
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Only administrators may write global items
+    let response = client
+        .post("/configuration/systems.enabled.code")
+        .json(&json!({ "asBoolean": true }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer user"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .delete("/configuration/systems.enabled.code")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // Create
+    let response = client
+        .post("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "asBoolean": true }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["key"]["name"], "systems.enabled.code");
+    assert_eq!(body["itemsGlobal"].as_array().unwrap().len(), 1);
+    assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], true);
+
+    // The key does not allow multiple values
+    let response = client
+        .post("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "asBoolean": false }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    // Update
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": false }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["itemsGlobal"].as_array().unwrap().len(), 1);
+    assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], false);
+
+    // Values that do not match the key's type are rejected
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asString": "false" }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    // Unknown keys are rejected
+    let response = client
+        .post("/configuration/systems.enabled.unknown")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "asBoolean": true }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::NotFound);
+
+    // Delete
+    let response = client
+        .delete("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["itemsGlobal"].as_array().unwrap().len(), 0);
+
+    let response = client.get("/configuration").dispatch().await;
+
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!([])
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_secrets() -> Result<(), db::Error> {
+    std::env::set_var(
+        "CONFIGURATION_SECRET_KEY",
+        "6Wd4VsJ9ZfXMDg0Xx7mPv7cE4lTq8oRSa8R2ZjHfLkI=",
+    );
+    let connection = initialize_unit_database().await?;
+
+    let secret_id =
+        insert_configuration_type_reference(&connection, "secret", "An encrypted string").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "integration.github.token",
+        "The GitHub API token",
+        secret_id,
+        true,
+        false,
+        false,
+    )
+    .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client
+        .post("/configuration/integration.github.token")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "asSecret": "hunter2" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["itemsGlobal"][0]["value"]["asSecret"], json!(null));
+    assert_eq!(body["itemsGlobal"][0]["value"]["redacted"], json!(true));
+
+    // Redacted by default
+    let response = client.get("/configuration").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["itemsGlobal"][0]["value"]["asSecret"], json!(null));
+    assert_eq!(body[0]["itemsGlobal"][0]["value"]["redacted"], json!(true));
+
+    // Only administrators may see the value, whatever user id is claimed
+    let response = client
+        .get("/configuration?revealSecrets=true")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .get("/configuration?revealSecrets=true")
+        .header(Header::new("X-User-Id", "admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .get("/configuration?revealSecrets=true")
+        .header(Header::new("Authorization", "Bearer user"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .get("/configuration?revealSecrets=true")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["itemsGlobal"][0]["value"]["asSecret"], "hunter2");
+    assert_eq!(body[0]["itemsGlobal"][0]["value"]["redacted"], json!(false));
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_environment_overrides() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    // Values that cannot be used stop the server from launching
+    let configuration = Configuration {
+        configuration_overrides: BTreeMap::from([(
+            "SYSTEMS.ENABLED.CODE".to_owned(),
+            "maybe".to_owned(),
+        )]),
+        ..Configuration::new()?
+    };
+
+    let error = Client::tracked(server_routes::rocket(
+        connect_db(DatabaseInstance::Unit)?,
+        configuration,
+    ))
+    .await
+    .expect_err("expected the server not to launch");
+
+    assert!(matches!(
+        error.kind(),
+        ErrorKind::FailedFairings(fairings)
+            if fairings.iter().any(|fairing| fairing.name == "Configuration overrides")
+    ));
+
+    let configuration = Configuration {
+        configuration_overrides: BTreeMap::from([(
+            "SYSTEMS.ENABLED.CODE".to_owned(),
+            "false".to_owned(),
+        )]),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client.get("/configuration/effective").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["source"], "environment");
+    assert_eq!(body[0]["value"]["asBoolean"], false);
+
+    Ok(())
+}
@@ -20,11 +20,15 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use config_env::Configuration;
 use db::{
     seeding::{insert_configuration_key_reference, insert_configuration_type_reference},
     testing::initialize_unit_database,
 };
-use rocket::{http::Status, local::asynchronous::Client};
+use rocket::{
+    http::{Header, Status},
+    local::asynchronous::Client,
+};
 use serde_json::json;
 use serial_test::serial;
 
@@ -47,7 +51,7 @@ async fn test_index() -> Result<(), db::Error> {
     )
     .await?;
 
-    let client = Client::tracked(server_routes::rocket(connection))
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
         .await
         .expect("error creating Rocket instance");
 
@@ -69,9 +73,416 @@ async fn test_index() -> Result<(), db::Error> {
                 "optional": false,
                 "allowsMultiple": false,
                 "allowsUserOverride": false,
+                "constraints": {
+                    "minimum": null,
+                    "maximum": null,
+                    "pattern": null,
+                    "allowedValues": null
+                },
+                "rollout": null
             }
         ])
     );
 
     Ok(())
 }
+
+#[async_std::test]
+#[serial]
+async fn test_write() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let integer_id =
+        insert_configuration_type_reference(&connection, "integer", "A signed integer number")
+            .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Create
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({
+            "name": "systems.enabled.code",
+            "description": "Whether the Code system is enabled or not",
+            "typeId": boolean_id,
+            "optional": true,
+            "allowsMultiple": false,
+            "allowsUserOverride": false
+        }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+    let key_id = body["id"].as_i64().unwrap();
+
+    assert_eq!(body["type"]["name"], "boolean");
+
+    let response = client
+        .put("/configuration/systems.enabled.code")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    // The type cannot change while values would not parse
+    let response = client
+        .put(format!("/configuration/keys/{key_id}"))
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({
+            "description": "Whether the Code system is enabled or not",
+            "typeId": integer_id,
+            "optional": true,
+            "allowsMultiple": false,
+            "allowsUserOverride": false
+        }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    // Update
+    let response = client
+        .put(format!("/configuration/keys/{key_id}"))
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({
+            "description": "Whether the Code system is turned on",
+            "typeId": boolean_id,
+            "optional": false,
+            "allowsMultiple": false,
+            "allowsUserOverride": true
+        }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!({
+            "id": key_id,
+            "name": "systems.enabled.code",
+            "description": "Whether the Code system is turned on",
+            "type": {
+                "id": boolean_id,
+                "name": "boolean",
+                "description": "A true/false value"
+            },
+            "optional": false,
+            "allowsMultiple": false,
+            "allowsUserOverride": true,
+            "constraints": {
+                "minimum": null,
+                "maximum": null,
+                "pattern": null,
+                "allowedValues": null
+            },
+            "rollout": null
+        })
+    );
+
+    // Delete
+    let response = client
+        .delete(format!("/configuration/keys/{key_id}"))
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::NoContent);
+
+    let response = client.get("/configuration/keys").dispatch().await;
+
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!([])
+    );
+
+    let response = client.get("/configuration").dispatch().await;
+
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!([])
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_constraints() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let integer_id =
+        insert_configuration_type_reference(&connection, "integer", "A signed integer number")
+            .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Patterns are only allowed for strings
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({
+            "name": "limits.workers",
+            "description": "The number of worker threads",
+            "typeId": integer_id,
+            "optional": true,
+            "allowsMultiple": false,
+            "allowsUserOverride": false,
+            "constraints": { "pattern": "^[0-9]+$" }
+        }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    // Create
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({
+            "name": "limits.workers",
+            "description": "The number of worker threads",
+            "typeId": integer_id,
+            "optional": true,
+            "allowsMultiple": false,
+            "allowsUserOverride": false,
+            "constraints": {
+                "minimum": 1,
+                "maximum": 16,
+                "allowedValues": [{ "asInteger": 1 }, { "asInteger": 4 }, { "asInteger": 16 }]
+            }
+        }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["constraints"]["minimum"], 1.0);
+    assert_eq!(body["constraints"]["maximum"], 16.0);
+    assert_eq!(body["constraints"]["pattern"], serde_json::Value::Null);
+    assert_eq!(body["constraints"]["allowedValues"][1]["asInteger"], 4);
+
+    // Values are checked against the constraints
+    let response = client
+        .put("/configuration/limits.workers")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asInteger": 4 }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    for value in [0, 17, 5] {
+        let response = client
+            .put("/configuration/limits.workers")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .json(&json!({ "items": [{ "asInteger": value }] }))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_rollout() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let integer_id =
+        insert_configuration_type_reference(&connection, "integer", "A signed integer number")
+            .await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Rollout rules are only allowed for booleans
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({
+            "name": "limits.workers",
+            "description": "The number of worker threads",
+            "typeId": integer_id,
+            "optional": true,
+            "allowsMultiple": false,
+            "allowsUserOverride": false,
+            "rollout": { "percentage": 50 }
+        }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    // Create
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({
+            "name": "systems.enabled.deploy",
+            "description": "Whether the Deploy system is enabled or not",
+            "typeId": boolean_id,
+            "optional": true,
+            "allowsMultiple": false,
+            "allowsUserOverride": false,
+            "rollout": { "allowUserIds": ["user"] }
+        }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(
+        body["rollout"],
+        json!({
+            "percentage": 0.0,
+            "allowUserIds": ["user"],
+            "denyUserIds": [],
+            "groupIds": []
+        })
+    );
+
+    let response = client
+        .put("/configuration/systems.enabled.deploy")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    // The rule is evaluated for the requesting user
+    for (user_id, value) in [("user", true), ("other", false)] {
+        let response = client
+            .get("/configuration/effective")
+            .header(Header::new("X-User-Id", user_id))
+            .dispatch()
+            .await;
+
+        let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+        assert_eq!(body[0]["source"], "rollout");
+        assert_eq!(body[0]["value"]["asBoolean"], value);
+    }
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_write_admin() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let key = json!({
+        "name": "systems.enabled.code",
+        "description": "Whether the Code system is enabled or not",
+        "typeId": boolean_id,
+        "optional": true,
+        "allowsMultiple": false,
+        "allowsUserOverride": false
+    });
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Without a token
+    let response = client
+        .post("/configuration/keys")
+        .json(&key)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap()["message"],
+        "an admin token is required in the Authorization header"
+    );
+
+    // With the wrong token
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer user"))
+        .json(&key)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    // Reads do not need a token
+    let response = client.get("/configuration/keys").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!([])
+    );
+
+    // Without a token configured, nobody is an admin
+    let connection = initialize_unit_database().await?;
+
+    let client = Client::tracked(server_routes::rocket(
+        connection,
+        Configuration {
+            admin_api_token: None,
+            ..Configuration::new()?
+        },
+    ))
+    .await
+    .expect("error creating Rocket instance");
+
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&key)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    Ok(())
+}
@@ -20,8 +20,12 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use config_env::Configuration;
 use db::{seeding::insert_configuration_type_reference, testing::initialize_unit_database};
-use rocket::{http::Status, local::asynchronous::Client};
+use rocket::{
+    http::{Header, Status},
+    local::asynchronous::Client,
+};
 use serde_json::json;
 use serial_test::serial;
 
@@ -37,7 +41,7 @@ async fn test_index() -> Result<(), db::Error> {
         insert_configuration_type_reference(&connection, "integer", "A signed integer number")
             .await?;
 
-    let client = Client::tracked(server_routes::rocket(connection))
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
         .await
         .expect("error creating Rocket instance");
 
@@ -62,3 +66,96 @@ async fn test_index() -> Result<(), db::Error> {
 
     Ok(())
 }
+
+#[async_std::test]
+#[serial]
+async fn test_write() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Create
+    let response = client
+        .post("/configuration/types")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "name": "boolean", "description": "A true/false value" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+    let boolean_id = body["id"].as_i64().unwrap();
+
+    assert_eq!(body["name"], "boolean");
+
+    // Invalid names and duplicates are rejected
+    let response = client
+        .post("/configuration/types")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "name": "not valid", "description": "A value" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = client
+        .post("/configuration/types")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "name": "boolean", "description": "A value" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Conflict);
+
+    // Update
+    let response = client
+        .put(format!("/configuration/types/{boolean_id}"))
+        .header(Header::new("Authorization", "Bearer admin"))
+        .json(&json!({ "description": "A true or false value" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!({
+            "id": boolean_id,
+            "name": "boolean",
+            "description": "A true or false value"
+        })
+    );
+
+    // Delete
+    let response = client
+        .delete(format!("/configuration/types/{boolean_id}"))
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::NoContent);
+
+    let response = client
+        .delete(format!("/configuration/types/{boolean_id}"))
+        .header(Header::new("Authorization", "Bearer admin"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client.get("/configuration/types").dispatch().await;
+
+    assert_eq!(
+        response.into_json::<serde_json::Value>().await.unwrap(),
+        json!([])
+    );
+
+    Ok(())
+}
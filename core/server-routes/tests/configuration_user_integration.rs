@@ -0,0 +1,213 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config_env::Configuration;
+use db::{
+    seeding::{
+        insert_configuration_entry, insert_configuration_key_reference,
+        insert_configuration_type_reference,
+    },
+    testing::initialize_unit_database,
+};
+use rocket::{
+    http::{Header, Status},
+    local::asynchronous::Client,
+};
+use serde_json::json;
+use serial_test::serial;
+
+#[async_std::test]
+#[serial]
+async fn test_write() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_key_reference(
+        &connection,
+        "systems.enabled.code",
+        "Whether the Code system is enabled or not",
+        boolean_id,
+        false,
+        false,
+        false,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, None, "false").await?;
+
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Requests without a user are rejected
+    let response = client
+        .put("/configuration/theme.darkMode/user")
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // Keys that do not allow user overrides are rejected
+    let response = client
+        .put("/configuration/systems.enabled.code/user")
+        .header(Header::new("X-User-Id", "user"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+
+    // Create override
+    let response = client
+        .put("/configuration/theme.darkMode/user")
+        .header(Header::new("X-User-Id", "user"))
+        .json(&json!({ "items": [{ "asBoolean": true }] }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], false);
+    assert_eq!(body["user"]["userId"], "user");
+    assert_eq!(body["user"]["items"][0]["value"]["asBoolean"], true);
+
+    // Read override
+    let response = client
+        .get("/configuration/theme.darkMode/user")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["user"]["items"][0]["value"]["asBoolean"], true);
+
+    let response = client
+        .get("/configuration")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["user"]["userId"], "user");
+
+    // Other users do not see the override
+    let response = client
+        .get("/configuration/theme.darkMode/user")
+        .header(Header::new("X-User-Id", "other"))
+        .dispatch()
+        .await;
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["user"], serde_json::Value::Null);
+
+    // Clear override
+    let response = client
+        .delete("/configuration/theme.darkMode/user")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["itemsGlobal"][0]["value"]["asBoolean"], false);
+    assert_eq!(body["user"], serde_json::Value::Null);
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_effective() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let boolean_id =
+        insert_configuration_type_reference(&connection, "boolean", "A true/false value").await?;
+
+    let theme_dark_mode_id = insert_configuration_key_reference(
+        &connection,
+        "theme.darkMode",
+        "Whether or not to use dark mode",
+        boolean_id,
+        false,
+        false,
+        true,
+    )
+    .await?;
+
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, None, "false").await?;
+    insert_configuration_entry(&connection, theme_dark_mode_id, 1, Some("user"), "true").await?;
+
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Without a user the global value is effective
+    let response = client.get("/configuration/effective").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["key"]["name"], "theme.darkMode");
+    assert_eq!(body[0]["source"], "global");
+    assert_eq!(body[0]["value"]["asBoolean"], false);
+
+    // The override is effective for its user
+    let response = client
+        .get("/configuration/effective")
+        .header(Header::new("X-User-Id", "user"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body[0]["source"], "user");
+    assert_eq!(body[0]["value"]["asBoolean"], true);
+    assert_eq!(body[0]["values"].as_array().unwrap().len(), 1);
+
+    Ok(())
+}
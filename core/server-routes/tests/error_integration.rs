@@ -0,0 +1,172 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config_env::Configuration;
+use db::testing::initialize_unit_database;
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::asynchronous::Client,
+};
+use serial_test::serial;
+
+#[async_std::test]
+#[serial]
+async fn test_catchers() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Unknown routes
+    let response = client.get("/missing").dispatch().await;
+
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(body["message"], "no route matches GET /missing");
+
+    // Bodies that do not match the request schema
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .header(ContentType::JSON)
+        .body(r#"{ "name": "systems.enabled.code" }"#)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert!(body["message"].is_string());
+
+    // Errors from handlers keep their own message
+    let response = client
+        .get("/configuration/systems.missing")
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::NotFound);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(
+        body["message"],
+        "configuration key not found for name \"systems.missing\""
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_validation() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Key names that do not match the pattern
+    let response = client
+        .post("/configuration/keys")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .header(ContentType::JSON)
+        .body(
+            r#"{
+                "name": "systems enabled",
+                "description": "",
+                "typeId": 1,
+                "optional": false,
+                "allowsMultiple": false,
+                "allowsUserOverride": false,
+                "rollout": { "percentage": 150 }
+            }"#,
+        )
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert!(body["message"].is_string());
+    assert_eq!(
+        body["errors"],
+        serde_json::json!([
+            { "field": "description", "rule": "length" },
+            { "field": "name", "rule": "regex" },
+            { "field": "rollout.percentage", "rule": "range" }
+        ])
+    );
+
+    // Type names that do not match the pattern
+    let response = client
+        .post("/configuration/types")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .header(ContentType::JSON)
+        .body(r#"{ "name": "not.a.type", "description": "Not a type" }"#)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert_eq!(
+        body["errors"],
+        serde_json::json!([{ "field": "name", "rule": "regex" }])
+    );
+
+    // Bodies that cannot be parsed have no field errors
+    let response = client
+        .put("/configuration/types/1")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .header(ContentType::JSON)
+        .body(r#"{ "description": 1 }"#)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let body = response.into_json::<serde_json::Value>().await.unwrap();
+
+    assert!(body["message"].as_str().unwrap().contains("invalid type"));
+    assert!(body.get("errors").is_none());
+
+    Ok(())
+}
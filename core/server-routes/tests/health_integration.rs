@@ -0,0 +1,139 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config_env::Configuration;
+use db::{connect_db, testing::initialize_unit_database, DatabaseInstance};
+use domain_api::health::{
+    HealthMigrationsResponse, HealthReadinessResponse, HealthResponse, HealthStatus,
+};
+use rocket::{http::Status, local::asynchronous::Client};
+use sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use serial_test::serial;
+
+/// Run a statement against the unit test database.
+async fn execute(connection: &DatabaseConnection, sql: String) -> Result<(), db::Error> {
+    connection
+        .execute(Statement::from_string(
+            connection.get_database_backend(),
+            sql,
+        ))
+        .await?;
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_health() -> Result<(), db::Error> {
+    let client = Client::tracked(server_routes::rocket(
+        initialize_unit_database().await?,
+        Configuration::new()?,
+    ))
+    .await
+    .expect("error creating Rocket instance");
+
+    // Liveness
+    let response = client.get("/health/live").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<HealthResponse>().await,
+        Some(HealthResponse {
+            status: HealthStatus::Up
+        })
+    );
+
+    // Readiness with an up to date schema
+    let response = client.get("/health/ready").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let readiness = response
+        .into_json::<HealthReadinessResponse>()
+        .await
+        .unwrap();
+
+    assert_eq!(readiness.status, HealthStatus::Up);
+    assert_eq!(readiness.database, HealthStatus::Up);
+
+    let migrations = readiness.migrations.unwrap();
+
+    assert!(migrations.pending.is_empty());
+    assert!(migrations.up_to_date);
+
+    // Readiness with the last migration marked as not applied
+    let connection = connect_db(DatabaseInstance::Unit)?;
+
+    let last = migrations.applied.last().unwrap().clone();
+
+    let applied_at = connection
+        .query_one(Statement::from_string(
+            connection.get_database_backend(),
+            format!("SELECT applied_at FROM seaql_migrations WHERE version = '{last}'"),
+        ))
+        .await?
+        .unwrap()
+        .try_get::<i64>("", "applied_at")?;
+
+    execute(
+        &connection,
+        format!("DELETE FROM seaql_migrations WHERE version = '{last}'"),
+    )
+    .await?;
+
+    let ready_response = client.get("/health/ready").dispatch().await;
+    let ready_status = ready_response.status();
+    let readiness = ready_response.into_json::<HealthReadinessResponse>().await;
+
+    let migrations_response = client.get("/health/migrations").dispatch().await;
+    let migrations_status = migrations_response.status();
+    let behind = migrations_response
+        .into_json::<HealthMigrationsResponse>()
+        .await;
+
+    // Restore the row before checking anything so that a failure does not
+    // leave the schema looking out of date
+    execute(
+        &connection,
+        format!(
+            "INSERT INTO seaql_migrations (version, applied_at) VALUES ('{last}', {applied_at})"
+        ),
+    )
+    .await?;
+
+    assert_eq!(ready_status, Status::ServiceUnavailable);
+
+    let readiness = readiness.unwrap();
+
+    assert_eq!(readiness.status, HealthStatus::Down);
+    assert_eq!(readiness.database, HealthStatus::Up);
+    assert_eq!(readiness.migrations.unwrap().pending, vec![last.clone()]);
+
+    assert_eq!(migrations_status, Status::Ok);
+
+    let behind = behind.unwrap();
+
+    assert_eq!(behind.pending, vec![last]);
+    assert!(!behind.up_to_date);
+
+    Ok(())
+}
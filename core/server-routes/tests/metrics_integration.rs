@@ -0,0 +1,85 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config_env::Configuration;
+use db::testing::initialize_unit_database;
+use rocket::{http::Status, local::asynchronous::Client};
+use serial_test::serial;
+
+#[async_std::test]
+#[serial]
+async fn test_metrics() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
+        .await
+        .expect("error creating Rocket instance");
+
+    assert_eq!(
+        client.get("/configuration/types").dispatch().await.status(),
+        Status::Ok
+    );
+    assert_eq!(
+        client
+            .get("/configuration/systems.missing")
+            .dispatch()
+            .await
+            .status(),
+        Status::NotFound
+    );
+    assert_eq!(
+        client.get("/missing").dispatch().await.status(),
+        Status::NotFound
+    );
+
+    let response = client.get("/metrics").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response
+            .content_type()
+            .map(|content_type| content_type.to_string()),
+        Some("text/plain; version=0.0.4; charset=utf-8".to_owned())
+    );
+
+    let body = response.into_string().await.unwrap();
+
+    // Requests are labelled by route pattern and status
+    assert!(body.contains(
+        r#"prelude_http_requests_total{method="GET",route="/configuration/types",status="200"} 1"#
+    ));
+    assert!(body.contains(
+        r#"prelude_http_requests_total{method="GET",route="/configuration/<name>",status="404"} 1"#
+    ));
+    assert!(body
+        .contains(r#"prelude_http_requests_total{method="GET",route="unmatched",status="404"} 1"#));
+    assert!(body.contains(
+        r#"prelude_http_request_duration_seconds_count{method="GET",route="/configuration/types"} 1"#
+    ));
+
+    // Queries and the connection pool
+    assert!(body.contains(r#"prelude_db_query_duration_seconds_count{operation="select"}"#));
+    assert!(body.contains(r#"prelude_db_pool_connections{state="active"}"#));
+    assert!(body.contains(r#"prelude_db_pool_connections{state="idle"}"#));
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// MIT License
+//
+// Copyright (c) 2023 Sophie Katz
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use config_env::Configuration;
+use db::{entities::configuration_type_reference_audit, testing::initialize_unit_database};
+use rocket::{
+    http::{Header, Status},
+    local::asynchronous::Client,
+};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde_json::json;
+use serial_test::serial;
+use uuid::Uuid;
+
+#[async_std::test]
+#[serial]
+async fn test_request_id() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let client = Client::tracked(server_routes::rocket(connection, Configuration::new()?))
+        .await
+        .expect("error creating Rocket instance");
+
+    // Generated when missing
+    let response = client.get("/health/live").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let request_id = response
+        .headers()
+        .get_one("X-Request-Id")
+        .expect("response has a request id");
+
+    assert!(Uuid::parse_str(request_id).is_ok());
+
+    // Echoed when given
+    let response = client
+        .get("/health/live")
+        .header(Header::new("X-Request-Id", "test-request-id"))
+        .dispatch()
+        .await;
+
+    assert_eq!(
+        response.headers().get_one("X-Request-Id"),
+        Some("test-request-id")
+    );
+
+    // Replaced when invalid
+    let response = client
+        .get("/health/live")
+        .header(Header::new("X-Request-Id", "has spaces"))
+        .dispatch()
+        .await;
+
+    let request_id = response
+        .headers()
+        .get_one("X-Request-Id")
+        .expect("response has a request id");
+
+    assert!(Uuid::parse_str(request_id).is_ok());
+
+    // Returned with errors too
+    let response = client
+        .get("/missing")
+        .header(Header::new("X-Request-Id", "test-request-id"))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(
+        response.headers().get_one("X-Request-Id"),
+        Some("test-request-id")
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+#[serial]
+async fn test_request_id_audit() -> Result<(), db::Error> {
+    let connection = initialize_unit_database().await?;
+
+    let configuration = Configuration {
+        admin_api_token: Some("admin".to_owned()),
+        ..Configuration::new()?
+    };
+
+    let client = Client::tracked(server_routes::rocket(connection, configuration))
+        .await
+        .expect("error creating Rocket instance");
+
+    let response = client
+        .post("/configuration/types")
+        .header(Header::new("Authorization", "Bearer admin"))
+        .header(Header::new("X-Request-Id", "test-request-id"))
+        .json(&json!({ "name": "boolean", "description": "A true/false value" }))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let connection = client
+        .rocket()
+        .state::<DatabaseConnection>()
+        .expect("database connection is managed");
+
+    let audit_rows = configuration_type_reference_audit::Entity::find()
+        .all(connection)
+        .await?;
+
+    assert_eq!(audit_rows.len(), 1);
+    assert_eq!(
+        audit_rows[0].audit_request_id,
+        Some("test-request-id".to_owned())
+    );
+
+    Ok(())
+}
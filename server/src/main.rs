@@ -25,11 +25,18 @@
 #[macro_use]
 extern crate rocket;
 
+use config_env::Configuration;
 use db::{connect_db, DatabaseInstance};
+use server_routes::logging::initialize_logging;
 
 #[launch]
 fn rocket() -> _ {
+    let configuration = Configuration::new().expect("unable to load configuration");
+
+    initialize_logging(&configuration).expect("unable to initialize logging");
+
     server_routes::rocket(
         connect_db(DatabaseInstance::Development).expect("unable to connect to database"),
+        configuration,
     )
 }
@@ -21,17 +21,77 @@
 // SOFTWARE.
 
 //! A utility program to seed the database with minimal usable data.
+//!
+//! It can also export the configuration to a declarative document and apply
+//! such a document back to the database:
+//!
+//! ```bash
+//! db-seed
+//! db-seed export <path>
+//! db-seed apply [--dry-run] <path>
+//! ```
+//!
+//! The format of the document is picked by the extension of the path, which
+//! can be `.yaml`, `.yml`, `.toml` or `.json`.
 
 use db::{
     connect_db,
+    queries::configuration::document::{
+        apply_configuration_document, export_configuration_document, ConfigurationDocument,
+    },
     seeding::{
-        insert_configuration_entry, insert_configuration_key_reference,
-        insert_configuration_type_reference,
+        find_configuration_type_reference, insert_configuration_entry,
+        insert_configuration_key_reference,
     },
     DatabaseInstance,
 };
 use futures::executor;
 use sea_orm::DatabaseConnection;
+use std::{env, fs, path::Path, process};
+
+/// The file formats that configuration documents can be written in
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum DocumentFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl DocumentFormat {
+    /// Gets the format for the extension of a path, if it is supported.
+    fn of(path: &Path) -> Option<DocumentFormat> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(DocumentFormat::Yaml),
+            "toml" => Some(DocumentFormat::Toml),
+            "json" => Some(DocumentFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Parse a configuration document.
+    fn parse(self, text: &str) -> Result<ConfigurationDocument, String> {
+        match self {
+            DocumentFormat::Yaml => serde_yaml::from_str(text).map_err(|error| error.to_string()),
+            DocumentFormat::Toml => toml::from_str(text).map_err(|error| error.to_string()),
+            DocumentFormat::Json => serde_json::from_str(text).map_err(|error| error.to_string()),
+        }
+    }
+
+    /// Format a configuration document.
+    fn format(self, document: &ConfigurationDocument) -> Result<String, String> {
+        match self {
+            DocumentFormat::Yaml => {
+                serde_yaml::to_string(document).map_err(|error| error.to_string())
+            }
+            DocumentFormat::Toml => {
+                toml::to_string_pretty(document).map_err(|error| error.to_string())
+            }
+            DocumentFormat::Json => {
+                serde_json::to_string_pretty(document).map_err(|error| error.to_string())
+            }
+        }
+    }
+}
 
 struct ConfigurationTypeReferenceIds {
     boolean: i32,
@@ -41,6 +101,14 @@ struct ConfigurationTypeReferenceIds {
     float: i32,
     #[allow(dead_code)]
     string: i32,
+    duration: i32,
+    url: i32,
+    #[allow(dead_code)]
+    datetime: i32,
+    #[allow(dead_code)]
+    json: i32,
+    #[allow(dead_code)]
+    r#enum: i32,
 }
 
 struct ConfigurationKeyReferenceIds {
@@ -49,23 +117,25 @@ struct ConfigurationKeyReferenceIds {
     system_enabled_deploy: i32,
     system_enabled_document: i32,
     system_enabled_ticket: i32,
+    integration_webhook_timeout: i32,
+    #[allow(dead_code)]
+    integration_webhook_url: i32,
 }
 
 async fn seed_configuration_type_reference(
     connection: &DatabaseConnection,
 ) -> Result<ConfigurationTypeReferenceIds, db::Error> {
+    // The types themselves are inserted by migrations
     Ok(ConfigurationTypeReferenceIds {
-        boolean: insert_configuration_type_reference(connection, "boolean", "A true/false value")
-            .await?,
-        integer: insert_configuration_type_reference(
-            connection,
-            "integer",
-            "A signed integer number",
-        )
-        .await?,
-        float: insert_configuration_type_reference(connection, "float", "A floating-point number")
-            .await?,
-        string: insert_configuration_type_reference(connection, "string", "A string value").await?,
+        boolean: find_configuration_type_reference(connection, "boolean").await?,
+        integer: find_configuration_type_reference(connection, "integer").await?,
+        float: find_configuration_type_reference(connection, "float").await?,
+        string: find_configuration_type_reference(connection, "string").await?,
+        duration: find_configuration_type_reference(connection, "duration").await?,
+        url: find_configuration_type_reference(connection, "url").await?,
+        datetime: find_configuration_type_reference(connection, "datetime").await?,
+        json: find_configuration_type_reference(connection, "json").await?,
+        r#enum: find_configuration_type_reference(connection, "enum").await?,
     })
 }
 
@@ -124,6 +194,26 @@ async fn seed_configuration_key_reference(
             false,
         )
         .await?,
+        integration_webhook_timeout: insert_configuration_key_reference(
+            connection,
+            "integration.webhook.timeout",
+            "How long to wait for webhook deliveries before giving up",
+            configuration_type_reference_ids.duration,
+            false,
+            false,
+            false,
+        )
+        .await?,
+        integration_webhook_url: insert_configuration_key_reference(
+            connection,
+            "integration.webhook.url",
+            "The URL that webhook events are delivered to",
+            configuration_type_reference_ids.url,
+            true,
+            false,
+            false,
+        )
+        .await?,
     })
 }
 
@@ -176,6 +266,15 @@ async fn seed_configuration_entries(
     )
     .await?;
 
+    insert_configuration_entry(
+        connection,
+        configuration_key_reference_ids.integration_webhook_timeout,
+        1,
+        None,
+        "30s",
+    )
+    .await?;
+
     Ok(())
 }
 
@@ -190,8 +289,59 @@ async fn seed(connection: &DatabaseConnection) -> Result<(), db::Error> {
     Ok(())
 }
 
+/// Write the configuration in the database to a document.
+async fn export(connection: &DatabaseConnection, path: &Path) -> Result<(), String> {
+    let format = DocumentFormat::of(path)
+        .ok_or_else(|| format!("unsupported document format for {}", path.display()))?;
+
+    let document = export_configuration_document(connection)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    fs::write(path, format.format(&document)?).map_err(|error| error.to_string())
+}
+
+/// Make the configuration in the database match a document, printing the
+/// planned changes.
+async fn apply(connection: &DatabaseConnection, path: &Path, dry_run: bool) -> Result<(), String> {
+    let format = DocumentFormat::of(path)
+        .ok_or_else(|| format!("unsupported document format for {}", path.display()))?;
+
+    let document = format.parse(&fs::read_to_string(path).map_err(|error| error.to_string())?)?;
+
+    let plan = apply_configuration_document(connection, &document, dry_run)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    for change in &plan {
+        println!("{change}");
+    }
+
+    match (plan.is_empty(), dry_run) {
+        (true, _) => println!("nothing to change"),
+        (false, true) => println!("dry run, {} changes not applied", plan.len()),
+        (false, false) => println!("{} changes applied", plan.len()),
+    }
+
+    Ok(())
+}
+
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<&str>>();
+
     let db = connect_db(DatabaseInstance::Development).expect("unable to connect to database");
 
-    executor::block_on(seed(&db)).expect("error while seeding");
+    let result = match args.as_slice() {
+        [] => executor::block_on(seed(&db)).map_err(|error| error.to_string()),
+        ["export", path] => executor::block_on(export(&db, Path::new(path))),
+        ["apply", path] => executor::block_on(apply(&db, Path::new(path), false)),
+        ["apply", "--dry-run", path] => executor::block_on(apply(&db, Path::new(path), true)),
+        _ => Err("usage: db-seed [export <path> | apply [--dry-run] <path>]".to_owned()),
+    };
+
+    if let Err(message) = result {
+        eprintln!("error: {message}");
+        process::exit(1);
+    }
 }